@@ -0,0 +1,26 @@
+#version 150
+
+uniform sampler2D source;
+uniform vec2 texel;
+uniform float threshold;
+uniform float intensity;
+
+in vec2 uv;
+out vec4 color;
+
+const int RADIUS = 3;
+
+void main() {
+    vec4 base = texture(source, uv);
+
+    vec3 glow = vec3(0.0);
+    for (int x = -RADIUS; x <= RADIUS; x++) {
+        for (int y = -RADIUS; y <= RADIUS; y++) {
+            vec3 s = texture(source, uv + vec2(x, y) * texel * 2.0).rgb;
+            glow += max(s - vec3(threshold), vec3(0.0));
+        }
+    }
+    glow /= float((RADIUS * 2 + 1) * (RADIUS * 2 + 1));
+
+    color = vec4(base.rgb + glow * intensity, base.a);
+}
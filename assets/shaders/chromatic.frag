@@ -0,0 +1,18 @@
+#version 150
+
+uniform sampler2D source;
+uniform vec2 texel;
+uniform float amount;
+
+in vec2 uv;
+out vec4 color;
+
+void main() {
+    // Shift red and blue apart, more strongly toward the screen edges.
+    vec2 offset = (uv - 0.5) * texel * amount;
+    float r = texture(source, uv + offset).r;
+    vec4 g = texture(source, uv);
+    float b = texture(source, uv - offset).b;
+
+    color = vec4(r, g.g, b, g.a);
+}
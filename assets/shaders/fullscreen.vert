@@ -0,0 +1,9 @@
+#version 150
+
+in vec2 pos;
+out vec2 uv;
+
+void main() {
+    uv = pos * 0.5 + 0.5;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use audio::{AudioError, MusicCue};
+
+/// A sound loaded by a backend, ready to be played any number of times.
+pub type SoundHandle = usize;
+/// One playing instance of a sound or music track.
+pub type VoiceId = u64;
+
+/// Plays decoded audio. `Audio` handles voice limits, channels and fades
+/// on top of this, so backends only need to start, stop and adjust voices.
+pub trait AudioBackend {
+    fn load_sound(&mut self, path: &Path) -> Result<SoundHandle, AudioError>;
+    fn play_sound(&mut self, sound: SoundHandle, volume: f32) -> VoiceId;
+    /// Starts streaming a music track, repeating its loop section if it
+    /// has one.
+    fn play_music(&mut self, cue: &MusicCue, volume: f32) -> Result<VoiceId, AudioError>;
+    fn set_volume(&mut self, voice: VoiceId, volume: f32);
+    fn stop(&mut self, voice: VoiceId);
+    fn is_playing(&self, voice: VoiceId) -> bool;
+}
+
+/// Backend that plays nothing, for running without an audio device.
+///
+/// Voices count as playing until they are stopped, so voice limits and
+/// fades behave the same as with a real device.
+#[derive(Debug, Clone, Default)]
+pub struct NullBackend {
+    sounds: usize,
+    next_voice: VoiceId,
+    playing: HashSet<VoiceId>,
+}
+
+impl NullBackend {
+    pub fn new() -> NullBackend {
+        NullBackend {
+            sounds: 0,
+            next_voice: 0,
+            playing: HashSet::new(),
+        }
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.playing.len()
+    }
+
+    fn start_voice(&mut self) -> VoiceId {
+        let voice = self.next_voice;
+        self.next_voice += 1;
+        self.playing.insert(voice);
+        voice
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn load_sound(&mut self, _path: &Path) -> Result<SoundHandle, AudioError> {
+        self.sounds += 1;
+        Ok(self.sounds - 1)
+    }
+
+    fn play_sound(&mut self, _sound: SoundHandle, _volume: f32) -> VoiceId {
+        self.start_voice()
+    }
+
+    fn play_music(&mut self, _cue: &MusicCue, _volume: f32) -> Result<VoiceId, AudioError> {
+        Ok(self.start_voice())
+    }
+
+    fn set_volume(&mut self, _voice: VoiceId, _volume: f32) {}
+
+    fn stop(&mut self, voice: VoiceId) {
+        self.playing.remove(&voice);
+    }
+
+    fn is_playing(&self, voice: VoiceId) -> bool {
+        self.playing.contains(&voice)
+    }
+}
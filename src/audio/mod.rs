@@ -0,0 +1,463 @@
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use data::{self, DataError};
+
+pub mod backend;
+#[cfg(feature = "rodio")]
+pub mod rodio_backend;
+
+pub use self::backend::{AudioBackend, NullBackend, SoundHandle, VoiceId};
+
+pub const SOUNDS_PATH: &str = "assets/sounds.toml";
+
+/// Sound effects triggered by gameplay. Each is configured under its
+/// `name()` in `assets/sounds.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sfx {
+    Shot,
+    Hit,
+    Explosion,
+    PlayerHit,
+    Pickup,
+    Graze,
+}
+
+/// Volume channels, each scaled separately by the player's settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Music,
+    Sfx,
+}
+
+/// A music track, optionally repeating the section between `loop_start`
+/// and `loop_end` (in seconds) forever once it is reached.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MusicCue {
+    pub path: String,
+    #[serde(default)]
+    pub loop_start: Option<f64>,
+    #[serde(default)]
+    pub loop_end: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SoundDef {
+    pub path: String,
+    #[serde(default = "default_max_voices")]
+    pub max_voices: usize,
+    #[serde(default = "default_sound_volume")]
+    pub volume: f32,
+}
+
+#[derive(Debug)]
+pub enum AudioError {
+    Io(io::Error),
+    Data(DataError),
+    Decode(String),
+    NoDevice,
+}
+
+struct LoadedSound {
+    def: SoundDef,
+    handle: SoundHandle,
+    /// Playing voices, oldest first.
+    voices: VecDeque<VoiceId>,
+}
+
+struct MusicVoice {
+    voice: VoiceId,
+    level: f64,
+    target: f64,
+    /// Change in `level` per second.
+    rate: f64,
+}
+
+/// Plays sound effects and music through an `AudioBackend`.
+pub struct Audio {
+    backend: Box<AudioBackend>,
+    sounds: HashMap<Sfx, LoadedSound>,
+    music: Vec<MusicVoice>,
+    volumes: HashMap<Channel, f32>,
+}
+
+fn default_max_voices() -> usize {
+    4
+}
+
+fn default_sound_volume() -> f32 {
+    1.0
+}
+
+/// The best backend available: the system's audio device when built with
+/// the `rodio` feature and a device exists, otherwise `NullBackend`.
+pub fn default_backend() -> Box<AudioBackend> {
+    #[cfg(feature = "rodio")]
+    {
+        match rodio_backend::RodioBackend::new() {
+            Ok(backend) => return Box::new(backend),
+            Err(e) => println!("Audio disabled: {}", e),
+        }
+    }
+    Box::new(NullBackend::new())
+}
+
+impl MusicCue {
+    /// Checks that the loop section, if there is one, starts inside the
+    /// track and ends after it starts.
+    pub fn validate(&self) -> Result<(), DataError> {
+        let problem = match (self.loop_start, self.loop_end) {
+            (Some(start), _) if !(start >= 0.0 && start.is_finite()) => {
+                format!("loop_start must be a time in the track, not {}", start)
+            }
+            (Some(start), Some(end)) if !(end > start && end.is_finite()) => {
+                format!("loop_end ({}) must come after loop_start ({})", end, start)
+            }
+            (None, Some(_)) => "loop_end is set without loop_start".to_string(),
+            _ => return Ok(()),
+        };
+        Err(DataError::Invalid(format!("{}: {}", self.path, problem)))
+    }
+}
+
+impl Sfx {
+    pub fn all() -> &'static [Sfx] {
+        &[
+            Sfx::Shot,
+            Sfx::Hit,
+            Sfx::Explosion,
+            Sfx::PlayerHit,
+            Sfx::Pickup,
+            Sfx::Graze,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Sfx::Shot => "shot",
+            Sfx::Hit => "hit",
+            Sfx::Explosion => "explosion",
+            Sfx::PlayerHit => "player_hit",
+            Sfx::Pickup => "pickup",
+            Sfx::Graze => "graze",
+        }
+    }
+}
+
+impl Audio {
+    pub fn new(backend: Box<AudioBackend>) -> Audio {
+        let mut volumes = HashMap::new();
+        volumes.insert(Channel::Music, 1.0);
+        volumes.insert(Channel::Sfx, 1.0);
+
+        Audio {
+            backend,
+            sounds: HashMap::new(),
+            music: Vec::new(),
+            volumes,
+        }
+    }
+
+    /// Loads every sound effect listed in the file at `path`. Sounds that
+    /// fail to load are reported and left silent.
+    pub fn load_sounds<P: AsRef<Path>>(&mut self, path: P) -> Result<(), AudioError> {
+        let mut defs: HashMap<String, SoundDef> = data::load_toml(path)?;
+
+        for &sfx in Sfx::all() {
+            let def = match defs.remove(sfx.name()) {
+                Some(def) => def,
+                None => continue,
+            };
+            match self.backend.load_sound(Path::new(&def.path)) {
+                Ok(handle) => {
+                    self.sounds.insert(
+                        sfx,
+                        LoadedSound {
+                            def,
+                            handle,
+                            voices: VecDeque::new(),
+                        },
+                    );
+                }
+                Err(e) => println!("Unable to load sound {}: {}", sfx.name(), e),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn volume(&self, channel: Channel) -> f32 {
+        self.volumes.get(&channel).cloned().unwrap_or(1.0)
+    }
+
+    pub fn set_volume(&mut self, channel: Channel, volume: f32) {
+        self.volumes.insert(channel, volume.max(0.0).min(1.0));
+    }
+
+    /// Plays `sfx`, cutting off its oldest voice if it's already playing
+    /// as many times as it's allowed.
+    pub fn play(&mut self, sfx: Sfx) {
+        let volume = self.volume(Channel::Sfx);
+        let backend = &mut self.backend;
+        let sound = match self.sounds.get_mut(&sfx) {
+            Some(sound) => sound,
+            None => return,
+        };
+
+        while sound.voices.len() >= sound.def.max_voices.max(1) {
+            if let Some(oldest) = sound.voices.pop_front() {
+                backend.stop(oldest);
+            }
+        }
+
+        let voice = backend.play_sound(sound.handle, sound.def.volume * volume);
+        sound.voices.push_back(voice);
+    }
+
+    /// Fades out whatever is playing and fades `cue` in over `fade`
+    /// seconds.
+    pub fn play_music(&mut self, cue: &MusicCue, fade: f64) {
+        if let Err(e) = cue.validate() {
+            println!("Unable to play {}: {}", cue.path, e);
+            return;
+        }
+        self.stop_music(fade);
+
+        let level = if fade > 0.0 { 0.0 } else { 1.0 };
+        let volume = level as f32 * self.volume(Channel::Music);
+        match self.backend.play_music(cue, volume) {
+            Ok(voice) => self.music.push(MusicVoice {
+                voice,
+                level,
+                target: 1.0,
+                rate: fade_rate(fade),
+            }),
+            Err(e) => println!("Unable to play {}: {}", cue.path, e),
+        }
+    }
+
+    pub fn stop_music(&mut self, fade: f64) {
+        for track in &mut self.music {
+            track.target = 0.0;
+            track.rate = fade_rate(fade);
+            if fade <= 0.0 {
+                track.level = 0.0;
+            }
+        }
+    }
+
+    /// Advances fades and forgets voices that have finished.
+    pub fn update(&mut self, dt: f64) {
+        let backend = &mut self.backend;
+
+        for sound in self.sounds.values_mut() {
+            sound.voices.retain(|&voice| {
+                let playing = backend.is_playing(voice);
+                if !playing {
+                    backend.stop(voice);
+                }
+                playing
+            });
+        }
+
+        let music_volume = self.volumes.get(&Channel::Music).cloned().unwrap_or(1.0);
+        self.music.retain(|track| {
+            if track.target <= 0.0 && track.level <= 0.0 {
+                backend.stop(track.voice);
+                false
+            } else {
+                true
+            }
+        });
+        for track in &mut self.music {
+            let step = track.rate * dt;
+            track.level = if (track.target - track.level).abs() <= step {
+                track.target
+            } else if track.target > track.level {
+                track.level + step
+            } else {
+                track.level - step
+            };
+            backend.set_volume(track.voice, track.level as f32 * music_volume);
+        }
+    }
+}
+
+/// Level change per second to complete a fade in `duration` seconds.
+fn fade_rate(duration: f64) -> f64 {
+    if duration > 0.0 {
+        1.0 / duration
+    } else {
+        ::std::f64::MAX
+    }
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AudioError::Io(ref e) => write!(f, "unable to read audio file: {}", e),
+            AudioError::Data(ref e) => write!(f, "{}", e),
+            AudioError::Decode(ref e) => write!(f, "unable to decode audio: {}", e),
+            AudioError::NoDevice => write!(f, "no audio output device"),
+        }
+    }
+}
+
+impl error::Error for AudioError {
+    fn description(&self) -> &str {
+        match *self {
+            AudioError::Io(_) => "unable to read audio file",
+            AudioError::Data(_) => "invalid sound list",
+            AudioError::Decode(_) => "unable to decode audio",
+            AudioError::NoDevice => "no audio output device",
+        }
+    }
+}
+
+impl From<io::Error> for AudioError {
+    fn from(e: io::Error) -> AudioError {
+        AudioError::Io(e)
+    }
+}
+
+impl From<DataError> for AudioError {
+    fn from(e: DataError) -> AudioError {
+        AudioError::Data(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    use data::DataError;
+    use super::{Audio, AudioBackend, AudioError, LoadedSound, MusicCue, NullBackend, Sfx,
+                SoundDef, SoundHandle, VoiceId};
+
+    /// A `NullBackend` the test can still inspect after `Audio` takes it.
+    #[derive(Clone, Default)]
+    struct SharedBackend(Rc<RefCell<NullBackend>>);
+
+    impl AudioBackend for SharedBackend {
+        fn load_sound(&mut self, path: &Path) -> Result<SoundHandle, AudioError> {
+            self.0.borrow_mut().load_sound(path)
+        }
+        fn play_sound(&mut self, sound: SoundHandle, volume: f32) -> VoiceId {
+            self.0.borrow_mut().play_sound(sound, volume)
+        }
+        fn play_music(&mut self, cue: &MusicCue, volume: f32) -> Result<VoiceId, AudioError> {
+            self.0.borrow_mut().play_music(cue, volume)
+        }
+        fn set_volume(&mut self, voice: VoiceId, volume: f32) {
+            self.0.borrow_mut().set_volume(voice, volume)
+        }
+        fn stop(&mut self, voice: VoiceId) {
+            self.0.borrow_mut().stop(voice)
+        }
+        fn is_playing(&self, voice: VoiceId) -> bool {
+            self.0.borrow().is_playing(voice)
+        }
+    }
+
+    fn audio() -> (Audio, SharedBackend) {
+        let backend = SharedBackend::default();
+        (Audio::new(Box::new(backend.clone())), backend)
+    }
+
+    fn add_sound(audio: &mut Audio, sfx: Sfx, max_voices: usize) {
+        let def = SoundDef {
+            path: format!("{}.wav", sfx.name()),
+            max_voices,
+            volume: 1.0,
+        };
+        let handle = audio.backend.load_sound(Path::new(&def.path)).unwrap();
+        audio.sounds.insert(
+            sfx,
+            LoadedSound {
+                def,
+                handle,
+                voices: VecDeque::new(),
+            },
+        );
+    }
+
+    fn cue(loop_start: Option<f64>, loop_end: Option<f64>) -> MusicCue {
+        MusicCue {
+            path: "music.wav".to_string(),
+            loop_start,
+            loop_end,
+        }
+    }
+
+    #[test]
+    fn voice_limit_cuts_off_the_oldest_voice() {
+        let (mut audio, backend) = audio();
+        add_sound(&mut audio, Sfx::Hit, 2);
+        for _ in 0..3 {
+            audio.play(Sfx::Hit);
+        }
+
+        let backend = backend.0.borrow();
+        assert_eq!(backend.voice_count(), 2);
+        assert!(!backend.is_playing(0));
+        assert!(backend.is_playing(1) && backend.is_playing(2));
+        assert_eq!(audio.sounds[&Sfx::Hit].voices, vec![1, 2]);
+    }
+
+    #[test]
+    fn zero_max_voices_allows_one() {
+        let (mut audio, backend) = audio();
+        add_sound(&mut audio, Sfx::Shot, 0);
+        audio.play(Sfx::Shot);
+        audio.play(Sfx::Shot);
+
+        assert_eq!(backend.0.borrow().voice_count(), 1);
+        assert!(backend.0.borrow().is_playing(1));
+    }
+
+    #[test]
+    fn crossfade_swaps_levels_then_stops_the_old_track() {
+        let (mut audio, backend) = audio();
+        audio.play_music(&cue(None, None), 0.0);
+        audio.update(0.1);
+        assert_eq!(audio.music[0].level, 1.0);
+
+        audio.play_music(&cue(None, None), 1.0);
+        audio.update(0.5);
+        assert_eq!(audio.music[0].level, 0.5);
+        assert_eq!(audio.music[1].level, 0.5);
+
+        audio.update(0.6);
+        assert_eq!(audio.music[0].level, 0.0);
+        assert_eq!(audio.music[1].level, 1.0);
+        assert_eq!(backend.0.borrow().voice_count(), 2);
+
+        audio.update(0.1);
+        assert_eq!(audio.music.len(), 1);
+        assert_eq!(audio.music[0].voice, 1);
+        assert!(!backend.0.borrow().is_playing(0));
+        assert_eq!(backend.0.borrow().voice_count(), 1);
+    }
+
+    #[test]
+    fn validate_checks_the_loop_section() {
+        let invalid = |cue: MusicCue| match cue.validate() {
+            Err(DataError::Invalid(_)) => true,
+            _ => false,
+        };
+
+        assert!(cue(None, None).validate().is_ok());
+        assert!(cue(Some(0.0), None).validate().is_ok());
+        assert!(cue(Some(2.0), Some(10.0)).validate().is_ok());
+
+        assert!(invalid(cue(Some(-1.0), None)));
+        assert!(invalid(cue(Some(10.0), Some(2.0))));
+        assert!(invalid(cue(Some(2.0), Some(2.0))));
+        assert!(invalid(cue(None, Some(10.0))));
+    }
+}
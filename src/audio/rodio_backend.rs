@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::{self, Source};
+
+use audio::{AudioError, MusicCue};
+use audio::backend::{AudioBackend, SoundHandle, VoiceId};
+
+type FileDecoder = rodio::Decoder<BufReader<File>>;
+
+/// Fully decoded sound effect, shared between every voice playing it.
+#[derive(Debug, Clone)]
+struct Samples {
+    data: Arc<Vec<i16>>,
+    channels: u16,
+    rate: u32,
+}
+
+/// A voice's position in a sound effect's samples.
+struct Playback {
+    samples: Samples,
+    pos: usize,
+}
+
+/// A music track, decoded from its file as it plays rather than up front.
+/// Loops by reopening the file and skipping to the start of the loop
+/// section, so memory use doesn't grow with the track's length.
+struct MusicStream {
+    path: PathBuf,
+    decoder: FileDecoder,
+    channels: u16,
+    rate: u32,
+    /// Samples read since the start of the track.
+    pos: usize,
+    /// Sample indices of the loop section. Without an end it loops from
+    /// the end of the track.
+    loop_range: Option<(usize, Option<usize>)>,
+}
+
+/// Plays audio on the system's default output device.
+pub struct RodioBackend {
+    endpoint: rodio::Endpoint,
+    sounds: Vec<Samples>,
+    voices: HashMap<VoiceId, rodio::Sink>,
+    next_voice: VoiceId,
+}
+
+fn open_decoder(path: &Path) -> Result<FileDecoder, AudioError> {
+    let file = File::open(path)?;
+    rodio::Decoder::new(BufReader::new(file))
+        .map_err(|e| AudioError::Decode(format!("{}: {:?}", path.display(), e)))
+}
+
+impl Samples {
+    fn decode(path: &Path) -> Result<Samples, AudioError> {
+        let decoder = open_decoder(path)?;
+        let channels = decoder.channels();
+        let rate = decoder.samples_rate();
+
+        Ok(Samples {
+            data: Arc::new(decoder.collect()),
+            channels,
+            rate,
+        })
+    }
+}
+
+impl Iterator for Playback {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.samples.data.get(self.pos).cloned();
+        self.pos += 1;
+        sample
+    }
+}
+
+impl MusicStream {
+    /// Opens the cue's track. Its loop range must already be validated.
+    fn open(cue: &MusicCue) -> Result<MusicStream, AudioError> {
+        let path = PathBuf::from(&cue.path);
+        let decoder = open_decoder(&path)?;
+        let channels = decoder.channels();
+        let rate = decoder.samples_rate();
+
+        let index = |seconds: f64| (seconds * rate as f64) as usize * channels as usize;
+        let loop_range = cue.loop_start
+            .map(|start| (index(start), cue.loop_end.map(&index)));
+
+        Ok(MusicStream {
+            path,
+            decoder,
+            channels,
+            rate,
+            pos: 0,
+            loop_range,
+        })
+    }
+
+    /// Goes back to the sample at `start`. Returns false, ending the track,
+    /// if the file can't be reopened or is shorter than that.
+    fn rewind(&mut self, start: usize) -> bool {
+        match open_decoder(&self.path) {
+            Ok(decoder) => self.decoder = decoder,
+            Err(e) => {
+                println!("Unable to loop {}: {}", self.path.display(), e);
+                return false;
+            }
+        }
+
+        self.pos = 0;
+        while self.pos < start {
+            if self.decoder.next().is_none() {
+                return false;
+            }
+            self.pos += 1;
+        }
+        true
+    }
+}
+
+impl Iterator for MusicStream {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some((start, Some(end))) = self.loop_range {
+            if self.pos >= end && !self.rewind(start) {
+                return None;
+            }
+        }
+
+        let mut sample = self.decoder.next();
+        if sample.is_none() {
+            if let Some((start, _)) = self.loop_range {
+                if self.rewind(start) {
+                    sample = self.decoder.next();
+                }
+            }
+        }
+        if sample.is_some() {
+            self.pos += 1;
+        }
+        sample
+    }
+}
+
+impl Source for MusicStream {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+    fn samples_rate(&self) -> u32 {
+        self.rate
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Source for Playback {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+    fn channels(&self) -> u16 {
+        self.samples.channels
+    }
+    fn samples_rate(&self) -> u32 {
+        self.samples.rate
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl RodioBackend {
+    pub fn new() -> Result<RodioBackend, AudioError> {
+        let endpoint = rodio::default_endpoint().ok_or(AudioError::NoDevice)?;
+        Ok(RodioBackend {
+            endpoint,
+            sounds: Vec::new(),
+            voices: HashMap::new(),
+            next_voice: 0,
+        })
+    }
+
+    fn start<S>(&mut self, source: S, volume: f32) -> VoiceId
+    where
+        S: Source<Item = i16> + Send + 'static,
+    {
+        let sink = rodio::Sink::new(&self.endpoint);
+        sink.set_volume(volume);
+        sink.append(source);
+
+        let voice = self.next_voice;
+        self.next_voice += 1;
+        self.voices.insert(voice, sink);
+        voice
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    fn load_sound(&mut self, path: &Path) -> Result<SoundHandle, AudioError> {
+        self.sounds.push(Samples::decode(path)?);
+        Ok(self.sounds.len() - 1)
+    }
+
+    fn play_sound(&mut self, sound: SoundHandle, volume: f32) -> VoiceId {
+        let playback = Playback {
+            samples: self.sounds[sound].clone(),
+            pos: 0,
+        };
+        self.start(playback, volume)
+    }
+
+    fn play_music(&mut self, cue: &MusicCue, volume: f32) -> Result<VoiceId, AudioError> {
+        cue.validate()?;
+        let stream = MusicStream::open(cue)?;
+        Ok(self.start(stream, volume))
+    }
+
+    fn set_volume(&mut self, voice: VoiceId, volume: f32) {
+        if let Some(sink) = self.voices.get(&voice) {
+            sink.set_volume(volume);
+        }
+    }
+
+    fn stop(&mut self, voice: VoiceId) {
+        if let Some(sink) = self.voices.remove(&voice) {
+            sink.stop();
+        }
+    }
+
+    fn is_playing(&self, voice: VoiceId) -> bool {
+        self.voices.get(&voice).map_or(false, |sink| !sink.empty())
+    }
+}
@@ -0,0 +1,223 @@
+use render::Canvas;
+use rng::Rng;
+use world;
+
+const DEFAULT_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+
+/// Backdrop of a stage as described by its data file. Layers are drawn in
+/// order, so the farthest layer comes first.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackgroundData {
+    #[serde(default = "default_color")]
+    pub color: [f32; 4],
+    #[serde(default)]
+    pub layers: Vec<LayerData>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LayerData {
+    /// Scroll velocity in pixels per second at a scroll speed of 1.
+    pub velocity: [f64; 2],
+    #[serde(flatten)]
+    pub kind: LayerKind,
+}
+
+/// What a layer looks like. Every kind tiles seamlessly across the
+/// playfield so it can scroll forever.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LayerKind {
+    /// Randomly scattered square stars.
+    Starfield {
+        count: usize,
+        size: f64,
+        color: [f32; 4],
+        #[serde(default)]
+        seed: u64,
+    },
+    /// A checkerboard of two colors.
+    Tiles {
+        size: f64,
+        colors: [[f32; 4]; 2],
+    },
+}
+
+/// Gradual change of a scroll speed, so stage events can ease it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ramp {
+    current: f64,
+    target: f64,
+    rate: f64,
+}
+
+struct Layer {
+    velocity: [f64; 2],
+    offset: [f64; 2],
+    speed: Ramp,
+    kind: LayerKind,
+    stars: Vec<[f64; 2]>,
+}
+
+/// The scrolling layers behind the playfield.
+pub struct Background {
+    color: [f32; 4],
+    layers: Vec<Layer>,
+}
+
+fn default_color() -> [f32; 4] {
+    DEFAULT_COLOR
+}
+
+impl Default for BackgroundData {
+    fn default() -> BackgroundData {
+        BackgroundData {
+            color: DEFAULT_COLOR,
+            layers: Vec::new(),
+        }
+    }
+}
+
+impl Ramp {
+    fn new(value: f64) -> Ramp {
+        Ramp {
+            current: value,
+            target: value,
+            rate: 0.0,
+        }
+    }
+
+    /// Moves toward `target`, getting there in `duration` seconds.
+    fn set(&mut self, target: f64, duration: f64) {
+        self.target = target;
+        if duration > 0.0 {
+            self.rate = (target - self.current).abs() / duration;
+        } else {
+            self.current = target;
+        }
+    }
+
+    fn update(&mut self, dt: f64) {
+        let step = self.rate * dt;
+        if (self.target - self.current).abs() <= step {
+            self.current = self.target;
+        } else if self.target > self.current {
+            self.current += step;
+        } else {
+            self.current -= step;
+        }
+    }
+}
+
+impl Layer {
+    fn new(data: &LayerData) -> Layer {
+        let stars = match data.kind {
+            LayerKind::Starfield { count, seed, .. } => {
+                let mut rng = Rng::new(seed);
+                (0..count)
+                    .map(|_| {
+                        [
+                            rng.range(0.0, world::PLAYFIELD_WIDTH),
+                            rng.range(0.0, world::PLAYFIELD_HEIGHT),
+                        ]
+                    })
+                    .collect()
+            }
+            LayerKind::Tiles { .. } => Vec::new(),
+        };
+
+        Layer {
+            velocity: data.velocity,
+            offset: [0.0, 0.0],
+            speed: Ramp::new(1.0),
+            kind: data.kind.clone(),
+            stars,
+        }
+    }
+
+    fn draw(&self, canvas: &mut Canvas) {
+        match self.kind {
+            LayerKind::Starfield { size, color, .. } => for star in &self.stars {
+                let x = wrap(star[0] + self.offset[0], world::PLAYFIELD_WIDTH);
+                let y = wrap(star[1] + self.offset[1], world::PLAYFIELD_HEIGHT);
+                canvas.fill_rect([x - size / 2.0, y - size / 2.0, size, size], color, false);
+            },
+            LayerKind::Tiles { size, colors } => {
+                if size <= 0.0 {
+                    return;
+                }
+                let start_x = wrap(self.offset[0], size * 2.0) - size * 2.0;
+                let start_y = wrap(self.offset[1], size * 2.0) - size * 2.0;
+
+                let mut row = 0;
+                let mut y = start_y;
+                while y < world::PLAYFIELD_HEIGHT {
+                    let mut column = 0;
+                    let mut x = start_x;
+                    while x < world::PLAYFIELD_WIDTH {
+                        canvas.fill_rect([x, y, size, size], colors[(row + column) % 2], false);
+                        x += size;
+                        column += 1;
+                    }
+                    y += size;
+                    row += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Background {
+    pub fn new(data: &BackgroundData) -> Background {
+        Background {
+            color: data.color,
+            layers: data.layers.iter().map(Layer::new).collect(),
+        }
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// Eases the scroll speed of `layer`, or of every layer if `None`, to
+    /// `speed` times its base velocity over `duration` seconds.
+    pub fn set_scroll_speed(&mut self, layer: Option<usize>, speed: f64, duration: f64) {
+        for (i, l) in self.layers.iter_mut().enumerate() {
+            if layer.map_or(true, |layer| layer == i) {
+                l.speed.set(speed, duration);
+            }
+        }
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        for layer in &mut self.layers {
+            layer.speed.update(dt);
+            let speed = layer.speed.current;
+            layer.offset[0] += layer.velocity[0] * speed * dt;
+            layer.offset[1] += layer.velocity[1] * speed * dt;
+        }
+    }
+
+    /// Draws every layer, back to front. The canvas should already be
+    /// cleared to `color()`.
+    pub fn draw(&self, canvas: &mut Canvas) {
+        for layer in &self.layers {
+            layer.draw(canvas);
+        }
+        canvas.flush();
+    }
+}
+
+impl Default for Background {
+    fn default() -> Background {
+        Background::new(&BackgroundData::default())
+    }
+}
+
+fn wrap(value: f64, size: f64) -> f64 {
+    let wrapped = value % size;
+    if wrapped < 0.0 {
+        wrapped + size
+    } else {
+        wrapped
+    }
+}
@@ -0,0 +1,110 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono;
+use gl;
+use image::{self, RgbaImage};
+
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Reads the back buffer of the current OpenGL context. Must be called
+/// after drawing and before the buffers are swapped.
+pub fn read_framebuffer(width: u32, height: u32) -> RgbaImage {
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadBuffer(gl::BACK);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+    }
+
+    // OpenGL rows start at the bottom of the window.
+    let image = RgbaImage::from_raw(width, height, pixels)
+        .expect("Framebuffer size doesn't match pixel buffer.");
+    image::imageops::flip_vertical(&image)
+}
+
+/// A numbered run of frames written to one directory.
+#[derive(Debug, Clone)]
+struct Sequence {
+    dir: PathBuf,
+    next_frame: u64,
+}
+
+/// Pending screenshots and the state of continuous frame capture.
+#[derive(Debug, Clone, Default)]
+pub struct FrameCapture {
+    screenshot_requested: bool,
+    sequence: Option<Sequence>,
+}
+
+impl FrameCapture {
+    pub fn new() -> FrameCapture {
+        FrameCapture {
+            screenshot_requested: false,
+            sequence: None,
+        }
+    }
+
+    /// Saves the next drawn frame to a timestamped PNG in `screenshots/`.
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
+    /// Writes every following frame to `dir` as `frame_000000.png`,
+    /// `frame_000001.png` and so on.
+    pub fn start_sequence<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        fs::create_dir_all(dir.as_ref())?;
+        self.sequence = Some(Sequence {
+            dir: dir.as_ref().to_path_buf(),
+            next_frame: 0,
+        });
+        Ok(())
+    }
+
+    pub fn stop_sequence(&mut self) {
+        self.sequence = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.sequence.is_some()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.screenshot_requested || self.sequence.is_some()
+    }
+
+    /// Saves `frame` wherever it's been asked for. A failure doesn't stop
+    /// a sequence; the frame is dropped and the next one tried as usual.
+    pub fn on_frame(&mut self, frame: &RgbaImage) -> io::Result<()> {
+        let mut result = Ok(());
+
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            result = save_screenshot(frame);
+        }
+
+        if let Some(ref mut sequence) = self.sequence {
+            let name = format!("frame_{:06}.png", sequence.next_frame);
+            match frame.save(sequence.dir.join(name)) {
+                Ok(()) => sequence.next_frame += 1,
+                Err(e) => result = result.and(Err(e)),
+            }
+        }
+        result
+    }
+}
+
+fn save_screenshot(frame: &RgbaImage) -> io::Result<()> {
+    fs::create_dir_all(SCREENSHOT_DIR)?;
+    let name = chrono::Local::now().format("screenshot_%Y%m%d_%H%M%S%.3f.png");
+    frame.save(Path::new(SCREENSHOT_DIR).join(name.to_string()))
+}
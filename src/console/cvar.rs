@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::error;
+use std::fmt;
+
+/// The value of a console variable. A variable keeps the type it was
+/// registered with; setting it parses the new value as that type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CvarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+/// A registered console variable.
+#[derive(Debug, Clone)]
+pub struct Cvar {
+    pub value: CvarValue,
+    pub default: CvarValue,
+    pub help: String,
+    /// Bounds a number variable is clamped to when set.
+    pub range: Option<(f64, f64)>,
+}
+
+/// Typed variables that can be read and set from the console.
+#[derive(Debug, Clone, Default)]
+pub struct Cvars {
+    vars: BTreeMap<String, Cvar>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CvarError {
+    Unknown(String),
+    /// `value` couldn't be read as the variable's type.
+    Invalid {
+        name: String,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+impl CvarValue {
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            CvarValue::Bool(_) => "bool",
+            CvarValue::Int(_) => "int",
+            CvarValue::Float(_) => "float",
+            CvarValue::Str(_) => "string",
+        }
+    }
+
+    /// Parses `text` as a value of the same type as this one. Booleans also
+    /// accept `1`/`0` and `on`/`off`; floats must be finite.
+    pub fn parse_as(&self, text: &str) -> Option<CvarValue> {
+        match *self {
+            CvarValue::Bool(_) => match text {
+                "true" | "1" | "on" => Some(CvarValue::Bool(true)),
+                "false" | "0" | "off" => Some(CvarValue::Bool(false)),
+                _ => None,
+            },
+            CvarValue::Int(_) => text.parse().ok().map(CvarValue::Int),
+            CvarValue::Float(_) => match text.parse::<f64>() {
+                Ok(x) if x.is_finite() => Some(CvarValue::Float(x)),
+                _ => None,
+            },
+            CvarValue::Str(_) => Some(CvarValue::Str(text.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for CvarValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CvarValue::Bool(b) => write!(f, "{}", b),
+            CvarValue::Int(i) => write!(f, "{}", i),
+            CvarValue::Float(x) => write!(f, "{}", x),
+            CvarValue::Str(ref s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+impl Cvar {
+    /// `value` brought within the variable's range, if it has one.
+    fn clamp(&self, value: CvarValue) -> CvarValue {
+        match (value, self.range) {
+            (CvarValue::Float(x), Some((min, max))) => CvarValue::Float(x.max(min).min(max)),
+            (CvarValue::Int(i), Some((min, max))) => {
+                CvarValue::Int((i as f64).max(min).min(max) as i64)
+            }
+            (value, _) => value,
+        }
+    }
+}
+
+impl Cvars {
+    pub fn new() -> Cvars {
+        Cvars { vars: BTreeMap::new() }
+    }
+
+    /// Adds a variable starting at `default`. Registering a name again
+    /// replaces the earlier variable.
+    pub fn register<S: Into<String>>(&mut self, name: S, default: CvarValue, help: &str) {
+        self.vars.insert(
+            name.into(),
+            Cvar {
+                value: default.clone(),
+                default,
+                help: help.to_string(),
+                range: None,
+            },
+        );
+    }
+
+    /// Keeps a number variable within `[min, max]` from now on, clamping
+    /// its current value too.
+    pub fn set_range(&mut self, name: &str, min: f64, max: f64) -> Result<(), CvarError> {
+        let var = self.vars
+            .get_mut(name)
+            .ok_or_else(|| CvarError::Unknown(name.to_string()))?;
+        var.range = Some((min, max));
+        var.value = var.clamp(var.value.clone());
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CvarValue> {
+        self.vars.get(name).map(|v| &v.value)
+    }
+    pub fn get_cvar(&self, name: &str) -> Option<&Cvar> {
+        self.vars.get(name)
+    }
+
+    /// The variable's value if it's a bool, otherwise false.
+    pub fn get_bool(&self, name: &str) -> bool {
+        match self.get(name) {
+            Some(&CvarValue::Bool(b)) => b,
+            _ => false,
+        }
+    }
+
+    /// The variable's value if it's a number, otherwise `default`.
+    pub fn get_float(&self, name: &str, default: f64) -> f64 {
+        match self.get(name) {
+            Some(&CvarValue::Float(x)) => x,
+            Some(&CvarValue::Int(i)) => i as f64,
+            _ => default,
+        }
+    }
+
+    /// Sets a variable, converting `value` to its type where that makes
+    /// sense and clamping it to the variable's range.
+    pub fn set(&mut self, name: &str, value: CvarValue) -> Result<(), CvarError> {
+        let var = self.vars
+            .get_mut(name)
+            .ok_or_else(|| CvarError::Unknown(name.to_string()))?;
+        let value = match (&var.value, value) {
+            (&CvarValue::Float(_), CvarValue::Int(i)) => CvarValue::Float(i as f64),
+            (current, value) => {
+                if current.type_name() != value.type_name() {
+                    return Err(CvarError::Invalid {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                        expected: current.type_name(),
+                    });
+                }
+                value
+            }
+        };
+        var.value = var.clamp(value);
+        Ok(())
+    }
+
+    /// Sets a variable from text typed in the console.
+    pub fn set_str(&mut self, name: &str, text: &str) -> Result<(), CvarError> {
+        let var = self.vars
+            .get_mut(name)
+            .ok_or_else(|| CvarError::Unknown(name.to_string()))?;
+        let expected = var.value.type_name();
+        let value = var.value.parse_as(text).ok_or_else(|| {
+            CvarError::Invalid {
+                name: name.to_string(),
+                value: text.to_string(),
+                expected,
+            }
+        })?;
+        var.value = var.clamp(value);
+        Ok(())
+    }
+
+    /// Flips a bool variable, returning its new value.
+    pub fn toggle(&mut self, name: &str) -> Result<bool, CvarError> {
+        let value = !self.get_bool(name);
+        self.set(name, CvarValue::Bool(value))?;
+        Ok(value)
+    }
+
+    /// Names of every variable starting with `prefix`, in alphabetical
+    /// order.
+    pub fn complete(&self, prefix: &str) -> Vec<&str> {
+        self.vars
+            .keys()
+            .map(|name| name.as_str())
+            .filter(|name| name.starts_with(prefix))
+            .collect()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<String, Cvar> {
+        self.vars.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.vars.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+}
+
+impl fmt::Display for CvarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CvarError::Unknown(ref name) => write!(f, "unknown variable '{}'", name),
+            CvarError::Invalid {
+                ref name,
+                ref value,
+                expected,
+            } => write!(f, "'{}' expects a {}, not '{}'", name, expected, value),
+        }
+    }
+}
+
+impl error::Error for CvarError {
+    fn description(&self) -> &str {
+        match *self {
+            CvarError::Unknown(_) => "unknown console variable",
+            CvarError::Invalid { .. } => "invalid value for console variable",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CvarError, CvarValue, Cvars};
+
+    fn cvars() -> Cvars {
+        let mut cvars = Cvars::new();
+        cvars.register("god_mode", CvarValue::Bool(false), "");
+        cvars.register("lives", CvarValue::Int(3), "");
+        cvars.register("time_scale", CvarValue::Float(1.0), "");
+        cvars.register("name", CvarValue::Str("Player".to_string()), "");
+        cvars
+    }
+
+    #[test]
+    fn set_str_parses_as_the_registered_type() {
+        let mut cvars = cvars();
+        cvars.set_str("god_mode", "on").unwrap();
+        cvars.set_str("lives", "5").unwrap();
+        cvars.set_str("time_scale", "0.5").unwrap();
+        cvars.set_str("name", "Someone else").unwrap();
+
+        assert_eq!(cvars.get("god_mode"), Some(&CvarValue::Bool(true)));
+        assert_eq!(cvars.get("lives"), Some(&CvarValue::Int(5)));
+        assert_eq!(cvars.get("time_scale"), Some(&CvarValue::Float(0.5)));
+        assert_eq!(cvars.get("name"), Some(&CvarValue::Str("Someone else".to_string())));
+
+        cvars.set_str("god_mode", "0").unwrap();
+        assert!(!cvars.get_bool("god_mode"));
+    }
+
+    #[test]
+    fn set_str_rejects_bad_values() {
+        let mut cvars = cvars();
+        for &(name, text) in &[
+            ("god_mode", "maybe"),
+            ("lives", "2.5"),
+            ("time_scale", "fast"),
+            ("time_scale", "inf"),
+            ("time_scale", "NaN"),
+        ] {
+            match cvars.set_str(name, text) {
+                Err(CvarError::Invalid { .. }) => (),
+                other => panic!("{} = {}: unexpected {:?}", name, text, other),
+            }
+        }
+        assert_eq!(
+            cvars.set_str("speed", "1"),
+            Err(CvarError::Unknown("speed".to_string()))
+        );
+        assert_eq!(cvars.get_float("time_scale", 0.0), 1.0);
+    }
+
+    #[test]
+    fn values_are_clamped_to_the_range() {
+        let mut cvars = cvars();
+        cvars.set_str("time_scale", "20").unwrap();
+        cvars.set_range("time_scale", 0.0, 10.0).unwrap();
+        assert_eq!(cvars.get_float("time_scale", 1.0), 10.0);
+
+        cvars.set_str("time_scale", "-1").unwrap();
+        assert_eq!(cvars.get_float("time_scale", 1.0), 0.0);
+        cvars.set("time_scale", CvarValue::Int(4)).unwrap();
+        assert_eq!(cvars.get("time_scale"), Some(&CvarValue::Float(4.0)));
+
+        cvars.set_range("lives", 1.0, 9.0).unwrap();
+        cvars.set_str("lives", "99").unwrap();
+        assert_eq!(cvars.get("lives"), Some(&CvarValue::Int(9)));
+    }
+
+    #[test]
+    fn set_rejects_other_types() {
+        let mut cvars = cvars();
+        assert!(cvars.set("lives", CvarValue::Bool(true)).is_err());
+        assert!(cvars.toggle("god_mode").unwrap());
+        assert!(cvars.toggle("lives").is_err());
+    }
+
+    #[test]
+    fn complete_lists_matches_in_order() {
+        let cvars = cvars();
+        assert_eq!(cvars.complete(""), vec!["god_mode", "lives", "name", "time_scale"]);
+        assert_eq!(cvars.complete("l"), vec!["lives"]);
+        assert!(cvars.complete("x").is_empty());
+    }
+}
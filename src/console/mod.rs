@@ -0,0 +1,433 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use glutin::VirtualKeyCode;
+
+use render;
+use world;
+use input::{InputEvent, InputEvents, Repeat};
+use input::command::CommandDirectory;
+
+pub mod cvar;
+
+pub use self::cvar::{Cvar, CvarError, CvarValue, Cvars};
+
+/// Multiplier on how fast game time passes.
+pub const TIME_SCALE: &str = "time_scale";
+/// The player can't be hit while set.
+pub const GOD_MODE: &str = "god_mode";
+/// Draws every entity's hitbox while set.
+pub const SHOW_HITBOXES: &str = "show_hitboxes";
+
+const MAX_HISTORY: usize = 100;
+const MAX_SCROLLBACK: usize = 200;
+/// Portion of the screen the console covers when open.
+const HEIGHT_FRACTION: f64 = 0.4;
+const FONT_SIZE: u32 = 14;
+const LINE_HEIGHT: f64 = 18.0;
+const PADDING: f64 = 8.0;
+const BACKGROUND_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.85];
+const TEXT_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+const PROMPT_COLOR: [f32; 4] = [1.0, 1.0, 0.6, 1.0];
+
+/// Commands the console runs itself rather than handing to the game.
+const BUILTINS: &[(&str, &str)] = &[
+    ("help", "lists commands"),
+    ("clear", "clears the console"),
+    ("cvars", "lists variables and their values"),
+];
+
+/// A registered command typed in the console, waiting for the game to run
+/// it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// Drop-down developer console. Lines typed into it either read or set a
+/// console variable, or invoke a registered command, which is queued until
+/// the game takes it with `take_invocations`.
+#[derive(Debug)]
+pub struct Console {
+    open: bool,
+    line: String,
+    output: VecDeque<String>,
+    history: Vec<String>,
+    /// Index into `history` while browsing it with up and down.
+    history_pos: Option<usize>,
+    /// What was typed before browsing history started.
+    draft: String,
+    commands: CommandDirectory,
+    help: HashMap<u32, String>,
+    cvars: Cvars,
+    pending: Vec<Invocation>,
+}
+
+impl Console {
+    pub fn new() -> Console {
+        let mut console = Console {
+            open: false,
+            line: String::new(),
+            output: VecDeque::new(),
+            history: Vec::new(),
+            history_pos: None,
+            draft: String::new(),
+            commands: CommandDirectory::new(),
+            help: HashMap::new(),
+            cvars: Cvars::new(),
+            pending: Vec::new(),
+        };
+        for &(name, help) in BUILTINS {
+            console.register_command(name, help);
+        }
+        console
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Adds a command. `help` is shown next to it by `help`.
+    pub fn register_command(&mut self, name: &str, help: &str) {
+        let id = self.commands.register_command(name.to_string()).id();
+        self.help.insert(id, help.to_string());
+    }
+
+    pub fn cvars(&self) -> &Cvars {
+        &self.cvars
+    }
+    pub fn cvars_mut(&mut self) -> &mut Cvars {
+        &mut self.cvars
+    }
+
+    /// Adds a line to the console's output.
+    pub fn print<S: Into<String>>(&mut self, line: S) {
+        self.output.push_back(line.into());
+        while self.output.len() > MAX_SCROLLBACK {
+            self.output.pop_front();
+        }
+    }
+
+    /// Commands typed since the last call, oldest first.
+    pub fn take_invocations(&mut self) -> Vec<Invocation> {
+        mem::replace(&mut self.pending, Vec::new())
+    }
+
+    /// Edits the input line with this frame's typing.
+    pub fn handle_input(&mut self, events: &InputEvents) {
+        for event in events {
+            match *event {
+                // The toggle key types a character too.
+                InputEvent::Character(c) if !c.is_control() && c != '`' && c != '~' => {
+                    self.line.push(c);
+                }
+                InputEvent::VirtKey(key, _, ref repeat) => {
+                    let pressed = *repeat == Repeat::NoRepeat;
+                    if let Repeat::EarlyRepeat(_) = *repeat {
+                        continue;
+                    }
+
+                    match key {
+                        VirtualKeyCode::Back => {
+                            self.line.pop();
+                        }
+                        VirtualKeyCode::Up => self.history_back(),
+                        VirtualKeyCode::Down => self.history_forward(),
+                        VirtualKeyCode::Return if pressed => self.submit(),
+                        VirtualKeyCode::Tab if pressed => self.complete(),
+                        VirtualKeyCode::Escape if pressed => self.open = false,
+                        _ => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Runs the input line and adds it to the history.
+    fn submit(&mut self) {
+        let line = mem::replace(&mut self.line, String::new());
+        let line = line.trim();
+        self.history_pos = None;
+        if line.is_empty() {
+            return;
+        }
+
+        self.print(format!("> {}", line));
+        if self.history.last().map_or(true, |last| last != line) {
+            self.history.push(line.to_string());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        self.execute(line);
+    }
+
+    /// Runs one line as if it had been typed.
+    pub fn execute(&mut self, line: &str) {
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return,
+        };
+        let args: Vec<String> = words.map(|w| w.to_string()).collect();
+
+        match name {
+            "help" => self.print_help(),
+            "clear" => self.output.clear(),
+            "cvars" => self.print_cvars(),
+            _ if self.cvars.get(name).is_some() => self.run_cvar(name, &args),
+            _ if self.commands.get_command_by_name(name).is_some() => {
+                self.pending.push(Invocation {
+                    name: name.to_string(),
+                    args,
+                });
+            }
+            _ => self.print(format!("Unknown command '{}'", name)),
+        }
+    }
+
+    /// Prints a variable, or sets it if given a value.
+    fn run_cvar(&mut self, name: &str, args: &[String]) {
+        if !args.is_empty() {
+            if let Err(e) = self.cvars.set_str(name, &args.join(" ")) {
+                self.print(e.to_string());
+                return;
+            }
+        }
+
+        let line = match self.cvars.get_cvar(name) {
+            Some(var) => format!(
+                "{} = {} ({}, default {})",
+                name,
+                var.value,
+                var.value.type_name(),
+                var.default
+            ),
+            None => return,
+        };
+        self.print(line);
+    }
+
+    fn print_help(&mut self) {
+        let lines: Vec<String> = self.commands
+            .complete("")
+            .into_iter()
+            .filter_map(|name| self.commands.get_command_by_name(name))
+            .map(|command| {
+                let help = self.help.get(&command.id()).map_or("", |h| h.as_str());
+                format!("{} - {}", command.name(), help)
+            })
+            .collect();
+        for line in lines {
+            self.print(line);
+        }
+        self.print("Type a variable's name to see it, or its name and a value to set it.");
+    }
+
+    fn print_cvars(&mut self) {
+        let lines: Vec<String> = self.cvars
+            .iter()
+            .map(|(name, var)| format!("{} = {} - {}", name, var.value, var.help))
+            .collect();
+        for line in lines {
+            self.print(line);
+        }
+    }
+
+    fn history_back(&mut self) {
+        let pos = match self.history_pos {
+            Some(0) => 0,
+            Some(pos) => pos - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.line.clone();
+                self.history.len() - 1
+            }
+        };
+        self.history_pos = Some(pos);
+        self.line = self.history[pos].clone();
+    }
+
+    fn history_forward(&mut self) {
+        match self.history_pos {
+            Some(pos) if pos + 1 < self.history.len() => {
+                self.history_pos = Some(pos + 1);
+                self.line = self.history[pos + 1].clone();
+            }
+            Some(_) => {
+                self.history_pos = None;
+                self.line = mem::replace(&mut self.draft, String::new());
+            }
+            None => (),
+        }
+    }
+
+    /// Completes the command or variable name being typed. With several
+    /// candidates the line is extended as far as they agree and the
+    /// candidates are listed.
+    fn complete(&mut self) {
+        if self.line.contains(' ') {
+            return;
+        }
+
+        let mut candidates: Vec<String> = self.commands
+            .complete(&self.line)
+            .into_iter()
+            .chain(self.cvars.complete(&self.line))
+            .map(|name| name.to_string())
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        match candidates.len() {
+            0 => (),
+            1 => self.line = format!("{} ", candidates[0]),
+            _ => {
+                self.line = common_prefix(&candidates).to_string();
+                let listing = candidates.join("  ");
+                self.print(listing);
+            }
+        }
+    }
+
+    /// Draws the console across the top of the screen if it's open.
+    pub fn draw(&self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        if !self.open {
+            return;
+        }
+
+        let height = world::PLAYFIELD_HEIGHT * HEIGHT_FRACTION;
+        graphics::Rectangle::new(BACKGROUND_COLOR).draw(
+            [0.0, 0.0, world::PLAYFIELD_WIDTH, height],
+            &ctx.draw_state,
+            ctx.transform,
+            gl,
+        );
+
+        let prompt_y = height - PADDING;
+        render::render_text(
+            &format!("> {}_", self.line),
+            [PADDING, prompt_y],
+            FONT_SIZE,
+            PROMPT_COLOR,
+            ctx,
+            gl,
+            glyphs,
+        );
+
+        let mut y = prompt_y - LINE_HEIGHT;
+        for line in self.output.iter().rev() {
+            if y < LINE_HEIGHT {
+                break;
+            }
+            render::render_text(line, [PADDING, y], FONT_SIZE, TEXT_COLOR, ctx, gl, glyphs);
+            y -= LINE_HEIGHT;
+        }
+    }
+}
+
+/// The longest prefix shared by every name in `names`.
+fn common_prefix(names: &[String]) -> &str {
+    let first = match names.first() {
+        Some(first) => first,
+        None => return "",
+    };
+
+    let mut len = first.len();
+    for name in &names[1..] {
+        len = first
+            .char_indices()
+            .zip(name.chars())
+            .take_while(|&((_, a), b)| a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(len);
+    }
+    &first[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{common_prefix, Console, CvarValue, MAX_HISTORY};
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn submit(console: &mut Console, line: &str) {
+        console.line = line.to_string();
+        console.submit();
+    }
+
+    #[test]
+    fn common_prefix_stops_at_the_first_difference() {
+        assert_eq!(common_prefix(&names(&["time_scale", "timer", "time"])), "time");
+        assert_eq!(common_prefix(&names(&["help", "hitboxes"])), "h");
+        assert_eq!(common_prefix(&names(&["god_mode", "clear"])), "");
+        assert_eq!(common_prefix(&names(&["only"])), "only");
+        assert_eq!(common_prefix(&names(&["éa", "éb"])), "é");
+        assert_eq!(common_prefix(&[]), "");
+    }
+
+    #[test]
+    fn tab_completes_commands_and_variables() {
+        let mut console = Console::new();
+        console.register_command("hitboxes", "");
+        console.cvars_mut().register("time_scale", CvarValue::Float(1.0), "");
+
+        console.line = "ti".to_string();
+        console.complete();
+        assert_eq!(console.line, "time_scale ");
+
+        console.line = "h".to_string();
+        console.complete();
+        assert_eq!(console.line, "h");
+        assert_eq!(console.output.back().map(|l| l.as_str()), Some("help  hitboxes"));
+
+        console.line = "time_scale 2".to_string();
+        console.complete();
+        assert_eq!(console.line, "time_scale 2");
+    }
+
+    #[test]
+    fn history_browses_back_and_restores_the_draft() {
+        let mut console = Console::new();
+        submit(&mut console, "first");
+        submit(&mut console, "second");
+        submit(&mut console, "second");
+        assert_eq!(console.history, names(&["first", "second"]));
+
+        console.line = "dra".to_string();
+        console.history_back();
+        assert_eq!(console.line, "second");
+        console.history_back();
+        assert_eq!(console.line, "first");
+        console.history_back();
+        assert_eq!(console.line, "first");
+
+        console.history_forward();
+        assert_eq!(console.line, "second");
+        console.history_forward();
+        assert_eq!(console.line, "dra");
+        console.history_forward();
+        assert_eq!(console.line, "dra");
+    }
+
+    #[test]
+    fn history_keeps_the_latest_lines() {
+        let mut console = Console::new();
+        for i in 0..MAX_HISTORY + 5 {
+            submit(&mut console, &i.to_string());
+        }
+        assert_eq!(console.history.len(), MAX_HISTORY);
+        assert_eq!(console.history[0], "5");
+    }
+}
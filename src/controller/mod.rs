@@ -1,36 +1,195 @@
 use specs;
-use cgmath::Vector2;
-use glutin::VirtualKeyCode;
+use cgmath::{Vector2, InnerSpace, Zero};
 use game_time::GameTime;
 
-use input::{InputManager, InputEvent};
+use input::InputManager;
+use input::bindings::KeyBindings;
+use render::RenderGraphicState;
+use ecs::spawn::Spawn;
+use ecs::component::bullet::Faction;
+use difficulty::{Difficulty, Scaling};
+use pattern::{PatternController, PatternSet};
+use script::ScriptLibrary;
+use script::vm::{ScriptController, ScriptState};
+
+/// Read-only view of the world handed to a `Controller` each frame.
+pub struct ControllerContext<'a> {
+    pub entity: specs::Entity,
+    pub time: &'a GameTime,
+    pub input: &'a InputManager,
+    /// The player's current key bindings, which can change mid-run.
+    pub bindings: &'a KeyBindings,
+    pub pos: Vector2<f64>,
+    pub vel: Vector2<f64>,
+    pub player_pos: Option<Vector2<f64>>,
+    pub entities: &'a [(specs::Entity, Vector2<f64>)],
+    pub difficulty: Difficulty,
+    pub scaling: &'a Scaling,
+}
+
+/// A bullet fired from the controlled entity's position.
+#[derive(Clone)]
+pub struct Projectile {
+    pub offset: Vector2<f64>,
+    pub vel: Vector2<f64>,
+    pub graphic: RenderGraphicState,
+    pub faction: Faction,
+}
+
+/// Commands returned by a `Controller` and applied by the `ControlSystem`.
+pub enum Action {
+    Move(Vector2<f64>),
+    /// Enter or leave focus. Only meaningful for the player entity.
+    Focus(bool),
+    /// Hold or release the trigger of the entity's `Weapon`.
+    Shoot(bool),
+    /// Use one of the player's bombs. Only meaningful for the player entity.
+    Bomb,
+    Fire(Projectile),
+    Spawn(Spawn),
+    /// Spawn the named prefab at `offset` from the controlled entity,
+    /// optionally replacing its velocity.
+    SpawnPrefab {
+        name: String,
+        offset: Vector2<f64>,
+        vel: Option<Vector2<f64>>,
+    },
+    Despawn(specs::Entity),
+}
 
 pub trait Controller {
-    fn do_actions(
-        &mut self,
-        entity: &mut specs::Entity,
-        time: &GameTime,
-        vel: &mut Vector2<f64>,
-        input: &InputManager,
-    );
+    fn do_actions(&mut self, ctx: &ControllerContext) -> Vec<Action>;
+
+    /// The controller's state for a save file, or `None` if it can't be
+    /// saved, in which case the entity is saved without one.
+    fn save(&self) -> Option<ControllerSave> {
+        None
+    }
+}
+
+/// A saved controller of one of the built in kinds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControllerSave {
+    Human,
+    Pattern(PatternController),
+    PatternSet(PatternSet),
+    Script(ScriptState),
 }
 
-pub struct HumanController {}
+pub struct HumanController {
+    speed: f64,
+    focus_speed: f64,
+    /// Whether the bomb key was down last frame, so holding it only bombs
+    /// once.
+    bomb_held: bool,
+}
+
+impl<'a> ControllerContext<'a> {
+    /// All other entities with a position within `radius` of this one.
+    pub fn entities_within(&self, radius: f64) -> Vec<(specs::Entity, Vector2<f64>)> {
+        let radius2 = radius * radius;
+        self.entities
+            .iter()
+            .filter(|&&(e, pos)| {
+                e != self.entity && (pos - self.pos).magnitude2() <= radius2
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Unit vector pointing from this entity towards the player, if there is one.
+    pub fn aim_at_player(&self) -> Option<Vector2<f64>> {
+        self.player_pos.and_then(|p| {
+            let dir = p - self.pos;
+            if dir.is_zero() {
+                None
+            } else {
+                Some(dir.normalize())
+            }
+        })
+    }
+}
 
 impl HumanController {
     pub fn new() -> HumanController {
-        HumanController {}
+        HumanController {
+            speed: 300.0,
+            focus_speed: 120.0,
+            bomb_held: false,
+        }
+    }
+}
+
+impl ControllerSave {
+    /// Rebuilds the controller, looking scripts up by name in `scripts`.
+    pub fn restore(
+        self,
+        scripts: &ScriptLibrary,
+    ) -> Result<Box<Controller + Send + Sync>, String> {
+        Ok(match self {
+            ControllerSave::Human => Box::new(HumanController::new()),
+            ControllerSave::Pattern(controller) => Box::new(controller),
+            ControllerSave::PatternSet(set) => Box::new(set),
+            ControllerSave::Script(state) => {
+                let name = state.script().to_string();
+                match ScriptController::restore(state, scripts) {
+                    Some(controller) => Box::new(controller),
+                    None => return Err(format!("unknown script '{}'", name)),
+                }
+            }
+        })
     }
 }
 
 impl Controller for HumanController {
-    fn do_actions(
-        &mut self,
-        entity: &mut specs::Entity,
-        time: &GameTime,
-        vel: &mut Vector2<f64>,
-        input: &InputManager,
-    ) {
+    fn do_actions(&mut self, ctx: &ControllerContext) -> Vec<Action> {
+        let input = ctx.input;
+        let keys = ctx.bindings;
+        let mut dir = Vector2::zero();
+
+        if input.is_key_down(keys.left) {
+            dir.x -= 1.0;
+        }
+        if input.is_key_down(keys.right) {
+            dir.x += 1.0;
+        }
+        if input.is_key_down(keys.up) {
+            dir.y -= 1.0;
+        }
+        if input.is_key_down(keys.down) {
+            dir.y += 1.0;
+        }
+
+        let focused = input.is_key_down(keys.focus);
+        let speed = if focused {
+            self.focus_speed
+        } else {
+            self.speed
+        };
+
+        if !dir.is_zero() {
+            dir = dir.normalize() * speed;
+        }
+
+        let firing = input.is_key_down(keys.shoot);
+
+        let mut actions = vec![
+            Action::Move(dir),
+            Action::Focus(focused),
+            Action::Shoot(firing),
+        ];
+
+        let bomb_down = input.is_key_down(keys.bomb);
+        if bomb_down && !self.bomb_held {
+            actions.push(Action::Bomb);
+        }
+        self.bomb_held = bomb_down;
+
+        actions
+    }
 
+    fn save(&self) -> Option<ControllerSave> {
+        Some(ControllerSave::Human)
     }
 }
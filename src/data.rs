@@ -0,0 +1,131 @@
+use std::io::{self, Read, Write};
+use std::fs::{self, File};
+use std::path::Path;
+use std::error;
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use toml;
+
+/// Failure to load one of the game's TOML data files.
+#[derive(Debug)]
+pub enum DataError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    /// The file parsed, but a value in it can't be used.
+    Invalid(String),
+}
+
+pub fn load_toml<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, DataError> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(toml::from_str(&contents)?)
+}
+
+/// Writes `value` to a temporary file beside `path` and renames it into
+/// place, so a crash mid-write leaves any earlier file intact. Creates the
+/// directory if needed.
+pub fn write_toml_atomic<T, P, E>(path: P, value: &T) -> Result<(), E>
+where
+    T: Serialize,
+    P: AsRef<Path>,
+    E: From<io::Error> + From<toml::ser::Error>,
+{
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // Going through `Value` puts plain values ahead of tables, which TOML
+    // requires.
+    let contents = toml::to_string(&toml::Value::try_from(value)?)?;
+    let temp = path.with_extension("tmp");
+    {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DataError::Io(ref e) => write!(f, "unable to read data file: {}", e),
+            DataError::Parse(ref e) => write!(f, "invalid data file: {}", e),
+            DataError::Invalid(ref e) => write!(f, "invalid value in data file: {}", e),
+        }
+    }
+}
+
+impl error::Error for DataError {
+    fn description(&self) -> &str {
+        match *self {
+            DataError::Io(_) => "unable to read data file",
+            DataError::Parse(_) => "invalid data file",
+            DataError::Invalid(_) => "invalid value in data file",
+        }
+    }
+}
+
+impl From<io::Error> for DataError {
+    fn from(e: io::Error) -> DataError {
+        DataError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for DataError {
+    fn from(e: toml::de::Error) -> DataError {
+        DataError::Parse(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::env;
+    use std::fs;
+    use std::io;
+
+    use toml;
+
+    use super::{load_toml, write_toml_atomic, DataError};
+
+    #[derive(Debug)]
+    enum WriteError {
+        Io(io::Error),
+        Serialize(toml::ser::Error),
+    }
+
+    impl From<io::Error> for WriteError {
+        fn from(e: io::Error) -> WriteError {
+            WriteError::Io(e)
+        }
+    }
+
+    impl From<toml::ser::Error> for WriteError {
+        fn from(e: toml::ser::Error) -> WriteError {
+            WriteError::Serialize(e)
+        }
+    }
+
+    #[test]
+    fn atomic_write_creates_the_directory_and_replaces_the_file() {
+        let dir = env::temp_dir().join("data_test_atomic_write");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("nested").join("value.toml");
+
+        let mut value = BTreeMap::new();
+        value.insert("table".to_string(), vec![1, 2, 3]);
+        write_toml_atomic::<_, _, WriteError>(&path, &value).unwrap();
+        value.insert("table".to_string(), vec![4]);
+        write_toml_atomic::<_, _, WriteError>(&path, &value).unwrap();
+
+        let read: Result<BTreeMap<String, Vec<i64>>, DataError> = load_toml(&path);
+        assert_eq!(read.unwrap()["table"], vec![4]);
+        assert!(!path.with_extension("tmp").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,265 @@
+use std::path::Path;
+
+use data::{self, DataError};
+use ecs::spawn::Spawn;
+
+pub const DIFFICULTY_PATH: &str = "assets/difficulty.toml";
+
+/// How hard the game plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Lunatic,
+}
+
+/// Multipliers a difficulty applies to patterns and enemies. Added to the
+/// world as a resource alongside the `Difficulty` itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scaling {
+    /// Bullets per volley.
+    pub bullet_count: f64,
+    pub bullet_speed: f64,
+    /// Volleys per second; intervals between volleys are divided by this.
+    pub fire_rate: f64,
+    /// Health of enemies and boss phases.
+    pub enemy_health: f64,
+}
+
+/// Replacements for some of a difficulty's built in multipliers, as read
+/// from `assets/difficulty.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScalingOverrides {
+    pub bullet_count: Option<f64>,
+    pub bullet_speed: Option<f64>,
+    pub fire_rate: Option<f64>,
+    pub enemy_health: Option<f64>,
+}
+
+/// An optional value for each difficulty, so data files can override a
+/// parameter on some difficulties and leave the rest scaled as usual.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerDifficulty<T> {
+    pub easy: Option<T>,
+    pub normal: Option<T>,
+    pub hard: Option<T>,
+    pub lunatic: Option<T>,
+}
+
+impl Difficulty {
+    pub fn all() -> [Difficulty; 4] {
+        [
+            Difficulty::Easy,
+            Difficulty::Normal,
+            Difficulty::Hard,
+            Difficulty::Lunatic,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Lunatic => "Lunatic",
+        }
+    }
+
+    /// The multipliers this difficulty applies when no data overrides them.
+    pub fn scaling(&self) -> Scaling {
+        let (bullet_count, bullet_speed, fire_rate, enemy_health) = match *self {
+            Difficulty::Easy => (0.5, 0.8, 0.6, 0.8),
+            Difficulty::Normal => (1.0, 1.0, 1.0, 1.0),
+            Difficulty::Hard => (1.5, 1.15, 1.3, 1.25),
+            Difficulty::Lunatic => (2.0, 1.3, 1.6, 1.5),
+        };
+        Scaling {
+            bullet_count,
+            bullet_speed,
+            fire_rate,
+            enemy_health,
+        }
+    }
+
+    /// This difficulty's multipliers with any overrides from the data file
+    /// at `path` applied. Every difficulty's overrides are checked, not
+    /// just this one's.
+    pub fn load_scaling<P: AsRef<Path>>(&self, path: P) -> Result<Scaling, DataError> {
+        let table: PerDifficulty<ScalingOverrides> = data::load_toml(path)?;
+        for &difficulty in &Difficulty::all() {
+            if let Some(overrides) = table.get(difficulty) {
+                overrides.validate()?;
+            }
+        }
+        match table.get(*self) {
+            Some(overrides) => self.scaling().with_overrides(overrides),
+            None => Ok(self.scaling()),
+        }
+    }
+}
+
+impl Default for Difficulty {
+    fn default() -> Difficulty {
+        Difficulty::Normal
+    }
+}
+
+impl Scaling {
+    pub fn with_overrides(&self, overrides: &ScalingOverrides) -> Result<Scaling, DataError> {
+        overrides.validate()?;
+        Ok(Scaling {
+            bullet_count: overrides.bullet_count.unwrap_or(self.bullet_count),
+            bullet_speed: overrides.bullet_speed.unwrap_or(self.bullet_speed),
+            fire_rate: overrides.fire_rate.unwrap_or(self.fire_rate),
+            enemy_health: overrides.enemy_health.unwrap_or(self.enemy_health),
+        })
+    }
+
+    /// Scales a count of bullets, keeping at least one.
+    pub fn count(&self, count: u32) -> u32 {
+        ((count as f64 * self.bullet_count).round() as u32).max(1)
+    }
+
+    /// Scales the health of anything that isn't a bullet.
+    pub fn scale_spawn(&self, spawn: &mut Spawn) {
+        if spawn.bullet.is_none() {
+            spawn.health = spawn.health.map(|h| h * self.enemy_health);
+        }
+    }
+}
+
+impl ScalingOverrides {
+    /// Every multiplier must be finite and above zero; a fire rate of zero,
+    /// for one, would stop patterns and scripts firing altogether.
+    pub fn validate(&self) -> Result<(), DataError> {
+        let multipliers = [
+            ("bullet_count", self.bullet_count),
+            ("bullet_speed", self.bullet_speed),
+            ("fire_rate", self.fire_rate),
+            ("enemy_health", self.enemy_health),
+        ];
+        for &(name, value) in &multipliers {
+            if let Some(value) = value {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(DataError::Invalid(format!(
+                        "{} must be a positive number, not {}",
+                        name, value
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Scaling {
+    fn default() -> Scaling {
+        Difficulty::Normal.scaling()
+    }
+}
+
+impl<T> PerDifficulty<T> {
+    pub fn get(&self, difficulty: Difficulty) -> Option<&T> {
+        match difficulty {
+            Difficulty::Easy => self.easy.as_ref(),
+            Difficulty::Normal => self.normal.as_ref(),
+            Difficulty::Hard => self.hard.as_ref(),
+            Difficulty::Lunatic => self.lunatic.as_ref(),
+        }
+    }
+
+    /// The value for `difficulty` if there is one, or `scaled` otherwise.
+    pub fn get_or<F: FnOnce() -> T>(&self, difficulty: Difficulty, scaled: F) -> T
+    where
+        T: Clone,
+    {
+        self.get(difficulty).cloned().unwrap_or_else(scaled)
+    }
+}
+
+impl<T> Default for PerDifficulty<T> {
+    fn default() -> PerDifficulty<T> {
+        PerDifficulty {
+            easy: None,
+            normal: None,
+            hard: None,
+            lunatic: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    use data::DataError;
+    use super::{Difficulty, ScalingOverrides};
+
+    fn load(name: &str, contents: &str) -> Result<super::Scaling, DataError> {
+        let path = env::temp_dir().join(format!("difficulty_test_{}.toml", name));
+        File::create(&path)
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+        let scaling = Difficulty::Hard.load_scaling(&path);
+        let _ = fs::remove_file(&path);
+        scaling
+    }
+
+    fn invalid<T>(result: Result<T, DataError>) -> bool {
+        match result {
+            Err(DataError::Invalid(_)) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn overrides_replace_only_what_they_set() {
+        let base = Difficulty::Hard.scaling();
+        let overrides = ScalingOverrides {
+            fire_rate: Some(2.0),
+            ..ScalingOverrides::default()
+        };
+        let scaling = base.with_overrides(&overrides).unwrap();
+
+        assert_eq!(scaling.fire_rate, 2.0);
+        assert_eq!(scaling.bullet_count, base.bullet_count);
+        assert_eq!(scaling.bullet_speed, base.bullet_speed);
+        assert_eq!(scaling.enemy_health, base.enemy_health);
+    }
+
+    #[test]
+    fn overrides_must_be_positive_and_finite() {
+        let base = Difficulty::Normal.scaling();
+        for &value in &[0.0, -1.0, ::std::f64::NAN, ::std::f64::INFINITY] {
+            let overrides = [
+                ScalingOverrides { bullet_count: Some(value), ..ScalingOverrides::default() },
+                ScalingOverrides { bullet_speed: Some(value), ..ScalingOverrides::default() },
+                ScalingOverrides { fire_rate: Some(value), ..ScalingOverrides::default() },
+                ScalingOverrides { enemy_health: Some(value), ..ScalingOverrides::default() },
+            ];
+            for overrides in &overrides {
+                assert!(invalid(base.with_overrides(overrides)), "{:?}", overrides);
+            }
+        }
+    }
+
+    #[test]
+    fn loader_applies_this_difficultys_overrides() {
+        let scaling = load("valid", "[hard]\nfire_rate = 3.0\n").unwrap();
+        assert_eq!(scaling.fire_rate, 3.0);
+        assert_eq!(scaling.bullet_count, Difficulty::Hard.scaling().bullet_count);
+    }
+
+    #[test]
+    fn loader_rejects_bad_multipliers_on_any_difficulty() {
+        assert!(invalid(load("zero", "[hard]\nfire_rate = 0.0\n")));
+        assert!(invalid(load("negative", "[easy]\nenemy_health = -2.0\n")));
+        assert!(invalid(load("normal", "[normal]\nbullet_count = 0.0\n")));
+    }
+}
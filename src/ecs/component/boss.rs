@@ -0,0 +1,265 @@
+use std::f64::consts::PI;
+
+use specs::{self, ReadStorage, WriteStorage, Join};
+use cgmath::Vector2;
+use game_time::GameTime;
+
+use ecs::component::Component;
+use ecs::component::pos::Movable;
+use ecs::component::bullet::{Bullet, Faction};
+use ecs::component::collision::Health;
+use ecs::event::{BossPhaseEnded, EventChannel, LifeLost, ReaderId};
+use ecs::component::controller::Control;
+use ecs::spawn::SpawnQueue;
+use pattern::{PatternSet, PatternSpec};
+use difficulty::{Difficulty, PerDifficulty, Scaling};
+
+/// How a boss moves during a phase, relative to where the phase began.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Movement {
+    Still,
+    /// Side to side motion `amplitude` pixels either way.
+    Sway { amplitude: f64, period: f64 },
+    Circle { radius: f64, period: f64 },
+}
+
+/// One health bar's worth of a boss fight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BossPhase {
+    pub name: String,
+    pub health: f64,
+    /// Health on particular difficulties, used instead of scaling `health`.
+    #[serde(default)]
+    pub health_by_difficulty: PerDifficulty<f64>,
+    pub time_limit: f64,
+    #[serde(default)]
+    pub patterns: Vec<PatternSpec>,
+    #[serde(default = "default_movement")]
+    pub movement: Movement,
+    /// Awarded when the phase is beaten without the player losing a life.
+    #[serde(default)]
+    pub bonus: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Boss {
+    pub name: String,
+    phases: Vec<BossPhase>,
+    current: usize,
+    phase_time: f64,
+    hit_during_phase: bool,
+    started: bool,
+}
+
+/// What the HUD needs to draw the active boss's health bar and timer.
+#[derive(Debug, Clone)]
+pub struct BossDisplay {
+    pub name: String,
+    pub phase_name: String,
+    pub health_fraction: f64,
+    pub time_left: f64,
+    pub phases_remaining: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BossStatus {
+    pub display: Option<BossDisplay>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BossSystem {
+    lives_lost: Option<ReaderId>,
+}
+
+#[derive(SystemData)]
+pub struct BossSystemData<'a> {
+    vel: WriteStorage<'a, Movable>,
+    boss: WriteStorage<'a, Boss>,
+    health: WriteStorage<'a, Health>,
+    control: WriteStorage<'a, Control>,
+    bullet: ReadStorage<'a, Bullet>,
+    entities: specs::Entities<'a>,
+    time: specs::Fetch<'a, GameTime>,
+    lives_lost: specs::Fetch<'a, EventChannel<LifeLost>>,
+    phase_ends: specs::FetchMut<'a, EventChannel<BossPhaseEnded>>,
+    status: specs::FetchMut<'a, BossStatus>,
+    difficulty: specs::Fetch<'a, Difficulty>,
+    scaling: specs::Fetch<'a, Scaling>,
+    spawns: specs::FetchMut<'a, SpawnQueue>,
+}
+
+fn default_movement() -> Movement {
+    Movement::Still
+}
+
+impl Movement {
+    pub fn velocity(&self, t: f64) -> Vector2<f64> {
+        match *self {
+            Movement::Still => Vector2::new(0.0, 0.0),
+            Movement::Sway { amplitude, period } => {
+                let w = 2.0 * PI / period;
+                Vector2::new(amplitude * w * (w * t).cos(), 0.0)
+            }
+            Movement::Circle { radius, period } => {
+                let w = 2.0 * PI / period;
+                let (sin, cos) = (w * t).sin_cos();
+                Vector2::new(-radius * w * sin, radius * w * cos)
+            }
+        }
+    }
+}
+
+impl Boss {
+    pub fn new(name: String, phases: Vec<BossPhase>) -> Boss {
+        Boss {
+            name,
+            phases,
+            current: 0,
+            phase_time: 0.0,
+            hit_during_phase: false,
+            started: false,
+        }
+    }
+
+    /// Has the fight begin at `phase` rather than the first phase.
+    pub fn starting_at(mut self, phase: usize) -> Boss {
+        self.current = phase.min(self.phases.len().saturating_sub(1));
+        self
+    }
+
+    pub fn phase(&self) -> Option<&BossPhase> {
+        self.phases.get(self.current)
+    }
+    pub fn phase_index(&self) -> usize {
+        self.current
+    }
+    pub fn phase_count(&self) -> usize {
+        self.phases.len()
+    }
+
+    fn begin_phase(
+        &mut self,
+        entity: specs::Entity,
+        health: &mut Health,
+        control: &mut WriteStorage<Control>,
+        difficulty: Difficulty,
+        scaling: &Scaling,
+    ) {
+        self.phase_time = 0.0;
+        self.hit_during_phase = false;
+        self.started = true;
+
+        if let Some(phase) = self.phases.get(self.current) {
+            *health = Health::new(phase.health_by_difficulty.get_or(difficulty, || {
+                phase.health * scaling.enemy_health
+            }));
+            control.insert(
+                entity,
+                Control::new(Box::new(PatternSet::new(phase.patterns.clone()))),
+            );
+        }
+    }
+}
+
+impl specs::Component for Boss {
+    type Storage = specs::HashMapStorage<Self>;
+}
+
+impl Component for Boss {
+    fn name(&self) -> &str {
+        "Boss"
+    }
+}
+
+impl BossStatus {
+    pub fn new() -> BossStatus {
+        BossStatus { display: None }
+    }
+}
+
+impl BossSystem {
+    pub fn new() -> BossSystem {
+        BossSystem { lives_lost: None }
+    }
+}
+
+impl<'a> specs::System<'a> for BossSystem {
+    type SystemData = BossSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let dt = data.time.elapsed_game_time().as_seconds();
+        let mut clear_bullets = false;
+
+        let lives_lost = &data.lives_lost;
+        let reader = self.lives_lost
+            .get_or_insert_with(|| lives_lost.register_reader());
+        let life_lost = lives_lost.read(reader).next().is_some();
+
+        data.status.display = None;
+        let difficulty = *data.difficulty;
+        let scaling = &*data.scaling;
+
+        for (entity, boss, health) in (&*data.entities, &mut data.boss, &mut data.health).join() {
+            if !boss.started {
+                boss.begin_phase(entity, health, &mut data.control, difficulty, scaling);
+            }
+
+            boss.phase_time += dt;
+            if life_lost {
+                boss.hit_during_phase = true;
+            }
+
+            let (time_limit, bonus, velocity) = match boss.phase() {
+                Some(phase) => (
+                    phase.time_limit,
+                    phase.bonus,
+                    phase.movement.velocity(boss.phase_time),
+                ),
+                None => continue,
+            };
+
+            if let Some(vel) = data.vel.get_mut(entity) {
+                vel.0 = velocity;
+            }
+
+            let defeated = health.is_dead();
+            if defeated || boss.phase_time >= time_limit {
+                clear_bullets = true;
+                data.phase_ends.single_write(BossPhaseEnded {
+                    boss: entity,
+                    defeated,
+                    bonus: if defeated && !boss.hit_during_phase {
+                        bonus
+                    } else {
+                        0
+                    },
+                });
+
+                boss.current += 1;
+                if boss.current >= boss.phases.len() {
+                    data.spawns.despawn(entity);
+                    continue;
+                }
+                boss.begin_phase(entity, health, &mut data.control, difficulty, scaling);
+            }
+
+            let phase = &boss.phases[boss.current];
+            data.status.display = Some(BossDisplay {
+                name: boss.name.clone(),
+                phase_name: phase.name.clone(),
+                health_fraction: health.fraction(),
+                time_left: (phase.time_limit - boss.phase_time).max(0.0),
+                phases_remaining: boss.phases.len() - boss.current - 1,
+            });
+        }
+
+        if clear_bullets {
+            for (entity, bullet) in (&*data.entities, &data.bullet).join() {
+                if bullet.faction == Faction::Enemy {
+                    data.spawns.despawn(entity);
+                }
+            }
+        }
+    }
+}
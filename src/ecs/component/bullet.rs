@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use specs::{self, ReadStorage, Join};
+use game_time::GameTime;
+
+use ecs::component::Component;
+use ecs::component::pos::Position;
+use ecs::component::collision::Health;
+use ecs::component::player::Player;
+use ecs::component::boss::Boss;
+use ecs::spawn::SpawnQueue;
+use world;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Faction {
+    Player,
+    Enemy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bullet {
+    pub faction: Faction,
+    pub damage: f64,
+    /// The graze for this bullet has been credited.
+    #[serde(default)]
+    pub grazed: bool,
+    /// Inside a graze radius, credited once it leaves without hitting.
+    #[serde(default)]
+    pub grazing: bool,
+}
+
+/// Removes bullets that have left the playfield.
+#[derive(Debug, Clone)]
+pub struct BulletCleanupSystem;
+
+#[derive(SystemData)]
+pub struct BulletCleanupSystemData<'a> {
+    pos: ReadStorage<'a, Position>,
+    bullet: ReadStorage<'a, Bullet>,
+    entities: specs::Entities<'a>,
+    spawns: specs::FetchMut<'a, SpawnQueue>,
+}
+
+/// Removes enemies that have stayed outside the playfield for longer than
+/// `ENEMY_GRACE_PERIOD`, so those that fly off don't build up over a stage.
+/// The grace period lets enemies spawned off-screen fly in first.
+#[derive(Debug, Clone, Default)]
+pub struct EnemyCleanupSystem {
+    /// Seconds each enemy has spent outside the playfield.
+    offscreen: HashMap<specs::Entity, f64>,
+}
+
+#[derive(SystemData)]
+pub struct EnemyCleanupSystemData<'a> {
+    pos: ReadStorage<'a, Position>,
+    health: ReadStorage<'a, Health>,
+    player: ReadStorage<'a, Player>,
+    boss: ReadStorage<'a, Boss>,
+    entities: specs::Entities<'a>,
+    time: specs::Fetch<'a, GameTime>,
+    spawns: specs::FetchMut<'a, SpawnQueue>,
+}
+
+const CLEANUP_MARGIN: f64 = 64.0;
+const ENEMY_GRACE_PERIOD: f64 = 3.0;
+
+impl Bullet {
+    pub fn new(faction: Faction) -> Bullet {
+        Bullet {
+            faction,
+            damage: 1.0,
+            grazed: false,
+            grazing: false,
+        }
+    }
+}
+
+impl EnemyCleanupSystem {
+    pub fn new() -> EnemyCleanupSystem {
+        EnemyCleanupSystem { offscreen: HashMap::new() }
+    }
+}
+
+impl specs::Component for Bullet {
+    type Storage = specs::VecStorage<Self>;
+}
+
+impl Component for Bullet {
+    fn name(&self) -> &str {
+        "Bullet"
+    }
+}
+
+impl<'a> specs::System<'a> for BulletCleanupSystem {
+    type SystemData = BulletCleanupSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        for (entity, &Position(ref pos), _) in (&*data.entities, &data.pos, &data.bullet).join() {
+            if !world::in_playfield(pos, CLEANUP_MARGIN) {
+                data.spawns.despawn(entity);
+            }
+        }
+    }
+}
+
+impl<'a> specs::System<'a> for EnemyCleanupSystem {
+    type SystemData = EnemyCleanupSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let dt = data.time.elapsed_game_time().as_seconds();
+        let mut offscreen = HashMap::new();
+
+        for (entity, &Position(ref pos), _) in (&*data.entities, &data.pos, &data.health).join() {
+            if data.player.get(entity).is_some() || data.boss.get(entity).is_some() ||
+                world::in_playfield(pos, CLEANUP_MARGIN)
+            {
+                continue;
+            }
+
+            let time = self.offscreen.get(&entity).map_or(0.0, |t| t + dt);
+            if time > ENEMY_GRACE_PERIOD {
+                data.spawns.despawn(entity);
+            } else {
+                offscreen.insert(entity, time);
+            }
+        }
+
+        // Entities that came back or were deleted drop out of the map.
+        self.offscreen = offscreen;
+    }
+}
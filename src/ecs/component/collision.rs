@@ -0,0 +1,142 @@
+use specs::{self, ReadStorage, WriteStorage, Join};
+use cgmath::{Vector2, InnerSpace};
+
+use ecs::component::Component;
+use ecs::component::pos::Position;
+use ecs::component::bullet::{Bullet, Faction};
+use ecs::component::player::Player;
+use ecs::component::boss::Boss;
+use ecs::event::{EnemyHit, EntityDied, EventChannel, PlayerHit};
+use ecs::spawn::SpawnQueue;
+
+/// Circular hit area centered on the entity's position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hitbox {
+    pub radius: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Health {
+    pub current: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollisionSystem;
+
+#[derive(SystemData)]
+pub struct CollisionSystemData<'a> {
+    pos: ReadStorage<'a, Position>,
+    hitbox: ReadStorage<'a, Hitbox>,
+    bullet: ReadStorage<'a, Bullet>,
+    player: ReadStorage<'a, Player>,
+    boss: ReadStorage<'a, Boss>,
+    health: WriteStorage<'a, Health>,
+    entities: specs::Entities<'a>,
+    spawns: specs::FetchMut<'a, SpawnQueue>,
+    enemy_hits: specs::FetchMut<'a, EventChannel<EnemyHit>>,
+    player_hits: specs::FetchMut<'a, EventChannel<PlayerHit>>,
+    deaths: specs::FetchMut<'a, EventChannel<EntityDied>>,
+}
+
+impl Hitbox {
+    pub fn new(radius: f64) -> Hitbox {
+        Hitbox { radius }
+    }
+}
+
+impl Health {
+    pub fn new(max: f64) -> Health {
+        Health { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+    pub fn fraction(&self) -> f64 {
+        if self.max > 0.0 {
+            (self.current / self.max).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl specs::Component for Hitbox {
+    type Storage = specs::VecStorage<Self>;
+}
+
+impl Component for Hitbox {
+    fn name(&self) -> &str {
+        "Hitbox"
+    }
+}
+
+impl specs::Component for Health {
+    type Storage = specs::VecStorage<Self>;
+}
+
+impl Component for Health {
+    fn name(&self) -> &str {
+        "Health"
+    }
+}
+
+pub fn overlaps(a: Vector2<f64>, a_radius: f64, b: Vector2<f64>, b_radius: f64) -> bool {
+    let reach = a_radius + b_radius;
+    (a - b).magnitude2() <= reach * reach
+}
+
+impl<'a> specs::System<'a> for CollisionSystem {
+    type SystemData = CollisionSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let targets: Vec<_> = (&*data.entities, &data.pos, &data.hitbox)
+            .join()
+            .map(|(e, &Position(pos), hitbox)| {
+                (e, pos, hitbox.radius, data.player.get(e).is_some())
+            })
+            .collect();
+
+        for (bullet_entity, &Position(bullet_pos), bullet, bullet_hitbox) in
+            (&*data.entities, &data.pos, &data.bullet, &data.hitbox).join()
+        {
+            for &(target, target_pos, target_radius, is_player) in &targets {
+                let hostile = match bullet.faction {
+                    Faction::Player => !is_player && data.health.get(target).is_some(),
+                    Faction::Enemy => is_player,
+                };
+                if !hostile || data.bullet.get(target).is_some() ||
+                    !overlaps(bullet_pos, bullet_hitbox.radius, target_pos, target_radius)
+                {
+                    continue;
+                }
+
+                data.spawns.despawn(bullet_entity);
+                if is_player {
+                    data.player_hits.single_write(PlayerHit {
+                        entity: target,
+                        pos: target_pos,
+                    });
+                } else if let Some(health) = data.health.get_mut(target) {
+                    health.current -= bullet.damage;
+                    data.enemy_hits.single_write(EnemyHit {
+                        entity: target,
+                        pos: bullet_pos,
+                    });
+                }
+                break;
+            }
+        }
+
+        // Bosses handle running out of health themselves by changing phase.
+        for (entity, &Position(pos), health) in (&*data.entities, &data.pos, &data.health).join() {
+            if health.is_dead() && data.player.get(entity).is_none() &&
+                data.boss.get(entity).is_none()
+            {
+                data.spawns.despawn(entity);
+                data.deaths.single_write(EntityDied { entity, pos });
+            }
+        }
+    }
+}
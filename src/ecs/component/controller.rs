@@ -1,9 +1,15 @@
 use specs::{self, ReadStorage, WriteStorage, Join};
+use cgmath::{Vector2, Zero};
 use game_time::GameTime;
 
 use ecs::{component, Component};
-use controller::Controller;
+use ecs::spawn::{Spawn, SpawnQueue};
+use ecs::prefab::{Prefab, PrefabLibrary};
+use controller::{Action, Controller, ControllerContext, ControllerSave};
 use input::InputManager;
+use input::bindings::KeyBindings;
+use script::ScriptLibrary;
+use difficulty::{Difficulty, Scaling};
 
 pub struct Control {
     controller: Box<Controller + Send + Sync>,
@@ -17,15 +23,31 @@ pub struct ControlSystemData<'a> {
     pos: ReadStorage<'a, component::pos::Position>,
     vel: WriteStorage<'a, component::pos::Movable>,
     controller: WriteStorage<'a, Control>,
+    player: WriteStorage<'a, component::player::Player>,
     entity: specs::Entities<'a>,
     time: specs::Fetch<'a, GameTime>,
     input: specs::Fetch<'a, InputManager>,
+    bindings: specs::Fetch<'a, KeyBindings>,
+    prefabs: specs::Fetch<'a, PrefabLibrary>,
+    difficulty: specs::Fetch<'a, Difficulty>,
+    scaling: specs::Fetch<'a, Scaling>,
+    spawns: specs::FetchMut<'a, SpawnQueue>,
 }
 
 impl Control {
     pub fn new(controller: Box<Controller + Send + Sync>) -> Control {
         Control { controller }
     }
+
+    /// A `Control` running the named script, if the library has it.
+    pub fn script(library: &ScriptLibrary, name: &str) -> Option<Control> {
+        library.controller(name).map(|c| Control::new(Box::new(c)))
+    }
+
+    /// The controller's state for a save file, if it can be saved.
+    pub fn controller_save(&self) -> Option<ControllerSave> {
+        self.controller.save()
+    }
 }
 
 impl Component for Control {
@@ -44,18 +66,81 @@ impl<'a> specs::System<'a> for ControlSystem {
     fn run(&mut self, mut data: Self::SystemData) {
         let time = &*data.time;
         let input = &*data.input;
+        let bindings = &*data.bindings;
+        let scaling = &*data.scaling;
+
+        let positions: Vec<_> = (&*data.entity, &data.pos)
+            .join()
+            .map(|(e, &component::pos::Position(pos))| (e, pos))
+            .collect();
+        let player_pos = (&data.pos, &data.player)
+            .join()
+            .map(|(&component::pos::Position(pos), _)| pos)
+            .next();
 
-        for (mut entity, controller, pos, mut vel) in
-            (
-                &*data.entity,
-                &mut data.controller,
-                &data.pos,
-                &mut data.vel,
-            ).join()
+        for (entity, controller, &component::pos::Position(pos)) in
+            (&*data.entity, &mut data.controller, &data.pos).join()
         {
-            controller
-                .controller
-                .do_actions(&mut entity, &time, &mut vel.0, &input);
+            let vel = data.vel.get(entity).map_or(Vector2::zero(), |v| v.0);
+            let ctx = ControllerContext {
+                entity,
+                time,
+                input,
+                bindings,
+                pos,
+                vel,
+                player_pos,
+                entities: &positions,
+                difficulty: *data.difficulty,
+                scaling,
+            };
+
+            for action in controller.controller.do_actions(&ctx) {
+                match action {
+                    Action::Move(new_vel) => {
+                        if let Some(vel) = data.vel.get_mut(entity) {
+                            vel.0 = new_vel;
+                            continue;
+                        }
+                        data.vel.insert(entity, component::pos::Movable(new_vel));
+                    }
+                    Action::Focus(focused) => {
+                        if let Some(player) = data.player.get_mut(entity) {
+                            player.focused = focused;
+                        }
+                    }
+                    Action::Shoot(firing) => {
+                        if let Some(player) = data.player.get_mut(entity) {
+                            player.firing = firing;
+                        }
+                    }
+                    Action::Bomb => {
+                        if let Some(player) = data.player.get_mut(entity) {
+                            player.bombing = true;
+                        }
+                    }
+                    Action::Fire(projectile) => {
+                        data.spawns.spawn(Spawn::from_projectile(pos, projectile));
+                    }
+                    Action::Spawn(mut spawn) => {
+                        scaling.scale_spawn(&mut spawn);
+                        data.spawns.spawn(spawn);
+                    }
+                    Action::SpawnPrefab { name, offset, vel } => {
+                        let overrides = vel.map(|v| Prefab {
+                            velocity: Some([v.x, v.y]),
+                            ..Prefab::default()
+                        });
+                        if let Some(mut spawn) =
+                            data.prefabs.spawn(&name, pos + offset, overrides.as_ref())
+                        {
+                            scaling.scale_spawn(&mut spawn);
+                            data.spawns.spawn(spawn);
+                        }
+                    }
+                    Action::Despawn(target) => data.spawns.despawn(target),
+                }
+            }
         }
     }
 }
@@ -0,0 +1,85 @@
+use specs::{self, ReadStorage, WriteStorage, Join};
+
+use ecs::component::Component;
+use ecs::component::pos::Position;
+use ecs::component::bullet::{Bullet, Faction};
+use ecs::component::collision::{self, Hitbox};
+use score::Score;
+use ecs::event::{EventChannel, Grazed};
+
+/// A second, larger radius around the player's hitbox. Enemy bullets that
+/// pass through it without hitting count as grazes, credited when they
+/// leave it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Graze {
+    pub radius: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GrazeSystem;
+
+#[derive(SystemData)]
+pub struct GrazeSystemData<'a> {
+    pos: ReadStorage<'a, Position>,
+    graze: ReadStorage<'a, Graze>,
+    hitbox: ReadStorage<'a, Hitbox>,
+    bullet: WriteStorage<'a, Bullet>,
+    score: specs::FetchMut<'a, Score>,
+    grazes: specs::FetchMut<'a, EventChannel<Grazed>>,
+}
+
+impl Graze {
+    pub fn new(radius: f64) -> Graze {
+        Graze { radius }
+    }
+}
+
+impl specs::Component for Graze {
+    type Storage = specs::HashMapStorage<Self>;
+}
+
+impl Component for Graze {
+    fn name(&self) -> &str {
+        "Graze"
+    }
+}
+
+impl<'a> specs::System<'a> for GrazeSystem {
+    type SystemData = GrazeSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let grazers: Vec<_> = (&data.pos, &data.graze, &data.hitbox)
+            .join()
+            .map(|(&Position(pos), graze, hitbox)| (pos, graze.radius, hitbox.radius))
+            .collect();
+
+        for (&Position(bullet_pos), bullet, bullet_hitbox) in
+            (&data.pos, &mut data.bullet, &data.hitbox).join()
+        {
+            if bullet.faction != Faction::Enemy || bullet.grazed {
+                continue;
+            }
+
+            let radius = bullet_hitbox.radius;
+            let mut inside = false;
+            let mut hit = false;
+            for &(pos, graze_radius, hit_radius) in &grazers {
+                hit |= collision::overlaps(bullet_pos, radius, pos, hit_radius);
+                inside |= collision::overlaps(bullet_pos, radius, pos, graze_radius);
+            }
+
+            if hit {
+                // A bullet that hits never counts as a graze.
+                bullet.grazing = false;
+                bullet.grazed = true;
+            } else if inside {
+                bullet.grazing = true;
+            } else if bullet.grazing {
+                bullet.grazing = false;
+                bullet.grazed = true;
+                data.score.on_graze();
+                data.grazes.single_write(Grazed { pos: bullet_pos });
+            }
+        }
+    }
+}
@@ -1,16 +1,56 @@
 use specs;
 
+use ecs::save::SaveRegistry;
+
 pub mod pos;
 pub mod render;
 pub mod controller;
+pub mod bullet;
+pub mod player;
+pub mod collision;
+pub mod boss;
+pub mod graze;
+pub mod pickup;
+pub mod weapon;
 
 pub trait Component {
     fn name(&self) -> &str;
 }
 
+/// Registers every component's storage, and adds a `SaveRegistry` resource
+/// listing those that can be written to save files.
 pub fn register_components(world: &mut specs::World) {
     world.register::<pos::Position>();
     world.register::<pos::Movable>();
     world.register::<render::Render>();
     world.register::<controller::Control>();
+    world.register::<bullet::Bullet>();
+    world.register::<player::Player>();
+    world.register::<collision::Hitbox>();
+    world.register::<collision::Health>();
+    world.register::<boss::Boss>();
+    world.register::<graze::Graze>();
+    world.register::<pickup::Pickup>();
+    world.register::<pickup::Collector>();
+    world.register::<pickup::Drops>();
+    world.register::<weapon::Weapon>();
+    world.register::<weapon::WeaponOption>();
+
+    let mut saves = SaveRegistry::new();
+    saves.register_component::<pos::Position>("position");
+    saves.register_component::<pos::Movable>("movable");
+    saves.register_component::<render::Render>("render");
+    saves.register_component::<bullet::Bullet>("bullet");
+    saves.register_component::<player::Player>("player");
+    saves.register_component::<collision::Hitbox>("hitbox");
+    saves.register_component::<collision::Health>("health");
+    saves.register_component::<graze::Graze>("graze");
+    saves.register_component::<pickup::Pickup>("pickup");
+    saves.register_component::<pickup::Collector>("collector");
+    saves.register_component::<pickup::Drops>("drops");
+    saves.register_component::<weapon::Weapon>("weapon");
+    saves.register_component::<weapon::WeaponOption>("weapon_option");
+    saves.register_component::<controller::Control>("control");
+    saves.register_component::<boss::Boss>("boss");
+    world.add_resource(saves);
 }
@@ -0,0 +1,219 @@
+use specs::{self, ReadStorage, WriteStorage, Join};
+use cgmath::{Vector2, InnerSpace};
+use graphics;
+use game_time::GameTime;
+
+use ecs::component::Component;
+use ecs::component::pos::{Position, Movable};
+use ecs::component::player::{Player, PlayerStats};
+use ecs::component::collision;
+use ecs::event::{EntityDied, EventChannel, PickupCollected, ReaderId};
+use ecs::spawn::{Spawn, SpawnQueue};
+use render::{RectangleGraphic, RenderGraphicState};
+use score::Score;
+use world;
+
+const GRAVITY: f64 = 300.0;
+const MAX_FALL_SPEED: f64 = 180.0;
+const LAUNCH_SPEED: f64 = 150.0;
+const DROP_SCATTER: f64 = 16.0;
+const PICKUP_SIZE: f64 = 10.0;
+const POWER_ITEM_VALUE: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PickupKind {
+    Power,
+    Point,
+    Life,
+    Bomb,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    /// Once set the pickup homes in on the player until collected.
+    pub magnetized: bool,
+}
+
+/// Lets the player collect pickups and pull them in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Collector {
+    pub radius: f64,
+    /// Every pickup is pulled in while the player is above this line.
+    pub magnet_line: f64,
+    /// Pickups within this distance are pulled in while focused.
+    pub focus_radius: f64,
+    pub magnet_speed: f64,
+}
+
+/// Pickups left behind when the entity is killed.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Drops(pub Vec<PickupKind>);
+
+#[derive(Debug, Clone, Default)]
+pub struct PickupSystem {
+    deaths: Option<ReaderId>,
+}
+
+#[derive(SystemData)]
+pub struct PickupSystemData<'a> {
+    pos: ReadStorage<'a, Position>,
+    vel: WriteStorage<'a, Movable>,
+    pickup: WriteStorage<'a, Pickup>,
+    collector: ReadStorage<'a, Collector>,
+    player: ReadStorage<'a, Player>,
+    drops: ReadStorage<'a, Drops>,
+    entities: specs::Entities<'a>,
+    time: specs::Fetch<'a, GameTime>,
+    deaths: specs::Fetch<'a, EventChannel<EntityDied>>,
+    collected: specs::FetchMut<'a, EventChannel<PickupCollected>>,
+    stats: specs::FetchMut<'a, PlayerStats>,
+    score: specs::FetchMut<'a, Score>,
+    spawns: specs::FetchMut<'a, SpawnQueue>,
+}
+
+impl PickupKind {
+    pub fn color(&self) -> [f32; 4] {
+        match *self {
+            PickupKind::Power => [0.9, 0.1, 0.1, 1.0],
+            PickupKind::Point => [0.1, 0.3, 0.9, 1.0],
+            PickupKind::Life => [0.9, 0.4, 0.8, 1.0],
+            PickupKind::Bomb => [0.1, 0.7, 0.2, 1.0],
+        }
+    }
+
+    pub fn spawn(&self, pos: Vector2<f64>) -> Spawn {
+        Spawn::new(pos)
+            .with_velocity(Vector2::new(0.0, -LAUNCH_SPEED))
+            .with_render(RenderGraphicState::Rectangle(RectangleGraphic {
+                rect: graphics::Rectangle::new(self.color()),
+                width: PICKUP_SIZE,
+                height: PICKUP_SIZE,
+            }))
+            .with_pickup(Pickup::new(*self))
+    }
+}
+
+impl Pickup {
+    pub fn new(kind: PickupKind) -> Pickup {
+        Pickup {
+            kind,
+            magnetized: false,
+        }
+    }
+
+    fn apply(&self, stats: &mut PlayerStats, score: &mut Score) {
+        match self.kind {
+            PickupKind::Power => stats.add_power(POWER_ITEM_VALUE),
+            PickupKind::Point => score.on_point_item(),
+            PickupKind::Life => stats.lives += 1,
+            PickupKind::Bomb => stats.bombs += 1,
+        }
+    }
+}
+
+impl Collector {
+    pub fn new(radius: f64) -> Collector {
+        Collector {
+            radius,
+            magnet_line: world::PLAYFIELD_HEIGHT / 4.0,
+            focus_radius: 96.0,
+            magnet_speed: 600.0,
+        }
+    }
+}
+
+impl specs::Component for Pickup {
+    type Storage = specs::VecStorage<Self>;
+}
+
+impl Component for Pickup {
+    fn name(&self) -> &str {
+        "Pickup"
+    }
+}
+
+impl specs::Component for Collector {
+    type Storage = specs::HashMapStorage<Self>;
+}
+
+impl Component for Collector {
+    fn name(&self) -> &str {
+        "Collector"
+    }
+}
+
+impl specs::Component for Drops {
+    type Storage = specs::VecStorage<Self>;
+}
+
+impl Component for Drops {
+    fn name(&self) -> &str {
+        "Drops"
+    }
+}
+
+impl PickupSystem {
+    pub fn new() -> PickupSystem {
+        PickupSystem { deaths: None }
+    }
+}
+
+impl<'a> specs::System<'a> for PickupSystem {
+    type SystemData = PickupSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let dt = data.time.elapsed_game_time().as_seconds();
+
+        let deaths = &data.deaths;
+        let reader = self.deaths.get_or_insert_with(|| deaths.register_reader());
+        for kill in deaths.read(reader) {
+            if let Some(&Drops(ref drops)) = data.drops.get(kill.entity) {
+                let count = drops.len() as f64;
+                for (i, kind) in drops.iter().enumerate() {
+                    let offset = (i as f64 - (count - 1.0) / 2.0) * DROP_SCATTER;
+                    data.spawns.spawn(kind.spawn(kill.pos + Vector2::new(offset, 0.0)));
+                }
+            }
+        }
+
+        let collector = (&data.pos, &data.collector, &data.player)
+            .join()
+            .map(|(&Position(pos), collector, player)| (pos, collector.clone(), player.focused))
+            .next();
+
+        for (entity, &Position(pos), pickup, vel) in
+            (&*data.entities, &data.pos, &mut data.pickup, &mut data.vel).join()
+        {
+            if let Some((player_pos, ref collector, focused)) = collector {
+                if collision::overlaps(pos, 0.0, player_pos, collector.radius) {
+                    pickup.apply(&mut data.stats, &mut data.score);
+                    data.collected.single_write(PickupCollected {
+                        kind: pickup.kind,
+                        pos,
+                    });
+                    data.spawns.despawn(entity);
+                    continue;
+                }
+
+                let in_focus_range = focused &&
+                    (pos - player_pos).magnitude() <= collector.focus_radius;
+                if player_pos.y < collector.magnet_line || in_focus_range {
+                    pickup.magnetized = true;
+                }
+
+                if pickup.magnetized {
+                    vel.0 = (player_pos - pos).normalize() * collector.magnet_speed;
+                    continue;
+                }
+            }
+
+            vel.0 = Vector2::new(0.0, (vel.0.y + GRAVITY * dt).min(MAX_FALL_SPEED));
+
+            if pos.y > world::PLAYFIELD_HEIGHT + PICKUP_SIZE {
+                data.spawns.despawn(entity);
+            }
+        }
+    }
+}
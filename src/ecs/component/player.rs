@@ -0,0 +1,118 @@
+use specs::{self, ReadStorage, WriteStorage, Join};
+
+use ecs::component::Component;
+use ecs::component::pos::Position;
+use ecs::component::bullet::{Bullet, Faction};
+use ecs::event::{BombUsed, EventChannel};
+use ecs::spawn::SpawnQueue;
+
+pub const MAX_POWER: f64 = 4.0;
+
+/// Marks the entity controlled by the player.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Player {
+    /// Set while the focus (slow movement) key is held.
+    pub focused: bool,
+    /// Set while the shoot key is held.
+    pub firing: bool,
+    /// Set when the bomb key is pressed, until the `BombSystem` handles it.
+    #[serde(default)]
+    pub bombing: bool,
+}
+
+/// Lives, bombs and shot power carried by the player between deaths.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub lives: u32,
+    pub bombs: u32,
+    pub power: f64,
+}
+
+/// Spends a bomb when the player asks for one, clearing every enemy
+/// bullet.
+#[derive(Debug, Clone)]
+pub struct BombSystem;
+
+#[derive(SystemData)]
+pub struct BombSystemData<'a> {
+    pos: ReadStorage<'a, Position>,
+    player: WriteStorage<'a, Player>,
+    bullet: ReadStorage<'a, Bullet>,
+    entities: specs::Entities<'a>,
+    stats: specs::FetchMut<'a, PlayerStats>,
+    bombs: specs::FetchMut<'a, EventChannel<BombUsed>>,
+    spawns: specs::FetchMut<'a, SpawnQueue>,
+}
+
+impl Player {
+    pub fn new() -> Player {
+        Player {
+            focused: false,
+            firing: false,
+            bombing: false,
+        }
+    }
+}
+
+impl PlayerStats {
+    pub fn new() -> PlayerStats {
+        PlayerStats {
+            lives: 3,
+            bombs: 3,
+            power: 1.0,
+        }
+    }
+
+    pub fn add_power(&mut self, amount: f64) {
+        self.power = (self.power + amount).min(MAX_POWER);
+    }
+
+    /// Whole power level from 1 to 4, which selects the shot layout.
+    pub fn power_level(&self) -> u32 {
+        (self.power.floor() as u32).max(1).min(MAX_POWER as u32)
+    }
+}
+
+impl Default for PlayerStats {
+    fn default() -> PlayerStats {
+        PlayerStats::new()
+    }
+}
+
+impl<'a> specs::System<'a> for BombSystem {
+    type SystemData = BombSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let mut bombed = Vec::new();
+        for (&Position(pos), player) in (&data.pos, &mut data.player).join() {
+            if player.bombing {
+                player.bombing = false;
+                bombed.push(pos);
+            }
+        }
+
+        for pos in bombed {
+            if data.stats.bombs == 0 {
+                continue;
+            }
+            data.stats.bombs -= 1;
+
+            for (entity, bullet) in (&*data.entities, &data.bullet).join() {
+                if bullet.faction == Faction::Enemy {
+                    data.spawns.despawn(entity);
+                }
+            }
+            data.bombs.single_write(BombUsed { pos });
+        }
+    }
+}
+
+impl specs::Component for Player {
+    type Storage = specs::HashMapStorage<Self>;
+}
+
+impl Component for Player {
+    fn name(&self) -> &str {
+        "Player"
+    }
+}
@@ -1,42 +1,189 @@
-use specs::{self, ReadStorage, Join};
-use graphics;
-use opengl_graphics::{self, GlGraphics};
-use cgmath;
+use std::collections::{HashMap, HashSet};
+
+use specs::{self, ReadStorage, WriteStorage, Join};
 use game_time::GameTime;
-use render::RenderGraphicState;
+use render::{BlendMode, Canvas, DrawList, Layer, RenderGraphic, RenderGraphicState};
 
 use ecs::component::Component;
+use ecs::component::bullet::Bullet;
+use ecs::event::{EnemyHit, EventChannel, ReaderId};
+
+/// Seconds an enemy stays tinted white after being hit.
+const HIT_FLASH_DURATION: f64 = 0.08;
 
+/// Draws one kind of graphic. Registered with a `RendererRegistry` under
+/// the name returned by `RenderGraphicState::kind`.
 pub trait Renderer: Send + Sync {
-    fn render(
-        &self,
-        entity: &specs::Entity,
-        pso: cgmath::Vector2<f64>,
-        obj: &Render,
-        ctx: &mut graphics::Context,
-        gl: &mut GlGraphics,
-    );
+    fn render(&self, graphic: &RenderGraphic, canvas: &mut Canvas);
+}
+
+/// Renderers for every graphic kind, looked up by name when the draw list
+/// is drawn.
+pub struct RendererRegistry {
+    renderers: HashMap<String, Box<Renderer>>,
+    /// Kinds that have been drawn without a renderer, so each is only
+    /// reported once.
+    missing: HashSet<String>,
 }
 
+/// Draws `RenderGraphicState::Rectangle`.
+#[derive(Debug, Clone)]
+pub struct RectangleRenderer;
+
+/// Gathers every visible entity into the `DrawList` resource.
 #[derive(Debug, Clone)]
 pub struct RenderSystem;
 
+/// Flashes entities white when they take damage.
+#[derive(Debug, Clone, Default)]
+pub struct HitFlashSystem {
+    hits: Option<ReaderId>,
+}
+
+#[derive(SystemData)]
+pub struct HitFlashSystemData<'a> {
+    render: WriteStorage<'a, Render>,
+    hits: specs::Fetch<'a, EventChannel<EnemyHit>>,
+    time: specs::Fetch<'a, GameTime>,
+}
+
 #[derive(SystemData)]
 pub struct RenderSystemData<'a> {
     pos: ReadStorage<'a, super::pos::Position>,
     renderer: ReadStorage<'a, Render>,
+    bullet: ReadStorage<'a, Bullet>,
     entities: specs::Entities<'a>,
-    time: specs::Fetch<'a, GameTime>,
-    ctx: specs::Fetch<'a, graphics::Context>,
+    draw_list: specs::FetchMut<'a, DrawList>,
 }
 
 pub struct Render {
     pub state: RenderGraphicState,
+    pub blend: BlendMode,
+    /// Seconds of hit flash remaining.
+    pub flash: f64,
 }
 
 impl Render {
     pub fn new(state: RenderGraphicState) -> Render {
-        Render { state }
+        Render {
+            state,
+            blend: BlendMode::Alpha,
+            flash: 0.0,
+        }
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Render {
+        self.blend = blend;
+        self
+    }
+}
+
+impl RendererRegistry {
+    /// A registry with the built in renderers.
+    pub fn new() -> RendererRegistry {
+        let mut registry = RendererRegistry {
+            renderers: HashMap::new(),
+            missing: HashSet::new(),
+        };
+        registry.register("rectangle", RectangleRenderer);
+        registry
+    }
+
+    /// Adds or replaces the renderer for graphics of `kind`.
+    pub fn register<S: Into<String>, R: Renderer + 'static>(&mut self, kind: S, renderer: R) {
+        self.renderers.insert(kind.into(), Box::new(renderer));
+    }
+
+    pub fn get(&self, kind: &str) -> Option<&Renderer> {
+        self.renderers.get(kind).map(|r| &**r)
+    }
+
+    /// Logs that a graphic of `kind` couldn't be drawn, the first time it
+    /// happens.
+    pub fn report_missing(&mut self, kind: &str) {
+        if self.missing.insert(kind.to_string()) {
+            println!("No renderer registered for graphics of kind '{}'", kind);
+        }
+    }
+}
+
+impl Default for RendererRegistry {
+    fn default() -> RendererRegistry {
+        RendererRegistry::new()
+    }
+}
+
+impl Renderer for RectangleRenderer {
+    fn render(&self, graphic: &RenderGraphic, canvas: &mut Canvas) {
+        if let RenderGraphicState::Rectangle(ref rect) = graphic.state {
+            let mut color = rect.rect.color;
+            for c in &mut color[..3] {
+                *c += (1.0 - *c) * graphic.flash;
+            }
+            canvas.fill_rect(
+                [
+                    graphic.pos.x - rect.width / 2.0,
+                    graphic.pos.y - rect.height / 2.0,
+                    rect.width,
+                    rect.height,
+                ],
+                color,
+                graphic.blend == BlendMode::Additive,
+            );
+        }
+    }
+}
+
+impl HitFlashSystem {
+    pub fn new() -> HitFlashSystem {
+        HitFlashSystem { hits: None }
+    }
+}
+
+impl<'a> specs::System<'a> for HitFlashSystem {
+    type SystemData = HitFlashSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let dt = data.time.elapsed_game_time().as_seconds();
+        for render in (&mut data.render).join() {
+            render.flash = (render.flash - dt).max(0.0);
+        }
+
+        let hits = &data.hits;
+        let reader = self.hits.get_or_insert_with(|| hits.register_reader());
+        for hit in hits.read(reader) {
+            if let Some(render) = data.render.get_mut(hit.entity) {
+                render.flash = HIT_FLASH_DURATION;
+            }
+        }
+    }
+}
+
+impl<'a> specs::System<'a> for RenderSystem {
+    type SystemData = RenderSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        data.draw_list.clear();
+
+        for (entity, render, &super::pos::Position(pos)) in
+            (&*data.entities, &data.renderer, &data.pos).join()
+        {
+            let layer = if data.bullet.get(entity).is_some() {
+                Layer::Bullets
+            } else {
+                Layer::Ships
+            };
+            data.draw_list.push(RenderGraphic {
+                entity,
+                pos,
+                layer,
+                blend: render.blend,
+                flash: (render.flash / HIT_FLASH_DURATION).min(1.0) as f32,
+                state: render.state.clone(),
+            });
+        }
+
+        data.draw_list.sort();
     }
 }
 
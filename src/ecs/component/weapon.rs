@@ -0,0 +1,220 @@
+use std::f64::consts::PI;
+
+use specs::{self, ReadStorage, WriteStorage, Join};
+use cgmath::Vector2;
+use graphics;
+use game_time::GameTime;
+
+use controller::Projectile;
+use ecs::component::Component;
+use ecs::component::pos::Position;
+use ecs::component::bullet::Faction;
+use ecs::component::player::{Player, PlayerStats};
+use ecs::event::{EventChannel, ShotFired};
+use ecs::spawn::{Spawn, SpawnQueue};
+use render::{RectangleGraphic, RenderGraphicState};
+
+const SHOT_SPEED: f64 = 900.0;
+const OPTION_SIZE: f64 = 10.0;
+/// How quickly options close the distance to their place, per second.
+/// About a third of the way each frame at 60 frames per second.
+const OPTION_FOLLOW_RATE: f64 = 26.0;
+
+/// The player's main gun. Its layout depends on the power level and on
+/// whether the player is focused.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Weapon {
+    /// Seconds between volleys.
+    pub cooldown: f64,
+    timer: f64,
+}
+
+/// A satellite that follows its owner and fires alongside its weapon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeaponOption {
+    pub owner: specs::Entity,
+    pub index: u32,
+}
+
+/// A single bullet in a volley, relative to the shooter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shot {
+    pub offset: Vector2<f64>,
+    /// Radians away from straight up, positive to the right.
+    pub angle: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WeaponSystem;
+
+#[derive(SystemData)]
+pub struct WeaponSystemData<'a> {
+    pos: WriteStorage<'a, Position>,
+    weapon: WriteStorage<'a, Weapon>,
+    option: ReadStorage<'a, WeaponOption>,
+    player: ReadStorage<'a, Player>,
+    entities: specs::Entities<'a>,
+    time: specs::Fetch<'a, GameTime>,
+    stats: specs::Fetch<'a, PlayerStats>,
+    spawns: specs::FetchMut<'a, SpawnQueue>,
+    shots: specs::FetchMut<'a, EventChannel<ShotFired>>,
+}
+
+impl Weapon {
+    pub fn new(cooldown: f64) -> Weapon {
+        Weapon {
+            cooldown,
+            timer: 0.0,
+        }
+    }
+}
+
+fn shot(x: f64, angle_degrees: f64) -> Shot {
+    Shot {
+        offset: Vector2::new(x, -12.0),
+        angle: angle_degrees * PI / 180.0,
+    }
+}
+
+/// Main gun layout for a power level from 1 to 4.
+pub fn main_layout(level: u32, focused: bool) -> Vec<Shot> {
+    match (level, focused) {
+        (1, _) => vec![shot(0.0, 0.0)],
+        (2, true) => vec![shot(-4.0, 0.0), shot(4.0, 0.0)],
+        (2, false) => vec![shot(-6.0, -4.0), shot(6.0, 4.0)],
+        (3, true) => vec![shot(-6.0, 0.0), shot(0.0, 0.0), shot(6.0, 0.0)],
+        (3, false) => vec![shot(-8.0, -8.0), shot(0.0, 0.0), shot(8.0, 8.0)],
+        (_, true) => vec![shot(-9.0, 0.0), shot(-3.0, 0.0), shot(3.0, 0.0), shot(9.0, 0.0)],
+        (_, false) => vec![shot(-10.0, -12.0), shot(-4.0, -3.0), shot(4.0, 3.0), shot(10.0, 12.0)],
+    }
+}
+
+/// Where option `index` of `count` sits relative to its owner.
+pub fn option_offset(index: u32, count: u32, focused: bool) -> Vector2<f64> {
+    let side = if index % 2 == 0 { -1.0 } else { 1.0 };
+    let rank = (index / 2 + 1) as f64;
+    if focused {
+        Vector2::new(side * 14.0 * rank, -28.0)
+    } else if count == 1 {
+        Vector2::new(0.0, 32.0)
+    } else {
+        Vector2::new(side * 36.0 * rank, 8.0 * rank)
+    }
+}
+
+/// Options fire straight ahead when focused and spread outwards otherwise.
+pub fn option_shot(index: u32, focused: bool) -> Shot {
+    let side = if index % 2 == 0 { -1.0 } else { 1.0 };
+    if focused {
+        shot(0.0, 0.0)
+    } else {
+        shot(0.0, side * 15.0 * (index / 2 + 1) as f64)
+    }
+}
+
+fn projectile(shot: &Shot) -> Projectile {
+    let (sin, cos) = shot.angle.sin_cos();
+    Projectile {
+        offset: shot.offset,
+        vel: Vector2::new(sin * SHOT_SPEED, -cos * SHOT_SPEED),
+        graphic: RenderGraphicState::Rectangle(RectangleGraphic {
+            rect: graphics::Rectangle::new([0.6, 0.9, 1.0, 0.8]),
+            width: 6.0,
+            height: 14.0,
+        }),
+        faction: Faction::Player,
+    }
+}
+
+impl specs::Component for Weapon {
+    type Storage = specs::HashMapStorage<Self>;
+}
+
+impl Component for Weapon {
+    fn name(&self) -> &str {
+        "Weapon"
+    }
+}
+
+impl specs::Component for WeaponOption {
+    type Storage = specs::HashMapStorage<Self>;
+}
+
+impl Component for WeaponOption {
+    fn name(&self) -> &str {
+        "WeaponOption"
+    }
+}
+
+impl<'a> specs::System<'a> for WeaponSystem {
+    type SystemData = WeaponSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let dt = data.time.elapsed_game_time().as_seconds();
+        let level = data.stats.power_level();
+        let option_count = level - 1;
+        let follow = 1.0 - (-OPTION_FOLLOW_RATE * dt).exp();
+
+        let owners: Vec<_> = (&*data.entities, &data.pos, &data.player, &data.weapon)
+            .join()
+            .map(|(e, &Position(pos), player, _)| (e, pos, player.clone()))
+            .collect();
+
+        for &(owner, owner_pos, ref player) in &owners {
+            let mut present = vec![false; option_count as usize];
+
+            for (entity, option, pos) in (&*data.entities, &data.option, &mut data.pos).join() {
+                if option.owner != owner {
+                    continue;
+                }
+                if option.index >= option_count {
+                    data.spawns.despawn(entity);
+                    continue;
+                }
+                present[option.index as usize] = true;
+
+                let target = owner_pos + option_offset(option.index, option_count, player.focused);
+                pos.0 += (target - pos.0) * follow;
+            }
+
+            for (index, _) in present.iter().enumerate().filter(|&(_, &p)| !p) {
+                let index = index as u32;
+                let spawn = Spawn::new(
+                    owner_pos + option_offset(index, option_count, player.focused),
+                ).with_render(RenderGraphicState::Rectangle(RectangleGraphic {
+                        rect: graphics::Rectangle::new([0.9, 0.9, 0.3, 1.0]),
+                        width: OPTION_SIZE,
+                        height: OPTION_SIZE,
+                    }))
+                    .with_option(WeaponOption { owner, index });
+                data.spawns.spawn(spawn);
+            }
+        }
+
+        for &(owner, owner_pos, ref player) in &owners {
+            let weapon = match data.weapon.get_mut(owner) {
+                Some(weapon) => weapon,
+                None => continue,
+            };
+
+            weapon.timer -= dt;
+            if !player.firing || weapon.timer > 0.0 {
+                weapon.timer = weapon.timer.max(0.0);
+                continue;
+            }
+            weapon.timer += weapon.cooldown;
+            data.shots.single_write(ShotFired { owner });
+
+            for shot in main_layout(level, player.focused) {
+                data.spawns.spawn(Spawn::from_projectile(owner_pos, projectile(&shot)));
+            }
+
+            for (option, &Position(pos)) in (&data.option, &data.pos).join() {
+                if option.owner == owner && option.index < option_count {
+                    let shot = option_shot(option.index, player.focused);
+                    data.spawns.spawn(Spawn::from_projectile(pos, projectile(&shot)));
+                }
+            }
+        }
+    }
+}
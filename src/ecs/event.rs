@@ -0,0 +1,239 @@
+use std::slice;
+
+use specs;
+use cgmath::Vector2;
+
+use ecs::component::pickup::PickupKind;
+use stage::StageEvent;
+
+/// A queue of events of one type, stored as a world resource. Writers push
+/// events and each reader keeps its own `ReaderId`, so any number of
+/// systems can see every event without knowing about each other.
+///
+/// Events written during a tick are kept until the `maintain` call at the
+/// end of the following tick. That lets readers that run before the writer
+/// in the dispatch order still see them, one tick late, while the cursor in
+/// each `ReaderId` stops anything being read twice.
+pub struct EventChannel<T> {
+    events: Vec<T>,
+    /// Sequence number of `events[0]`.
+    first: u64,
+    /// Number of events written before the current tick.
+    previous_tick: usize,
+}
+
+/// A reader's position in an `EventChannel`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReaderId {
+    next: u64,
+}
+
+/// A player bullet struck an enemy.
+#[derive(Debug, Clone)]
+pub struct EnemyHit {
+    pub entity: specs::Entity,
+    pub pos: Vector2<f64>,
+}
+
+/// An enemy ran out of health and was removed.
+#[derive(Debug, Clone)]
+pub struct EntityDied {
+    pub entity: specs::Entity,
+    pub pos: Vector2<f64>,
+}
+
+/// An enemy bullet struck the player.
+#[derive(Debug, Clone)]
+pub struct PlayerHit {
+    pub entity: specs::Entity,
+    pub pos: Vector2<f64>,
+}
+
+/// A hit cost the player a life. Hits while invulnerable don't count.
+#[derive(Debug, Clone)]
+pub struct LifeLost {
+    pub lives_left: u32,
+}
+
+/// The player used a bomb, clearing the enemy bullets.
+#[derive(Debug, Clone)]
+pub struct BombUsed {
+    pub pos: Vector2<f64>,
+}
+
+/// An enemy bullet passed close to the player without hitting.
+#[derive(Debug, Clone)]
+pub struct Grazed {
+    pub pos: Vector2<f64>,
+}
+
+/// A weapon fired a volley.
+#[derive(Debug, Clone)]
+pub struct ShotFired {
+    pub owner: specs::Entity,
+}
+
+#[derive(Debug, Clone)]
+pub struct PickupCollected {
+    pub kind: PickupKind,
+    pub pos: Vector2<f64>,
+}
+
+/// A boss phase ended, either by defeat or by running out of time.
+#[derive(Debug, Clone)]
+pub struct BossPhaseEnded {
+    pub boss: specs::Entity,
+    pub defeated: bool,
+    /// Clear bonus earned, zero if the phase timed out or the player lost
+    /// a life during it.
+    pub bonus: u64,
+}
+
+impl<T> EventChannel<T> {
+    pub fn new() -> EventChannel<T> {
+        EventChannel {
+            events: Vec::new(),
+            first: 0,
+            previous_tick: 0,
+        }
+    }
+
+    pub fn single_write(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    pub fn iter_write<I: IntoIterator<Item = T>>(&mut self, events: I) {
+        self.events.extend(events);
+    }
+
+    /// Creates a reader that starts with the oldest event still kept.
+    pub fn register_reader(&self) -> ReaderId {
+        ReaderId { next: self.first }
+    }
+
+    /// Events written since `reader` last read, advancing it past them.
+    pub fn read(&self, reader: &mut ReaderId) -> slice::Iter<T> {
+        let start = (reader.next.max(self.first) - self.first) as usize;
+        reader.next = self.first + self.events.len() as u64;
+        self.events[start.min(self.events.len())..].iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Ends a tick, dropping the events written during the one before it.
+    pub fn maintain(&mut self) {
+        self.events.drain(..self.previous_tick);
+        self.first += self.previous_tick as u64;
+        self.previous_tick = self.events.len();
+    }
+}
+
+impl<T> Default for EventChannel<T> {
+    fn default() -> EventChannel<T> {
+        EventChannel::new()
+    }
+}
+
+/// Defines `register_events` and `maintain_events` from one list of event
+/// types, so every channel that's added is also maintained.
+macro_rules! event_channels {
+    ($($event:ty),*) => {
+        /// Adds a channel for each gameplay event type.
+        pub fn register_events(world: &mut specs::World) {
+            $(world.add_resource(EventChannel::<$event>::new());)*
+        }
+
+        /// Ends the tick for every channel added by `register_events`.
+        /// Called once per update, after everything that reads events has
+        /// run.
+        pub fn maintain_events(world: &specs::World) {
+            $(world.write_resource::<EventChannel<$event>>().maintain();)*
+        }
+    };
+}
+
+event_channels!(
+    EnemyHit,
+    EntityDied,
+    PlayerHit,
+    LifeLost,
+    BombUsed,
+    Grazed,
+    ShotFired,
+    PickupCollected,
+    BossPhaseEnded,
+    StageEvent
+);
+
+#[cfg(test)]
+mod tests {
+    use super::EventChannel;
+
+    fn read(channel: &EventChannel<u32>, reader: &mut super::ReaderId) -> Vec<u32> {
+        channel.read(reader).cloned().collect()
+    }
+
+    #[test]
+    fn readers_see_each_event_once() {
+        let mut channel = EventChannel::new();
+        let mut reader = channel.register_reader();
+
+        channel.single_write(1);
+        assert_eq!(read(&channel, &mut reader), vec![1]);
+        assert!(read(&channel, &mut reader).is_empty());
+
+        channel.maintain();
+        assert!(read(&channel, &mut reader).is_empty());
+        channel.iter_write(vec![2, 3]);
+        assert_eq!(read(&channel, &mut reader), vec![2, 3]);
+    }
+
+    #[test]
+    fn events_survive_exactly_one_maintain() {
+        let mut channel = EventChannel::new();
+        let mut late = channel.register_reader();
+        let mut later = channel.register_reader();
+
+        channel.single_write(1);
+        channel.maintain();
+        assert_eq!(channel.len(), 1);
+        assert_eq!(read(&channel, &mut late), vec![1]);
+
+        channel.maintain();
+        assert!(channel.is_empty());
+        assert!(read(&channel, &mut later).is_empty());
+    }
+
+    #[test]
+    fn readers_far_behind_skip_dropped_events() {
+        let mut channel = EventChannel::new();
+        let mut reader = channel.register_reader();
+
+        for event in 1..4 {
+            channel.single_write(event);
+            channel.maintain();
+        }
+        channel.single_write(4);
+
+        assert_eq!(read(&channel, &mut reader), vec![3, 4]);
+        assert!(read(&channel, &mut reader).is_empty());
+    }
+
+    #[test]
+    fn new_readers_start_at_the_oldest_kept_event() {
+        let mut channel = EventChannel::new();
+        channel.single_write(1);
+        channel.maintain();
+        channel.single_write(2);
+        channel.maintain();
+        channel.single_write(3);
+
+        let mut reader = channel.register_reader();
+        assert_eq!(read(&channel, &mut reader), vec![2, 3]);
+    }
+}
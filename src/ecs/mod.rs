@@ -1,4 +1,8 @@
 
 pub mod component;
+pub mod event;
+pub mod prefab;
+pub mod save;
+pub mod spawn;
 
 pub use self::component::Component;
@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+
+use cgmath::Vector2;
+use graphics;
+
+use data::{self, DataError};
+use ecs::spawn::Spawn;
+use ecs::component::bullet::{Bullet, Faction};
+use ecs::component::pickup::{Drops, PickupKind};
+use pattern::{PatternController, PatternSpec};
+use render::{BlendMode, CustomGraphic, RectangleGraphic, RenderGraphicState};
+use script::ScriptLibrary;
+
+pub const PREFAB_PATH: &str = "assets/prefabs.toml";
+
+/// A named entity archetype. Every field is optional: missing ones leave the
+/// component off the entity, or in an override, keep the prefab's value.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Prefab {
+    /// Added to the position the prefab is spawned at.
+    pub offset: Option<[f64; 2]>,
+    pub velocity: Option<[f64; 2]>,
+    pub render: Option<RenderData>,
+    pub hitbox: Option<f64>,
+    pub health: Option<f64>,
+    pub controller: Option<ControllerData>,
+    /// Makes the entity a bullet fired by the given side.
+    pub bullet: Option<Faction>,
+    pub drops: Option<Vec<PickupKind>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderData {
+    pub size: [f64; 2],
+    pub color: [f32; 4],
+    /// Defaults to additive for bullets and alpha for everything else.
+    #[serde(default)]
+    pub blend: Option<BlendMode>,
+    /// Name of a registered renderer to draw with instead of a rectangle.
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Values passed to the renderer named by `kind`.
+    #[serde(default)]
+    pub params: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControllerData {
+    Pattern { pattern: PatternSpec },
+    /// Name of a script in `assets/scripts/`.
+    Script { name: String },
+}
+
+/// Every prefab available to the game, keyed by name.
+#[derive(Debug, Clone, Default)]
+pub struct PrefabLibrary {
+    prefabs: Arc<HashMap<String, Prefab>>,
+    scripts: ScriptLibrary,
+}
+
+impl Prefab {
+    /// This prefab with every field set in `overrides` replaced.
+    pub fn with_overrides(&self, overrides: &Prefab) -> Prefab {
+        Prefab {
+            offset: overrides.offset.or(self.offset),
+            velocity: overrides.velocity.or(self.velocity),
+            render: overrides.render.clone().or_else(|| self.render.clone()),
+            hitbox: overrides.hitbox.or(self.hitbox),
+            health: overrides.health.or(self.health),
+            controller: overrides.controller.clone().or_else(|| self.controller.clone()),
+            bullet: overrides.bullet.or(self.bullet),
+            drops: overrides.drops.clone().or_else(|| self.drops.clone()),
+        }
+    }
+
+    pub fn spawn(&self, pos: Vector2<f64>, scripts: &ScriptLibrary) -> Spawn {
+        let offset = self.offset.map_or(Vector2::new(0.0, 0.0), to_vector);
+        let mut spawn = Spawn::new(pos + offset);
+
+        if let Some(velocity) = self.velocity {
+            spawn = spawn.with_velocity(to_vector(velocity));
+        }
+        if let Some(ref render) = self.render {
+            let default_blend = if self.bullet.is_some() {
+                BlendMode::Additive
+            } else {
+                BlendMode::Alpha
+            };
+            spawn = spawn
+                .with_render(render.graphic())
+                .with_blend(render.blend.unwrap_or(default_blend));
+        }
+        if let Some(radius) = self.hitbox {
+            spawn = spawn.with_hitbox(radius);
+        }
+        if let Some(health) = self.health {
+            spawn = spawn.with_health(health);
+        }
+        if let Some(faction) = self.bullet {
+            spawn = spawn.with_bullet(Bullet::new(faction));
+        }
+        if let Some(ref drops) = self.drops {
+            spawn = spawn.with_drops(Drops(drops.clone()));
+        }
+
+        match self.controller {
+            Some(ControllerData::Pattern { ref pattern }) => {
+                spawn.with_controller(Box::new(PatternController::new(pattern.clone())))
+            }
+            Some(ControllerData::Script { ref name }) => match scripts.controller(name) {
+                Some(controller) => spawn.with_controller(Box::new(controller)),
+                None => spawn,
+            },
+            None => spawn,
+        }
+    }
+}
+
+impl RenderData {
+    /// Describes an existing graphic, so it can be saved.
+    pub fn from_graphic(graphic: &RenderGraphicState, blend: BlendMode) -> RenderData {
+        match *graphic {
+            RenderGraphicState::Rectangle(ref r) => RenderData {
+                size: [r.width, r.height],
+                color: r.rect.color,
+                blend: Some(blend),
+                kind: None,
+                params: BTreeMap::new(),
+            },
+            RenderGraphicState::Custom(ref c) => RenderData {
+                size: [c.width, c.height],
+                color: c.color,
+                blend: Some(blend),
+                kind: Some(c.kind.clone()),
+                params: c.params.clone(),
+            },
+        }
+    }
+
+    pub fn graphic(&self) -> RenderGraphicState {
+        match self.kind {
+            Some(ref kind) => RenderGraphicState::Custom(CustomGraphic {
+                kind: kind.clone(),
+                width: self.size[0],
+                height: self.size[1],
+                color: self.color,
+                params: self.params.clone(),
+            }),
+            None => RenderGraphicState::Rectangle(RectangleGraphic {
+                rect: graphics::Rectangle::new(self.color),
+                width: self.size[0],
+                height: self.size[1],
+            }),
+        }
+    }
+}
+
+impl PrefabLibrary {
+    pub fn new(prefabs: HashMap<String, Prefab>, scripts: ScriptLibrary) -> PrefabLibrary {
+        PrefabLibrary {
+            prefabs: Arc::new(prefabs),
+            scripts,
+        }
+    }
+
+    /// Loads a table of prefabs, using `scripts` for any script controllers.
+    /// Fails if a prefab names a prefab or script that doesn't exist, or a
+    /// script spawns a prefab that doesn't.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        scripts: ScriptLibrary,
+    ) -> Result<PrefabLibrary, DataError> {
+        let library = PrefabLibrary::new(data::load_toml(path)?, scripts);
+        for (name, prefab) in library.prefabs.iter() {
+            library.check_prefab_data(prefab, &format!("prefab '{}'", name))?;
+        }
+        for program in library.scripts.programs() {
+            for name in program.prefab_names() {
+                library.check_prefab(name, &format!("script '{}'", program.name))?;
+            }
+        }
+        Ok(library)
+    }
+
+    /// Fails unless `name` is a prefab. `user` describes what refers to it,
+    /// for the error.
+    pub fn check_prefab(&self, name: &str, user: &str) -> Result<(), DataError> {
+        if self.prefabs.contains_key(name) {
+            Ok(())
+        } else {
+            Err(unknown_name(user, "prefab", name))
+        }
+    }
+
+    /// Fails unless `name` is a loaded script.
+    pub fn check_script(&self, name: &str, user: &str) -> Result<(), DataError> {
+        if self.scripts.get(name).is_some() {
+            Ok(())
+        } else {
+            Err(unknown_name(user, "script", name))
+        }
+    }
+
+    /// Checks the prefab a pattern fires, if it names one.
+    pub fn check_pattern(&self, pattern: &PatternSpec, user: &str) -> Result<(), DataError> {
+        match pattern.prefab {
+            Some(ref name) => self.check_prefab(name, user),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks every name `prefab` refers to. Also used for overrides.
+    pub fn check_prefab_data(&self, prefab: &Prefab, user: &str) -> Result<(), DataError> {
+        match prefab.controller {
+            Some(ControllerData::Pattern { ref pattern }) => self.check_pattern(pattern, user),
+            Some(ControllerData::Script { ref name }) => self.check_script(name, user),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// Describes an instance of the named prefab at `pos`, with any fields
+    /// in `overrides` taking precedence. Returns `None` if there's no such
+    /// prefab; names in data files are checked when they load, so that
+    /// only happens for names typed in the console.
+    pub fn spawn(
+        &self,
+        name: &str,
+        pos: Vector2<f64>,
+        overrides: Option<&Prefab>,
+    ) -> Option<Spawn> {
+        let prefab = match self.prefabs.get(name) {
+            Some(prefab) => prefab,
+            None => return None,
+        };
+
+        Some(match overrides {
+            Some(overrides) => prefab.with_overrides(overrides).spawn(pos, &self.scripts),
+            None => prefab.spawn(pos, &self.scripts),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.prefabs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.prefabs.is_empty()
+    }
+}
+
+fn to_vector(v: [f64; 2]) -> Vector2<f64> {
+    Vector2::new(v[0], v[1])
+}
+
+fn unknown_name(user: &str, kind: &str, name: &str) -> DataError {
+    DataError::Invalid(format!("{} refers to unknown {} '{}'", user, kind, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cgmath::Vector2;
+    use toml;
+
+    use data::DataError;
+    use script::ScriptLibrary;
+    use super::{Prefab, PrefabLibrary};
+
+    fn enemy() -> Prefab {
+        Prefab {
+            offset: Some([0.0, 10.0]),
+            velocity: Some([5.0, 0.0]),
+            hitbox: Some(8.0),
+            health: Some(3.0),
+            ..Prefab::default()
+        }
+    }
+
+    fn library() -> PrefabLibrary {
+        let mut prefabs = HashMap::new();
+        prefabs.insert("enemy".to_string(), enemy());
+        PrefabLibrary::new(prefabs, ScriptLibrary::new())
+    }
+
+    #[test]
+    fn overrides_replace_only_the_fields_they_set() {
+        let overrides = Prefab {
+            velocity: Some([0.0, 1.0]),
+            health: Some(10.0),
+            ..Prefab::default()
+        };
+        let prefab = enemy().with_overrides(&overrides);
+
+        assert_eq!(prefab.velocity, Some([0.0, 1.0]));
+        assert_eq!(prefab.health, Some(10.0));
+        assert_eq!(prefab.offset, Some([0.0, 10.0]));
+        assert_eq!(prefab.hitbox, Some(8.0));
+        assert!(prefab.bullet.is_none());
+        assert!(prefab.render.is_none());
+    }
+
+    #[test]
+    fn spawn_applies_the_prefab_and_overrides() {
+        let overrides = Prefab {
+            health: Some(10.0),
+            ..Prefab::default()
+        };
+        let spawn = library()
+            .spawn("enemy", Vector2::new(100.0, 100.0), Some(&overrides))
+            .unwrap();
+
+        assert_eq!(spawn.pos, Vector2::new(100.0, 110.0));
+        assert_eq!(spawn.vel, Some(Vector2::new(5.0, 0.0)));
+        assert_eq!(spawn.hitbox, Some(8.0));
+        assert_eq!(spawn.health, Some(10.0));
+    }
+
+    #[test]
+    fn unknown_prefabs_spawn_nothing() {
+        let library = library();
+        assert!(library.spawn("missing", Vector2::new(0.0, 0.0), None).is_none());
+        assert!(library.check_prefab("enemy", "test").is_ok());
+        match library.check_prefab("missing", "test") {
+            Err(DataError::Invalid(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn names_in_controllers_are_checked() {
+        let library = library();
+        let fires: Prefab = toml::from_str(
+            r#"
+            [controller]
+            type = "pattern"
+
+            [controller.pattern]
+            pattern = { type = "ring", count = 1, speed = 1.0 }
+            interval = 1.0
+            prefab = "missing"
+            "#,
+        ).unwrap();
+        let scripted: Prefab =
+            toml::from_str(r#"controller = { type = "script", name = "missing" }"#).unwrap();
+
+        assert!(library.check_prefab_data(&enemy(), "test").is_ok());
+        assert!(library.check_prefab_data(&fires, "test").is_err());
+        assert!(library.check_prefab_data(&scripted, "test").is_err());
+    }
+}
@@ -0,0 +1,584 @@
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read};
+use std::fs::File;
+use std::path::Path;
+use std::error;
+use std::fmt;
+
+use specs::{self, Join};
+use cgmath::Vector2;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use toml;
+
+use ecs::component::{boss, bullet, collision, controller, graze, pickup, player, pos, render,
+                     weapon};
+use ecs::prefab::RenderData;
+use data;
+use controller::ControllerSave;
+use script::ScriptLibrary;
+use score::Score;
+use stage::StageCursor;
+
+/// A component or resource that can be written to a save file and read
+/// back, through a plain data form when the type itself isn't
+/// serializable.
+pub trait Saveable: Sized {
+    type Data: Serialize + DeserializeOwned;
+
+    /// The data to save, or `None` to leave this one out of the save.
+    fn save(&self, ctx: &SaveContext) -> Option<Self::Data>;
+    fn restore(data: Self::Data, ctx: &LoadContext) -> Result<Self, SaveError>;
+}
+
+/// Maps entities to their positions in `WorldSave::entities`, so saved data
+/// can refer to other entities.
+pub struct SaveContext {
+    indices: HashMap<specs::Entity, usize>,
+}
+
+/// The world being loaded into and the entities created for the save.
+pub struct LoadContext<'a> {
+    world: &'a specs::World,
+    entities: &'a [specs::Entity],
+}
+
+type HasComponentFn = fn(&specs::World, specs::Entity) -> bool;
+type SaveComponentFn = fn(&specs::World, specs::Entity, &SaveContext)
+    -> Result<Option<toml::Value>, SaveError>;
+type SaveResourceFn = fn(&specs::World, &SaveContext) -> Result<Option<toml::Value>, SaveError>;
+type RestoreFn = fn(toml::Value, &LoadContext) -> Result<Box<Any>, SaveError>;
+type InsertComponentFn = fn(&specs::World, specs::Entity, Box<Any>);
+type InsertResourceFn = fn(&specs::World, Box<Any>);
+
+struct ComponentEntry {
+    has: HasComponentFn,
+    save: SaveComponentFn,
+    restore: RestoreFn,
+    insert: InsertComponentFn,
+}
+
+struct ResourceEntry {
+    save: SaveResourceFn,
+    restore: RestoreFn,
+    insert: InsertResourceFn,
+}
+
+/// Everything read from a save, ready to be put into the world.
+struct Restored {
+    resources: Vec<(InsertResourceFn, Box<Any>)>,
+    components: Vec<(specs::Entity, InsertComponentFn, Box<Any>)>,
+}
+
+/// The components and resources written to save files, by name.
+///
+/// Components left out of the registry are dropped when the world is
+/// saved.
+#[derive(Default)]
+pub struct SaveRegistry {
+    components: BTreeMap<String, ComponentEntry>,
+    resources: BTreeMap<String, ResourceEntry>,
+}
+
+/// Every entity's saveable components and every registered resource, keyed
+/// by their registered names.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorldSave {
+    pub resources: BTreeMap<String, toml::Value>,
+    pub entities: Vec<BTreeMap<String, toml::Value>>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Serialize(toml::ser::Error),
+    Parse(toml::de::Error),
+    UnknownComponent(String),
+    UnknownResource(String),
+    /// A reference to an entity past the end of the save.
+    UnknownEntity(usize),
+    /// Saved data that parsed but can't be restored.
+    Invalid(String),
+}
+
+impl SaveContext {
+    /// The saved index of `entity`, if it is in the save.
+    pub fn index(&self, entity: specs::Entity) -> Option<usize> {
+        self.indices.get(&entity).cloned()
+    }
+}
+
+impl<'a> LoadContext<'a> {
+    pub fn world(&self) -> &specs::World {
+        self.world
+    }
+
+    /// The entity created for the saved entity at `index`.
+    pub fn entity(&self, index: usize) -> Result<specs::Entity, SaveError> {
+        self.entities
+            .get(index)
+            .cloned()
+            .ok_or(SaveError::UnknownEntity(index))
+    }
+}
+
+impl SaveRegistry {
+    pub fn new() -> SaveRegistry {
+        SaveRegistry::default()
+    }
+
+    pub fn register_component<T>(&mut self, name: &str)
+    where
+        T: Saveable + specs::Component + Send + Sync,
+    {
+        self.components.insert(
+            name.to_string(),
+            ComponentEntry {
+                has: has_component::<T>,
+                save: save_component::<T>,
+                restore: restore_value::<T>,
+                insert: insert_component::<T>,
+            },
+        );
+    }
+
+    /// Registers a resource. It must already be in the world both when
+    /// saving and when loading.
+    pub fn register_resource<T>(&mut self, name: &str)
+    where
+        T: Saveable + Send + Sync + 'static,
+    {
+        self.resources.insert(
+            name.to_string(),
+            ResourceEntry {
+                save: save_resource::<T>,
+                restore: restore_value::<T>,
+                insert: insert_resource::<T>,
+            },
+        );
+    }
+
+    pub fn save(&self, world: &specs::World) -> Result<WorldSave, SaveError> {
+        let saved: Vec<specs::Entity> = (&*world.entities())
+            .join()
+            .filter(|&entity| self.components.values().any(|c| (c.has)(world, entity)))
+            .collect();
+        let ctx = SaveContext {
+            indices: saved.iter().enumerate().map(|(i, &e)| (e, i)).collect(),
+        };
+
+        let mut save = WorldSave::default();
+
+        for (name, entry) in &self.resources {
+            if let Some(value) = (entry.save)(world, &ctx)? {
+                save.resources.insert(name.clone(), value);
+            }
+        }
+
+        // Every saved entity gets a slot, even if all of its components are
+        // left out, so indices in the save stay valid.
+        for &entity in &saved {
+            let mut components = BTreeMap::new();
+            for (name, entry) in &self.components {
+                if let Some(value) = (entry.save)(world, entity, &ctx)? {
+                    components.insert(name.clone(), value);
+                }
+            }
+            save.entities.push(components);
+        }
+
+        Ok(save)
+    }
+
+    /// Replaces every entity in `world` with those in `save` and restores
+    /// the saved resources. Returns the new entities in save order.
+    ///
+    /// Everything is read back before the world is changed, so a save that
+    /// fails to load leaves the world as it was.
+    pub fn load(
+        &self,
+        world: &mut specs::World,
+        save: &WorldSave,
+    ) -> Result<Vec<specs::Entity>, SaveError> {
+        for name in save.resources.keys() {
+            if !self.resources.contains_key(name) {
+                return Err(SaveError::UnknownResource(name.clone()));
+            }
+        }
+        for name in save.entities.iter().flat_map(|e| e.keys()) {
+            if !self.components.contains_key(name) {
+                return Err(SaveError::UnknownComponent(name.clone()));
+            }
+        }
+
+        let old: Vec<specs::Entity> = (&*world.entities()).join().collect();
+        let loaded: Vec<specs::Entity> = save.entities
+            .iter()
+            .map(|_| world.create_entity().build())
+            .collect();
+
+        let restored = match self.restore(world, save, &loaded) {
+            Ok(restored) => restored,
+            Err(e) => {
+                delete_all(world, &loaded);
+                return Err(e);
+            }
+        };
+
+        delete_all(world, &old);
+        for (insert, value) in restored.resources {
+            insert(world, value);
+        }
+        for (entity, insert, value) in restored.components {
+            insert(world, entity, value);
+        }
+        world.maintain();
+
+        Ok(loaded)
+    }
+
+    fn restore(
+        &self,
+        world: &specs::World,
+        save: &WorldSave,
+        entities: &[specs::Entity],
+    ) -> Result<Restored, SaveError> {
+        let ctx = LoadContext { world, entities };
+        let mut restored = Restored {
+            resources: Vec::new(),
+            components: Vec::new(),
+        };
+
+        for (name, value) in &save.resources {
+            let entry = &self.resources[name];
+            let resource = (entry.restore)(value.clone(), &ctx)?;
+            restored.resources.push((entry.insert, resource));
+        }
+
+        for (&entity, components) in entities.iter().zip(&save.entities) {
+            for (name, value) in components {
+                let entry = &self.components[name];
+                let component = (entry.restore)(value.clone(), &ctx)?;
+                restored.components.push((entity, entry.insert, component));
+            }
+        }
+
+        Ok(restored)
+    }
+}
+
+impl WorldSave {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<WorldSave, SaveError> {
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Writes the save to `path`, creating its directory if needed.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        data::write_toml_atomic(path, self)
+    }
+}
+
+fn delete_all(world: &mut specs::World, entities: &[specs::Entity]) {
+    {
+        let all = world.entities();
+        for &entity in entities {
+            let _ = all.delete(entity);
+        }
+    }
+    world.maintain();
+}
+
+fn has_component<T>(world: &specs::World, entity: specs::Entity) -> bool
+where
+    T: Saveable + specs::Component + Send + Sync,
+{
+    world.read::<T>().get(entity).is_some()
+}
+
+fn save_component<T>(
+    world: &specs::World,
+    entity: specs::Entity,
+    ctx: &SaveContext,
+) -> Result<Option<toml::Value>, SaveError>
+where
+    T: Saveable + specs::Component + Send + Sync,
+{
+    match world.read::<T>().get(entity).and_then(|c| c.save(ctx)) {
+        Some(data) => Ok(Some(toml::Value::try_from(data)?)),
+        None => Ok(None),
+    }
+}
+
+fn save_resource<T>(
+    world: &specs::World,
+    ctx: &SaveContext,
+) -> Result<Option<toml::Value>, SaveError>
+where
+    T: Saveable + Send + Sync + 'static,
+{
+    match world.read_resource::<T>().save(ctx) {
+        Some(data) => Ok(Some(toml::Value::try_from(data)?)),
+        None => Ok(None),
+    }
+}
+
+fn restore_value<T>(value: toml::Value, ctx: &LoadContext) -> Result<Box<Any>, SaveError>
+where
+    T: Saveable + 'static,
+{
+    let data: T::Data = value.try_into()?;
+    Ok(Box::new(T::restore(data, ctx)?))
+}
+
+fn insert_component<T>(world: &specs::World, entity: specs::Entity, value: Box<Any>)
+where
+    T: Saveable + specs::Component + Send + Sync,
+{
+    if let Ok(component) = value.downcast::<T>() {
+        world.write::<T>().insert(entity, *component);
+    }
+}
+
+fn insert_resource<T>(world: &specs::World, value: Box<Any>)
+where
+    T: Saveable + Send + Sync + 'static,
+{
+    if let Ok(resource) = value.downcast::<T>() {
+        *world.write_resource::<T>() = *resource;
+    }
+}
+
+impl Saveable for pos::Position {
+    type Data = [f64; 2];
+
+    fn save(&self, _: &SaveContext) -> Option<[f64; 2]> {
+        Some([self.0.x, self.0.y])
+    }
+    fn restore(data: [f64; 2], _: &LoadContext) -> Result<pos::Position, SaveError> {
+        Ok(pos::Position(Vector2::new(data[0], data[1])))
+    }
+}
+
+impl Saveable for pos::Movable {
+    type Data = [f64; 2];
+
+    fn save(&self, _: &SaveContext) -> Option<[f64; 2]> {
+        Some([self.0.x, self.0.y])
+    }
+    fn restore(data: [f64; 2], _: &LoadContext) -> Result<pos::Movable, SaveError> {
+        Ok(pos::Movable(Vector2::new(data[0], data[1])))
+    }
+}
+
+impl Saveable for render::Render {
+    type Data = RenderData;
+
+    fn save(&self, _: &SaveContext) -> Option<RenderData> {
+        Some(RenderData::from_graphic(&self.state, self.blend))
+    }
+    fn restore(data: RenderData, _: &LoadContext) -> Result<render::Render, SaveError> {
+        let render = render::Render::new(data.graphic());
+        Ok(match data.blend {
+            Some(blend) => render.with_blend(blend),
+            None => render,
+        })
+    }
+}
+
+/// Controllers that can't be saved leave the entity without a `Control`.
+/// Restoring needs the world's `ScriptLibrary` for script controllers.
+impl Saveable for controller::Control {
+    type Data = ControllerSave;
+
+    fn save(&self, _: &SaveContext) -> Option<ControllerSave> {
+        self.controller_save()
+    }
+    fn restore(data: ControllerSave, ctx: &LoadContext) -> Result<controller::Control, SaveError> {
+        let scripts = ctx.world().read_resource::<ScriptLibrary>();
+        data.restore(&scripts)
+            .map(controller::Control::new)
+            .map_err(SaveError::Invalid)
+    }
+}
+
+/// A `WeaponOption` with its owner as an index into the save.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeaponOptionData {
+    owner: usize,
+    index: u32,
+}
+
+/// Options whose owner isn't saved are left out.
+impl Saveable for weapon::WeaponOption {
+    type Data = WeaponOptionData;
+
+    fn save(&self, ctx: &SaveContext) -> Option<WeaponOptionData> {
+        ctx.index(self.owner).map(|owner| WeaponOptionData {
+            owner,
+            index: self.index,
+        })
+    }
+    fn restore(
+        data: WeaponOptionData,
+        ctx: &LoadContext,
+    ) -> Result<weapon::WeaponOption, SaveError> {
+        Ok(weapon::WeaponOption {
+            owner: ctx.entity(data.owner)?,
+            index: data.index,
+        })
+    }
+}
+
+/// Implements `Saveable` for types that serialize as they are.
+macro_rules! saveable_as_is {
+    ($($t:ty),*) => {
+        $(
+            impl Saveable for $t {
+                type Data = $t;
+
+                fn save(&self, _: &SaveContext) -> Option<$t> {
+                    Some(self.clone())
+                }
+                fn restore(data: $t, _: &LoadContext) -> Result<$t, SaveError> {
+                    Ok(data)
+                }
+            }
+        )*
+    };
+}
+
+saveable_as_is!(
+    bullet::Bullet,
+    player::Player,
+    player::PlayerStats,
+    collision::Hitbox,
+    collision::Health,
+    graze::Graze,
+    pickup::Pickup,
+    pickup::Collector,
+    pickup::Drops,
+    weapon::Weapon,
+    boss::Boss,
+    Score,
+    StageCursor
+);
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref e) => write!(f, "unable to access save file: {}", e),
+            SaveError::Serialize(ref e) => write!(f, "unable to write save: {}", e),
+            SaveError::Parse(ref e) => write!(f, "invalid save file: {}", e),
+            SaveError::UnknownComponent(ref name) => write!(f, "unknown component '{}'", name),
+            SaveError::UnknownResource(ref name) => write!(f, "unknown resource '{}'", name),
+            SaveError::UnknownEntity(index) => write!(f, "no saved entity {}", index),
+            SaveError::Invalid(ref reason) => write!(f, "invalid save: {}", reason),
+        }
+    }
+}
+
+impl error::Error for SaveError {
+    fn description(&self) -> &str {
+        match *self {
+            SaveError::Io(_) => "unable to access save file",
+            SaveError::Serialize(_) => "unable to write save",
+            SaveError::Parse(_) => "invalid save file",
+            SaveError::UnknownComponent(_) => "unknown component",
+            SaveError::UnknownResource(_) => "unknown resource",
+            SaveError::UnknownEntity(_) => "unknown entity",
+            SaveError::Invalid(_) => "invalid save",
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+
+impl From<toml::ser::Error> for SaveError {
+    fn from(e: toml::ser::Error) -> SaveError {
+        SaveError::Serialize(e)
+    }
+}
+
+impl From<toml::de::Error> for SaveError {
+    fn from(e: toml::de::Error) -> SaveError {
+        SaveError::Parse(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{self, Join};
+    use cgmath::Vector2;
+    use toml;
+
+    use ecs::component::{self, pos, weapon};
+    use super::SaveRegistry;
+
+    fn world() -> specs::World {
+        let mut world = specs::World::new();
+        component::register_components(&mut world);
+        world
+    }
+
+    fn save(world: &specs::World) -> super::WorldSave {
+        world.read_resource::<SaveRegistry>().save(world).unwrap()
+    }
+
+    fn load(world: &mut specs::World, save: &super::WorldSave) -> bool {
+        let saves = ::std::mem::replace(
+            &mut *world.write_resource::<SaveRegistry>(),
+            SaveRegistry::new(),
+        );
+        let loaded = saves.load(world, save).is_ok();
+        *world.write_resource::<SaveRegistry>() = saves;
+        loaded
+    }
+
+    #[test]
+    fn options_keep_their_owner() {
+        let mut world = world();
+        let owner = world
+            .create_entity()
+            .with(pos::Position(Vector2::new(1.0, 2.0)))
+            .build();
+        world
+            .create_entity()
+            .with(weapon::WeaponOption { owner, index: 1 })
+            .build();
+
+        let saved = save(&world);
+        assert!(load(&mut world, &saved));
+
+        let positions = world.read::<pos::Position>();
+        let options = world.read::<weapon::WeaponOption>();
+        let option = options.join().next().unwrap();
+        assert_eq!(option.index, 1);
+        assert_eq!(positions.get(option.owner).unwrap().0, Vector2::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn failed_load_leaves_world_untouched() {
+        let mut world = world();
+        world
+            .create_entity()
+            .with(pos::Position(Vector2::new(1.0, 2.0)))
+            .build();
+
+        let mut saved = save(&world);
+        saved.entities.push(saved.entities[0].clone());
+        saved.entities[1].insert("position".to_string(), toml::Value::from("nowhere"));
+        assert!(!load(&mut world, &saved));
+
+        let positions = world.read::<pos::Position>();
+        let all: Vec<_> = (&*world.entities(), &positions).join().collect();
+        assert_eq!(all.len(), 1);
+        assert_eq!((all[0].1).0, Vector2::new(1.0, 2.0));
+    }
+}
@@ -0,0 +1,187 @@
+use std::mem;
+
+use specs;
+use cgmath::Vector2;
+
+use ecs::component;
+use controller::{Controller, Projectile};
+use render::{BlendMode, RenderGraphicState};
+
+/// Description of an entity to be created once the current dispatch finishes.
+pub struct Spawn {
+    pub pos: Vector2<f64>,
+    pub vel: Option<Vector2<f64>>,
+    pub render: Option<RenderGraphicState>,
+    pub blend: BlendMode,
+    pub controller: Option<Box<Controller + Send + Sync>>,
+    pub bullet: Option<component::bullet::Bullet>,
+    pub hitbox: Option<f64>,
+    pub health: Option<f64>,
+    pub boss: Option<component::boss::Boss>,
+    pub pickup: Option<component::pickup::Pickup>,
+    pub drops: Option<component::pickup::Drops>,
+    pub option: Option<component::weapon::WeaponOption>,
+}
+
+/// Entity creation and deletion requested by systems during a dispatch.
+#[derive(Default)]
+pub struct SpawnQueue {
+    spawns: Vec<Spawn>,
+    despawns: Vec<specs::Entity>,
+}
+
+impl Spawn {
+    pub fn new(pos: Vector2<f64>) -> Spawn {
+        Spawn {
+            pos,
+            vel: None,
+            render: None,
+            blend: BlendMode::Alpha,
+            controller: None,
+            bullet: None,
+            hitbox: None,
+            health: None,
+            boss: None,
+            pickup: None,
+            drops: None,
+            option: None,
+        }
+    }
+
+    pub fn from_projectile(origin: Vector2<f64>, projectile: Projectile) -> Spawn {
+        let (width, height) = projectile.graphic.size();
+        Spawn::new(origin + projectile.offset)
+            .with_hitbox(width.min(height) * 0.4)
+            .with_velocity(projectile.vel)
+            .with_render(projectile.graphic)
+            .with_blend(BlendMode::Additive)
+            .with_bullet(component::bullet::Bullet::new(projectile.faction))
+    }
+
+    pub fn with_velocity(mut self, vel: Vector2<f64>) -> Spawn {
+        self.vel = Some(vel);
+        self
+    }
+    pub fn with_render(mut self, render: RenderGraphicState) -> Spawn {
+        self.render = Some(render);
+        self
+    }
+    pub fn with_blend(mut self, blend: BlendMode) -> Spawn {
+        self.blend = blend;
+        self
+    }
+    pub fn with_controller(mut self, controller: Box<Controller + Send + Sync>) -> Spawn {
+        self.controller = Some(controller);
+        self
+    }
+    pub fn with_bullet(mut self, bullet: component::bullet::Bullet) -> Spawn {
+        self.bullet = Some(bullet);
+        self
+    }
+
+    pub fn with_hitbox(mut self, radius: f64) -> Spawn {
+        self.hitbox = Some(radius);
+        self
+    }
+    pub fn with_health(mut self, health: f64) -> Spawn {
+        self.health = Some(health);
+        self
+    }
+    pub fn with_boss(mut self, boss: component::boss::Boss) -> Spawn {
+        self.boss = Some(boss);
+        self
+    }
+
+    pub fn with_pickup(mut self, pickup: component::pickup::Pickup) -> Spawn {
+        self.pickup = Some(pickup);
+        self
+    }
+    pub fn with_drops(mut self, drops: component::pickup::Drops) -> Spawn {
+        self.drops = Some(drops);
+        self
+    }
+    pub fn with_option(mut self, option: component::weapon::WeaponOption) -> Spawn {
+        self.option = Some(option);
+        self
+    }
+
+    pub fn build(self, world: &mut specs::World) -> specs::Entity {
+        let mut builder = world
+            .create_entity()
+            .with(component::pos::Position(self.pos));
+
+        if let Some(vel) = self.vel {
+            builder = builder.with(component::pos::Movable(vel));
+        }
+        if let Some(render) = self.render {
+            builder = builder.with(component::render::Render::new(render).with_blend(self.blend));
+        }
+        if let Some(controller) = self.controller {
+            builder = builder.with(component::controller::Control::new(controller));
+        }
+        if let Some(bullet) = self.bullet {
+            builder = builder.with(bullet);
+        }
+        if let Some(radius) = self.hitbox {
+            builder = builder.with(component::collision::Hitbox::new(radius));
+        }
+        if let Some(health) = self.health {
+            builder = builder.with(component::collision::Health::new(health));
+        }
+        if let Some(boss) = self.boss {
+            builder = builder.with(boss);
+        }
+        if let Some(pickup) = self.pickup {
+            builder = builder.with(pickup);
+        }
+        if let Some(drops) = self.drops {
+            builder = builder.with(drops);
+        }
+        if let Some(option) = self.option {
+            builder = builder.with(option);
+        }
+
+        builder.build()
+    }
+}
+
+impl SpawnQueue {
+    pub fn new() -> SpawnQueue {
+        SpawnQueue {
+            spawns: Vec::new(),
+            despawns: Vec::new(),
+        }
+    }
+
+    pub fn spawn(&mut self, spawn: Spawn) {
+        self.spawns.push(spawn);
+    }
+    pub fn despawn(&mut self, entity: specs::Entity) {
+        self.despawns.push(entity);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spawns.is_empty() && self.despawns.is_empty()
+    }
+
+    /// Drains the queue stored as a resource in `world` and applies it.
+    pub fn flush(world: &mut specs::World) {
+        let queue = mem::replace(&mut *world.write_resource::<SpawnQueue>(), SpawnQueue::new());
+        queue.apply(world);
+    }
+
+    pub fn apply(self, world: &mut specs::World) {
+        for spawn in self.spawns {
+            spawn.build(world);
+        }
+
+        {
+            let entities = world.entities();
+            for entity in self.despawns {
+                let _ = entities.delete(entity);
+            }
+        }
+
+        world.maintain();
+    }
+}
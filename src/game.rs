@@ -1,17 +1,19 @@
 use std::os;
+use std::io;
+use std::path::Path;
 
-use world;
-use ecs::component;
-use render;
-use controller;
+use state::{StateStack, TitleState};
+use profile::{self, Profile};
 
 use graphics;
-use opengl_graphics::{self, GlGraphics};
+use opengl_graphics::{self, GlGraphics, GlyphCache};
 use gl;
 use glutin;
-use specs::{self, Join};
-use cgmath;
-use input;
+use chrono;
+use input::{self, InputEvent};
+use console::{Console, CvarValue, GOD_MODE, SHOW_HITBOXES, TIME_SCALE};
+use capture::{self, FrameCapture};
+use render::post::PostProcess;
 
 use game_time::{self, GameTime};
 
@@ -19,32 +21,88 @@ pub struct Game {
     window: glutin::Window,
     evt_loop: glutin::EventsLoop,
     is_running: bool,
-    world: world::World,
     gl_context: Option<GlGraphics>,
+    glyphs: Option<GlyphCache<'static>>,
     input: input::InputManager,
+    states: StateStack,
+    capture: FrameCapture,
+    post: Option<PostProcess>,
+    console: Console,
 }
 
+const FONT_PATH: &str = "assets/fonts/DejaVuSans.ttf";
+const CAPTURE_DIR: &str = "captures";
+const SCREENSHOT_KEY: glutin::VirtualKeyCode = glutin::VirtualKeyCode::F12;
+const RECORD_KEY: glutin::VirtualKeyCode = glutin::VirtualKeyCode::F11;
+const CONSOLE_KEY: glutin::VirtualKeyCode = glutin::VirtualKeyCode::Grave;
+/// Fastest the `speed` command and `time_scale` can make the game run.
+const MAX_TIME_SCALE: f64 = 10.0;
+
 impl Game {
     pub fn build_with_defaults() -> Game {
         let (window, evt_loop) = Game::create_window();
-        let mut entity_set = specs::World::new();
-        component::register_components(&mut entity_set);
 
         Game {
             window,
             evt_loop,
             is_running: false,
-            world: world::World::new(entity_set),
             gl_context: None,
+            glyphs: None,
             input: input::InputManager::new(),
+            states: StateStack::new(),
+            capture: FrameCapture::new(),
+            post: None,
+            console: Game::build_console(),
         }
     }
 
-    pub fn world(&self) -> &world::World {
-        &self.world
+    pub fn states(&self) -> &StateStack {
+        &self.states
+    }
+    pub fn states_mut(&mut self) -> &mut StateStack {
+        &mut self.states
+    }
+
+    /// Saves the next frame to `screenshots/`.
+    pub fn request_screenshot(&mut self) {
+        self.capture.request_screenshot();
+    }
+
+    /// Starts writing every frame to `dir` as a numbered PNG sequence.
+    pub fn start_capture<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        self.capture.start_sequence(dir)
     }
-    pub fn world_mut(&mut self) -> &mut world::World {
-        &mut self.world
+    pub fn stop_capture(&mut self) {
+        self.capture.stop_sequence();
+    }
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_recording()
+    }
+
+    pub fn console(&self) -> &Console {
+        &self.console
+    }
+    pub fn console_mut(&mut self) -> &mut Console {
+        &mut self.console
+    }
+
+    /// A console with the game's commands and variables registered.
+    fn build_console() -> Console {
+        let mut console = Console::new();
+        console.register_command("spawn", "spawn <prefab> [x y] - spawns a prefab");
+        console.register_command("god", "toggles invincibility");
+        console.register_command("speed", "speed <scale> - sets how fast the game runs");
+        console.register_command("stage", "stage <path> - loads a stage");
+        console.register_command("hitboxes", "toggles drawing hitboxes");
+
+        let cvars = console.cvars_mut();
+        cvars.register(TIME_SCALE, CvarValue::Float(1.0), "how fast game time passes");
+        cvars
+            .set_range(TIME_SCALE, 0.0, MAX_TIME_SCALE)
+            .expect("Time scale isn't registered.");
+        cvars.register(GOD_MODE, CvarValue::Bool(false), "the player can't be hit");
+        cvars.register(SHOW_HITBOXES, CvarValue::Bool(false), "draws hitboxes");
+        console
     }
 
     pub fn create_window() -> (glutin::Window, glutin::EventsLoop) {
@@ -65,29 +123,22 @@ impl Game {
     }
 
     pub fn initialize(&mut self) {
-        use render::{RectangleGraphic, RenderGraphicState};
-
         let graphics = init_graphics(&mut self.window);
         self.gl_context = Some(graphics);
-
-        let entity_set = self.world.get_specs_mut();
-        entity_set
-            .create_entity()
-            .with(component::pos::Position(cgmath::Vector2::new(50.0, 50.0)))
-            .with(component::pos::Movable(cgmath::Vector2::new(0.0, 4.0)))
-            .with(component::render::Render::new(
-                RenderGraphicState::Rectangle(RectangleGraphic {
-                    rect: graphics::Rectangle::new([1.0, 0.0, 1.0, 1.0]),
-                    width: 25.0,
-                    height: 25.0,
-                }),
-            ))
-            .with(component::controller::Control::new(
-                Box::new(controller::HumanController::new()),
-            ))
-            .build();
-
-        entity_set.maintain();
+        self.glyphs = Some(GlyphCache::new(FONT_PATH).expect("Unable to load font."));
+
+        let fb_size = self.window.get_inner_size_pixels().unwrap();
+        self.post = match PostProcess::new(fb_size.0, fb_size.1) {
+            Ok(post) => Some(post),
+            Err(e) => {
+                println!("Post-processing disabled: {}", e);
+                None
+            }
+        };
+
+        let profile = Profile::load_or_default(profile::PROFILE_PATH);
+        let settings = profile.options.clone().shared();
+        self.states.push(Box::new(TitleState::new(settings, profile.shared())));
     }
 
     pub fn run(&mut self) {
@@ -106,17 +157,24 @@ impl Game {
         self.is_running = true;
 
         while self.is_running {
+            let time_scale = if self.states.freezes_game_time() {
+                0.0
+            } else {
+                self.console.cvars().get_float(TIME_SCALE, 1.0)
+            };
+            clock.set_clock_multiplier(time_scale);
+
             let time = clock.tick(&game_time::step::FixedStep::new(&fps_counter));
             fps_counter.tick(&time);
 
             self.handle_events(&time);
-
-            self.world
-                .get_specs_mut()
-                .add_resource_with_id(time.clone(), 0);
+            if !self.is_running {
+                break;
+            }
 
             self.update(&time);
             self.draw(&time);
+            self.capture_frame();
 
             self.window.swap_buffers().unwrap();
 
@@ -145,30 +203,123 @@ impl Game {
         }
 
         let events = self.input.get_events(time);
-        println!("{:?}", events);
+        self.handle_capture_keys(&events);
+
+        let console_toggled = events.as_vec().iter().any(|e| match *e {
+            InputEvent::VirtKey(CONSOLE_KEY, _, input::Repeat::NoRepeat) => true,
+            _ => false,
+        });
+        if console_toggled {
+            self.console.toggle();
+        }
+
+        if self.console.is_open() || console_toggled {
+            // Keep typing from reaching the game, and let go of any keys
+            // held when the console opened.
+            self.console.handle_input(&events);
+            self.run_console_commands();
+            let idle = input::InputManager::new();
+            self.is_running = is_running &&
+                self.states.handle_input(&input::InputEvents::new(), &idle);
+        } else {
+            self.is_running = is_running && self.states.handle_input(&events, &self.input);
+        }
+    }
 
-        self.world.get_specs_mut().add_resource(self.input.clone());
+    /// Runs commands typed into the console since last frame. Those that
+    /// only change console variables are handled here; the rest go to the
+    /// current state.
+    fn run_console_commands(&mut self) {
+        for command in self.console.take_invocations() {
+            let console = &mut self.console;
+            match command.name.as_str() {
+                "god" => toggle_cvar(console, GOD_MODE),
+                "hitboxes" => toggle_cvar(console, SHOW_HITBOXES),
+                "speed" => {
+                    let result = match command.args.first() {
+                        Some(scale) => console.cvars_mut().set_str(TIME_SCALE, scale),
+                        None => Ok(()),
+                    };
+                    match result {
+                        Ok(()) => console.execute(TIME_SCALE),
+                        Err(e) => console.print(e.to_string()),
+                    }
+                }
+                _ => {
+                    if !self.states.run_command(&command, console) {
+                        console.print(format!("'{}' can't be used here", command.name));
+                    }
+                }
+            }
+        }
+    }
 
-        self.is_running = is_running;
+    fn handle_capture_keys(&mut self, events: &input::InputEvents) {
+        for event in events {
+            match *event {
+                InputEvent::VirtKey(SCREENSHOT_KEY, _, input::Repeat::NoRepeat) => {
+                    self.request_screenshot()
+                }
+                InputEvent::VirtKey(RECORD_KEY, _, input::Repeat::NoRepeat) => {
+                    if self.is_capturing() {
+                        self.stop_capture();
+                    } else {
+                        let dir = Path::new(CAPTURE_DIR)
+                            .join(chrono::Local::now().format("%Y%m%d_%H%M%S").to_string());
+                        if let Err(e) = self.start_capture(&dir) {
+                            println!("Unable to start capture in {}: {}", dir.display(), e);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
     }
 
     fn update(&mut self, time: &GameTime) {
-        let mut dispatcher = specs::DispatcherBuilder::new()
-            .add(component::pos::MovementSystem, "movement", &[])
-            .add(component::controller::ControlSystem, "control", &[])
-            .build();
-        dispatcher.dispatch(&mut self.world.get_specs_mut().res);
-
-        self.world.get_specs_mut().maintain();
+        self.states.apply_cvars(self.console.cvars());
+        if !self.states.update(time) {
+            self.is_running = false;
+        }
     }
 
     fn draw(&mut self, time: &GameTime) {
         let mut gl_ctx = self.gl_context.as_mut().unwrap();
-        let mut world = &mut self.world;
+        let mut glyphs = self.glyphs.as_mut().unwrap();
+        let states = &mut self.states;
+        let console = &self.console;
 
         let viewport = Game::build_window_viewport(&self.window);
 
-        gl_ctx.draw(viewport, |ctx, gl| { render::render(world, &ctx, gl); });
+        if let Some(ref mut post) = self.post {
+            post.begin(viewport.draw_size[0], viewport.draw_size[1]);
+        }
+
+        gl_ctx.draw(viewport, |ctx, gl| { states.draw(&ctx, gl, glyphs); });
+
+        if let Some(ref mut post) = self.post {
+            post.finish(&states.screen_effects(), gl_ctx);
+        }
+
+        // Drawn after post-processing so it stays legible.
+        if console.is_open() {
+            gl_ctx.draw(viewport, |ctx, gl| { console.draw(&ctx, gl, glyphs); });
+        }
+    }
+
+    /// Hands the frame just drawn to any pending capture.
+    fn capture_frame(&mut self) {
+        if !self.capture.is_pending() {
+            return;
+        }
+
+        let fb_size = self.window.get_inner_size_pixels().unwrap();
+        let frame = capture::read_framebuffer(fb_size.0, fb_size.1);
+        if let Err(e) = self.capture.on_frame(&frame) {
+            let message = format!("Unable to save frame: {}", e);
+            println!("{}", message);
+            self.console.print(message);
+        }
     }
 
     fn post_frame<C: game_time::FrameCount>(&mut self, time: &GameTime, fps_counter: &C) {
@@ -179,12 +330,6 @@ impl Game {
             time.elapsed_wall_time()
         );
         println!("{}", fps_counter.average_frame_rate());
-
-        for e in self.world.get_specs().entities().join() {
-            let pos_reader = self.world.get_specs().read::<component::pos::Position>();
-            let pos = pos_reader.get(e).unwrap();
-            println!(">>> Entity {} @ {:?}", e.id(), pos.0);
-        }
     }
 
     fn build_window_viewport(window: &glutin::Window) -> graphics::Viewport {
@@ -199,6 +344,13 @@ impl Game {
     }
 }
 
+fn toggle_cvar(console: &mut Console, name: &str) {
+    match console.cvars_mut().toggle(name) {
+        Ok(value) => console.print(format!("{} = {}", name, value)),
+        Err(e) => console.print(e.to_string()),
+    }
+}
+
 fn init_graphics(window: &mut glutin::Window) -> GlGraphics {
     gl::load_with(|s| window.get_proc_address(s) as *const os::raw::c_void);
 
@@ -0,0 +1,209 @@
+use std::path::Path;
+
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use toml;
+
+use data::{self, DataError};
+use render;
+
+pub const HUD_PATH: &str = "assets/hud.toml";
+
+/// Layout used when `HUD_PATH` can't be loaded.
+const DEFAULT_LAYOUT: &str = include_str!("../../assets/hud.toml");
+
+/// The value a widget displays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HudSource {
+    Score,
+    HiScore,
+    Lives,
+    Bombs,
+    Power,
+    Graze,
+    Fps,
+    BossTimer,
+}
+
+/// Screen corner a widget's position is measured from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WidgetKind {
+    /// The value as text after an optional label.
+    Text {
+        source: HudSource,
+        #[serde(default)]
+        label: String,
+    },
+    /// One small square per unit of the value, e.g. remaining lives.
+    Pips {
+        source: HudSource,
+        #[serde(default = "default_pip_size")]
+        pip_size: f64,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HudWidget {
+    #[serde(flatten)]
+    pub kind: WidgetKind,
+    pub pos: [f64; 2],
+    #[serde(default = "default_anchor")]
+    pub anchor: Anchor,
+    #[serde(default = "default_font_size")]
+    pub size: u32,
+    #[serde(default = "default_color")]
+    pub color: [f32; 4],
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HudLayout {
+    #[serde(default)]
+    pub widgets: Vec<HudWidget>,
+}
+
+/// Snapshot of everything the HUD can show. Values that aren't currently
+/// available are `None` and their widgets are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct HudValues {
+    pub score: u64,
+    pub hi_score: u64,
+    pub lives: u32,
+    pub bombs: u32,
+    pub power: f64,
+    pub graze: u64,
+    pub fps: Option<f64>,
+    pub boss_time: Option<f64>,
+}
+
+/// Draws a `HudLayout` in screen coordinates, independent of the transform
+/// used for the playfield.
+pub struct Hud {
+    layout: HudLayout,
+}
+
+fn default_pip_size() -> f64 {
+    12.0
+}
+fn default_anchor() -> Anchor {
+    Anchor::TopLeft
+}
+fn default_font_size() -> u32 {
+    18
+}
+fn default_color() -> [f32; 4] {
+    [0.1, 0.1, 0.1, 1.0]
+}
+
+impl HudValues {
+    pub fn text(&self, source: HudSource) -> Option<String> {
+        match source {
+            HudSource::Score => Some(format!("{:010}", self.score)),
+            HudSource::HiScore => Some(format!("{:010}", self.hi_score.max(self.score))),
+            HudSource::Lives => Some(self.lives.to_string()),
+            HudSource::Bombs => Some(self.bombs.to_string()),
+            HudSource::Power => Some(format!("{:.2}", self.power)),
+            HudSource::Graze => Some(self.graze.to_string()),
+            HudSource::Fps => self.fps.map(|fps| format!("{:.1}", fps)),
+            HudSource::BossTimer => self.boss_time.map(|t| format!("{:05.2}", t)),
+        }
+    }
+
+    pub fn count(&self, source: HudSource) -> Option<u32> {
+        match source {
+            HudSource::Lives => Some(self.lives),
+            HudSource::Bombs => Some(self.bombs),
+            HudSource::Power => Some(self.power.floor() as u32),
+            _ => None,
+        }
+    }
+}
+
+impl Hud {
+    pub fn new(layout: HudLayout) -> Hud {
+        Hud { layout }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Hud, DataError> {
+        Ok(Hud::new(data::load_toml(path)?))
+    }
+
+    /// Loads `HUD_PATH`, falling back to the layout built into the binary.
+    pub fn load_or_default() -> Hud {
+        Hud::load(HUD_PATH).unwrap_or_else(|e| {
+            println!("Unable to load {}: {}", HUD_PATH, e);
+            Hud::new(toml::from_str(DEFAULT_LAYOUT).expect("Invalid built-in HUD layout."))
+        })
+    }
+
+    pub fn layout(&self) -> &HudLayout {
+        &self.layout
+    }
+
+    pub fn draw(
+        &self,
+        values: &HudValues,
+        ctx: &graphics::Context,
+        gl: &mut GlGraphics,
+        glyphs: &mut GlyphCache,
+    ) {
+        let screen = graphics::Context {
+            transform: ctx.view,
+            ..*ctx
+        };
+        let view_size = ctx.get_view_size();
+
+        for widget in &self.layout.widgets {
+            let pos = resolve(widget.pos, widget.anchor, view_size);
+
+            match widget.kind {
+                WidgetKind::Text { source, ref label } => {
+                    if let Some(value) = values.text(source) {
+                        let text = format!("{}{}", label, value);
+                        render::render_text(
+                            &text,
+                            pos,
+                            widget.size,
+                            widget.color,
+                            &screen,
+                            gl,
+                            glyphs,
+                        );
+                    }
+                }
+                WidgetKind::Pips { source, pip_size } => {
+                    let count = values.count(source).unwrap_or(0);
+                    let pip = graphics::Rectangle::new(widget.color);
+                    for i in 0..count {
+                        let x = pos[0] + i as f64 * pip_size * 1.5;
+                        pip.draw(
+                            [x, pos[1] - pip_size, pip_size, pip_size],
+                            &screen.draw_state,
+                            screen.transform,
+                            gl,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn resolve(pos: [f64; 2], anchor: Anchor, view_size: [f64; 2]) -> [f64; 2] {
+    match anchor {
+        Anchor::TopLeft => pos,
+        Anchor::TopRight => [view_size[0] - pos[0], pos[1]],
+        Anchor::BottomLeft => [pos[0], view_size[1] - pos[1]],
+        Anchor::BottomRight => [view_size[0] - pos[0], view_size[1] - pos[1]],
+    }
+}
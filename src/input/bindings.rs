@@ -0,0 +1,93 @@
+use glutin::VirtualKeyCode;
+
+/// Keys that can be bound to an action, which are also the keys that can be
+/// read back from a saved profile.
+const BINDABLE_KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::A, VirtualKeyCode::B, VirtualKeyCode::C, VirtualKeyCode::D,
+    VirtualKeyCode::E, VirtualKeyCode::F, VirtualKeyCode::G, VirtualKeyCode::H,
+    VirtualKeyCode::I, VirtualKeyCode::J, VirtualKeyCode::K, VirtualKeyCode::L,
+    VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O, VirtualKeyCode::P,
+    VirtualKeyCode::Q, VirtualKeyCode::R, VirtualKeyCode::S, VirtualKeyCode::T,
+    VirtualKeyCode::U, VirtualKeyCode::V, VirtualKeyCode::W, VirtualKeyCode::X,
+    VirtualKeyCode::Y, VirtualKeyCode::Z,
+    VirtualKeyCode::Key0, VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+    VirtualKeyCode::Up, VirtualKeyCode::Down, VirtualKeyCode::Left, VirtualKeyCode::Right,
+    VirtualKeyCode::Space, VirtualKeyCode::Return, VirtualKeyCode::Tab, VirtualKeyCode::Back,
+    VirtualKeyCode::LShift, VirtualKeyCode::RShift, VirtualKeyCode::LControl,
+    VirtualKeyCode::RControl, VirtualKeyCode::LAlt, VirtualKeyCode::RAlt,
+    VirtualKeyCode::Comma, VirtualKeyCode::Period, VirtualKeyCode::Slash,
+    VirtualKeyCode::Semicolon, VirtualKeyCode::Apostrophe, VirtualKeyCode::LBracket,
+    VirtualKeyCode::RBracket,
+];
+
+/// Keys used to control the player. Saved by key name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    #[serde(with = "key_name")]
+    pub up: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub down: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub left: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub right: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub shoot: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub focus: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub bomb: VirtualKeyCode,
+}
+
+impl KeyBindings {
+    pub fn new() -> KeyBindings {
+        KeyBindings {
+            up: VirtualKeyCode::Up,
+            down: VirtualKeyCode::Down,
+            left: VirtualKeyCode::Left,
+            right: VirtualKeyCode::Right,
+            shoot: VirtualKeyCode::Z,
+            focus: VirtualKeyCode::LShift,
+            bomb: VirtualKeyCode::X,
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings::new()
+    }
+}
+
+/// Looks up a bindable key by the name it's saved under.
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|key| format!("{:?}", key) == name)
+        .cloned()
+}
+
+mod key_name {
+    use glutin::VirtualKeyCode;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    pub fn serialize<S>(key: &VirtualKeyCode, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!("{:?}", key))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<VirtualKeyCode, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        super::key_from_name(&name)
+            .ok_or_else(|| D::Error::custom(format!("unknown key '{}'", name)))
+    }
+}
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
 #[derive(Debug, Default)]
-pub struct CommandDirectory<'a> {
-    commands: HashMap<&'a str, u32>,
+pub struct CommandDirectory {
+    commands: HashMap<String, u32>,
     commands_rev: HashMap<u32, String>,
     next_id: u32,
 }
@@ -13,8 +13,8 @@ pub struct Command<'a> {
     name: &'a str,
 }
 
-impl<'a> CommandDirectory<'a> {
-    pub fn new() -> CommandDirectory<'a> {
+impl CommandDirectory {
+    pub fn new() -> CommandDirectory {
         CommandDirectory {
             commands: HashMap::new(),
             commands_rev: HashMap::new(),
@@ -22,15 +22,24 @@ impl<'a> CommandDirectory<'a> {
         }
     }
 
-    pub fn register_command(&'a mut self, name: String) -> Command<'a> {
-        let id = self.next_id;
-        self.next_id += 1;
+    /// Registers `name`, or returns the existing command if it's already
+    /// registered.
+    pub fn register_command(&mut self, name: String) -> Command {
+        let id = match self.commands.get(&name) {
+            Some(&id) => id,
+            None => {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.commands.insert(name.clone(), id);
+                self.commands_rev.insert(id, name);
+                id
+            }
+        };
 
-        self.commands_rev.insert(id, name);
-        let name_str = &self.commands_rev[&id];
-        self.commands.insert(name_str, id);
-
-        Command { id, name: name_str }
+        Command {
+            id,
+            name: &self.commands_rev[&id],
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -40,7 +49,7 @@ impl<'a> CommandDirectory<'a> {
         self.commands.is_empty()
     }
 
-    pub fn get_command_by_name(&'a self, name: &str) -> Option<Command<'a>> {
+    pub fn get_command_by_name(&self, name: &str) -> Option<Command> {
         if let Some(&id) = self.commands.get(name) {
             let name_str = &self.commands_rev[&id];
             Some(Command { id, name: name_str })
@@ -49,7 +58,7 @@ impl<'a> CommandDirectory<'a> {
         }
     }
 
-    pub fn get_command_by_id(&'a self, id: u32) -> Option<Command<'a>> {
+    pub fn get_command_by_id(&self, id: u32) -> Option<Command> {
         if let Some(name) = self.commands_rev.get(&id) {
             Some(Command {
                 id: id,
@@ -59,6 +68,24 @@ impl<'a> CommandDirectory<'a> {
             None
         }
     }
+
+    /// Names of every command starting with `prefix`, in alphabetical order.
+    pub fn complete(&self, prefix: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = self.commands
+            .keys()
+            .map(|name| name.as_str())
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        names
+    }
 }
 
-impl<'a> Command<'a> {}
+impl<'a> Command<'a> {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+}
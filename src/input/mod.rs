@@ -4,13 +4,14 @@ use std::mem;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 
-use glutin::{VirtualKeyCode, ElementState, WindowEvent};
+use glutin::{VirtualKeyCode, ElementState, MouseButton, WindowEvent};
 use winit;
 use chrono;
 use game_time::GameTime;
 use float_duration::{TimePoint, FloatDuration};
 
 pub mod command;
+pub mod bindings;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Repeat {
@@ -30,13 +31,15 @@ pub struct Modifiers {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Button {
     VirtualKey(VirtualKeyCode),
-    Mouse,
+    Mouse(MouseButton),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputEvent {
     Character(char),
     VirtKey(VirtualKeyCode, Modifiers, Repeat),
+    MouseDown(MouseButton, [f64; 2]),
+    MouseUp(MouseButton, [f64; 2]),
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +48,7 @@ pub struct InputManager {
     keys_down: HashMap<Button, chrono::DateTime<chrono::Local>>,
     modifiers: Modifiers,
     text_repeat: FloatDuration,
+    mouse_pos: [f64; 2],
 }
 
 #[derive(Debug, Clone, Default)]
@@ -87,6 +91,7 @@ impl InputManager {
             keys_down: HashMap::new(),
             modifiers: Modifiers::new(),
             text_repeat: FloatDuration::seconds(0.25),
+            mouse_pos: [0.0, 0.0],
         }
     }
 
@@ -114,6 +119,20 @@ impl InputManager {
                     }
                 }
             }
+            WindowEvent::MouseMoved(x, y) => {
+                self.mouse_pos = [x as f64, y as f64];
+            }
+            WindowEvent::MouseInput(state, button) => {
+                let pos = self.mouse_pos;
+                if state == ElementState::Pressed {
+                    self.keys_down
+                        .insert(Button::Mouse(button), time.frame_start_time());
+                    self.frame_events.push(InputEvent::MouseDown(button, pos));
+                } else {
+                    self.keys_down.remove(&Button::Mouse(button));
+                    self.frame_events.push(InputEvent::MouseUp(button, pos));
+                }
+            }
             WindowEvent::ReceivedCharacter(ch) => {
                 self.frame_events.push(InputEvent::Character(ch));
             }
@@ -153,6 +172,15 @@ impl InputManager {
     pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
         self.keys_down.contains_key(&Button::VirtualKey(key))
     }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.keys_down.contains_key(&Button::Mouse(button))
+    }
+
+    /// Last known cursor position in window coordinates.
+    pub fn mouse_position(&self) -> [f64; 2] {
+        self.mouse_pos
+    }
 }
 
 impl Default for InputManager {
@@ -0,0 +1,44 @@
+extern crate glutin;
+extern crate winit;
+extern crate chrono;
+extern crate cgmath;
+extern crate float_duration;
+extern crate game_time;
+extern crate graphics;
+extern crate opengl_graphics;
+extern crate gl;
+extern crate specs;
+extern crate shred;
+#[macro_use]
+extern crate shred_derive;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+extern crate image;
+#[cfg(feature = "rodio")]
+extern crate rodio;
+
+pub mod game;
+pub mod data;
+pub mod world;
+pub mod ecs;
+pub mod render;
+pub mod input;
+pub mod controller;
+pub mod pattern;
+pub mod stage;
+pub mod script;
+pub mod score;
+pub mod state;
+pub mod settings;
+pub mod ui;
+pub mod hud;
+pub mod rng;
+pub mod particle;
+pub mod capture;
+pub mod background;
+pub mod audio;
+pub mod difficulty;
+pub mod profile;
+pub mod console;
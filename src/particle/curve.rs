@@ -0,0 +1,117 @@
+/// Piecewise linear function of a particle's normalized age (0 at birth,
+/// 1 at death).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    keys: Vec<(f32, f32)>,
+}
+
+/// Like `Curve` but interpolating RGBA colors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorCurve {
+    keys: Vec<(f32, [f32; 4])>,
+}
+
+impl Curve {
+    pub fn constant(value: f32) -> Curve {
+        Curve { keys: vec![(0.0, value)] }
+    }
+
+    pub fn linear(from: f32, to: f32) -> Curve {
+        Curve { keys: vec![(0.0, from), (1.0, to)] }
+    }
+
+    /// Builds a curve from `(t, value)` keys. Keys are sorted by `t`.
+    pub fn from_keys(mut keys: Vec<(f32, f32)>) -> Curve {
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(::std::cmp::Ordering::Equal));
+        Curve { keys }
+    }
+
+    pub fn sample(&self, t: f32) -> f32 {
+        sample(&self.keys, t, |a, b, f| a + (b - a) * f).unwrap_or(0.0)
+    }
+}
+
+impl ColorCurve {
+    pub fn constant(color: [f32; 4]) -> ColorCurve {
+        ColorCurve { keys: vec![(0.0, color)] }
+    }
+
+    pub fn linear(from: [f32; 4], to: [f32; 4]) -> ColorCurve {
+        ColorCurve { keys: vec![(0.0, from), (1.0, to)] }
+    }
+
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        sample(&self.keys, t, |a, b, f| {
+            [
+                a[0] + (b[0] - a[0]) * f,
+                a[1] + (b[1] - a[1]) * f,
+                a[2] + (b[2] - a[2]) * f,
+                a[3] + (b[3] - a[3]) * f,
+            ]
+        }).unwrap_or([1.0; 4])
+    }
+}
+
+fn sample<T: Copy, F: Fn(T, T, f32) -> T>(keys: &[(f32, T)], t: f32, lerp: F) -> Option<T> {
+    let first = match keys.first() {
+        Some(&(_, v)) => v,
+        None => return None,
+    };
+
+    let mut prev = (0.0, first);
+    for &(key_t, value) in keys {
+        if t <= key_t {
+            let span = key_t - prev.0;
+            if span <= 0.0 {
+                return Some(value);
+            }
+            return Some(lerp(prev.1, value, (t - prev.0) / span));
+        }
+        prev = (key_t, value);
+    }
+
+    Some(prev.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorCurve, Curve};
+
+    #[test]
+    fn curves_interpolate_between_keys() {
+        let curve = Curve::linear(0.0, 10.0);
+        assert_eq!(curve.sample(0.0), 0.0);
+        assert_eq!(curve.sample(0.5), 5.0);
+        assert_eq!(curve.sample(1.0), 10.0);
+
+        let color = ColorCurve::linear([0.0, 0.0, 0.0, 1.0], [1.0, 0.5, 0.0, 0.0]);
+        assert_eq!(color.sample(0.5), [0.5, 0.25, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn curves_clamp_outside_their_keys() {
+        let curve = Curve::from_keys(vec![(0.25, 2.0), (0.75, 4.0)]);
+        assert_eq!(curve.sample(-1.0), 2.0);
+        assert_eq!(curve.sample(0.0), 2.0);
+        assert_eq!(curve.sample(1.0), 4.0);
+        assert_eq!(curve.sample(2.0), 4.0);
+
+        let color = ColorCurve::constant([0.2, 0.4, 0.6, 0.8]);
+        assert_eq!(color.sample(-1.0), [0.2, 0.4, 0.6, 0.8]);
+        assert_eq!(color.sample(2.0), [0.2, 0.4, 0.6, 0.8]);
+    }
+
+    #[test]
+    fn from_keys_sorts_keys() {
+        let curve = Curve::from_keys(vec![(1.0, 0.0), (0.0, 1.0), (0.5, 3.0)]);
+        assert_eq!(curve, Curve::from_keys(vec![(0.0, 1.0), (0.5, 3.0), (1.0, 0.0)]));
+        assert_eq!(curve.sample(0.25), 2.0);
+        assert_eq!(curve.sample(0.75), 1.5);
+    }
+
+    #[test]
+    fn empty_curves_have_defaults() {
+        assert_eq!(Curve::from_keys(Vec::new()).sample(0.5), 0.0);
+        assert_eq!(ColorCurve { keys: Vec::new() }.sample(0.5), [1.0; 4]);
+    }
+}
@@ -0,0 +1,93 @@
+use std::f64::consts::PI;
+
+use particle::curve::{ColorCurve, Curve};
+
+/// Everything needed to spawn and animate one kind of particle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterConfig {
+    /// Particles per second for continuous emitters.
+    pub rate: f64,
+    pub lifetime: (f64, f64),
+    pub speed: (f64, f64),
+    /// Center of the velocity cone, in radians.
+    pub direction: f64,
+    /// Total width of the velocity cone, in radians.
+    pub spread: f64,
+    pub gravity: [f64; 2],
+    pub size: Curve,
+    pub alpha: Curve,
+    pub color: ColorCurve,
+    pub additive: bool,
+}
+
+/// A continuous source of particles, e.g. a trail.
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    pub style: usize,
+    pub pos: [f64; 2],
+    /// Seconds left to emit for, or `None` to emit until removed.
+    pub remaining: Option<f64>,
+    pub(super) accumulator: f64,
+}
+
+impl EmitterConfig {
+    pub fn explosion() -> EmitterConfig {
+        EmitterConfig {
+            rate: 0.0,
+            lifetime: (0.3, 0.8),
+            speed: (60.0, 260.0),
+            direction: 0.0,
+            spread: 2.0 * PI,
+            gravity: [0.0, 0.0],
+            size: Curve::linear(6.0, 1.0),
+            alpha: Curve::from_keys(vec![(0.0, 1.0), (0.6, 0.8), (1.0, 0.0)]),
+            color: ColorCurve::linear([1.0, 0.9, 0.4, 1.0], [0.9, 0.2, 0.1, 1.0]),
+            additive: true,
+        }
+    }
+
+    pub fn hit_spark() -> EmitterConfig {
+        EmitterConfig {
+            rate: 0.0,
+            lifetime: (0.1, 0.25),
+            speed: (120.0, 300.0),
+            direction: -PI / 2.0,
+            spread: PI / 2.0,
+            gravity: [0.0, 400.0],
+            size: Curve::constant(2.0),
+            alpha: Curve::linear(1.0, 0.0),
+            color: ColorCurve::constant([1.0, 1.0, 0.8, 1.0]),
+            additive: true,
+        }
+    }
+
+    pub fn trail() -> EmitterConfig {
+        EmitterConfig {
+            rate: 60.0,
+            lifetime: (0.3, 0.5),
+            speed: (10.0, 30.0),
+            direction: PI / 2.0,
+            spread: PI / 4.0,
+            gravity: [0.0, 0.0],
+            size: Curve::linear(4.0, 0.0),
+            alpha: Curve::linear(0.6, 0.0),
+            color: ColorCurve::constant([0.6, 0.8, 1.0, 1.0]),
+            additive: true,
+        }
+    }
+}
+
+impl Emitter {
+    pub fn new(style: usize, pos: [f64; 2], duration: Option<f64>) -> Emitter {
+        Emitter {
+            style,
+            pos,
+            remaining: duration,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining.map_or(false, |r| r <= 0.0)
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use graphics;
+use opengl_graphics::GlGraphics;
+
+use render::QuadBatch;
+use rng::Rng;
+
+pub mod curve;
+pub mod emitter;
+
+pub use self::curve::{ColorCurve, Curve};
+pub use self::emitter::{Emitter, EmitterConfig};
+
+/// Index of an `EmitterConfig` registered with a `ParticleSystem`.
+pub type StyleId = usize;
+/// Handle to an emitter added to a `ParticleSystem`.
+pub type EmitterId = u64;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Particle {
+    pos: [f64; 2],
+    vel: [f64; 2],
+    age: f64,
+    lifetime: f64,
+    style: StyleId,
+}
+
+impl Particle {
+    /// How far through its life the particle is, from 0 to 1. A particle
+    /// with no lifetime counts as already at the end.
+    fn progress(&self) -> f64 {
+        if self.lifetime > 0.0 {
+            (self.age / self.lifetime).min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Simulates and draws short-lived visual particles in bulk, outside of
+/// the entity world.
+pub struct ParticleSystem {
+    styles: Vec<EmitterConfig>,
+    particles: Vec<Particle>,
+    emitters: HashMap<EmitterId, Emitter>,
+    next_emitter: EmitterId,
+    max_particles: usize,
+    rng: Rng,
+    batch: QuadBatch,
+}
+
+impl ParticleSystem {
+    pub fn new(max_particles: usize) -> ParticleSystem {
+        ParticleSystem {
+            styles: Vec::new(),
+            particles: Vec::with_capacity(max_particles),
+            emitters: HashMap::new(),
+            next_emitter: 0,
+            max_particles,
+            rng: Rng::default(),
+            batch: QuadBatch::new(),
+        }
+    }
+
+    pub fn add_style(&mut self, config: EmitterConfig) -> StyleId {
+        self.styles.push(config);
+        self.styles.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.emitters.clear();
+    }
+
+    /// Emits `count` particles at once, e.g. for an explosion.
+    pub fn burst(&mut self, style: StyleId, pos: [f64; 2], count: usize) {
+        for _ in 0..count {
+            self.spawn(style, pos);
+        }
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) -> EmitterId {
+        let id = self.next_emitter;
+        self.next_emitter += 1;
+        self.emitters.insert(id, emitter);
+        id
+    }
+
+    /// Moves an emitter, e.g. to follow an entity. Returns false if it has
+    /// finished and been removed.
+    pub fn move_emitter(&mut self, id: EmitterId, pos: [f64; 2]) -> bool {
+        match self.emitters.get_mut(&id) {
+            Some(emitter) => {
+                emitter.pos = pos;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_emitter(&mut self, id: EmitterId) {
+        self.emitters.remove(&id);
+    }
+
+    fn spawn(&mut self, style: StyleId, pos: [f64; 2]) {
+        if self.particles.len() >= self.max_particles {
+            return;
+        }
+        let (lifetime, speed, angle) = match self.styles.get(style) {
+            Some(config) => (
+                self.rng.range(config.lifetime.0, config.lifetime.1),
+                self.rng.range(config.speed.0, config.speed.1),
+                config.direction + self.rng.range(-0.5, 0.5) * config.spread,
+            ),
+            None => return,
+        };
+
+        let (sin, cos) = angle.sin_cos();
+        self.particles.push(Particle {
+            pos,
+            vel: [cos * speed, sin * speed],
+            age: 0.0,
+            lifetime,
+            style,
+        });
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        let mut emitters = ::std::mem::replace(&mut self.emitters, HashMap::new());
+        for emitter in emitters.values_mut() {
+            let rate = self.styles.get(emitter.style).map_or(0.0, |s| s.rate);
+            emitter.accumulator += rate * dt;
+            while emitter.accumulator >= 1.0 {
+                emitter.accumulator -= 1.0;
+                self.spawn(emitter.style, emitter.pos);
+            }
+            if let Some(ref mut remaining) = emitter.remaining {
+                *remaining -= dt;
+            }
+        }
+        emitters.retain(|_, e| !e.is_finished());
+        self.emitters = emitters;
+
+        let styles = &self.styles;
+        for p in &mut self.particles {
+            let gravity = styles[p.style].gravity;
+            p.age += dt;
+            p.vel[0] += gravity[0] * dt;
+            p.vel[1] += gravity[1] * dt;
+            p.pos[0] += p.vel[0] * dt;
+            p.pos[1] += p.vel[1] * dt;
+        }
+
+        let mut i = 0;
+        while i < self.particles.len() {
+            if self.particles[i].age >= self.particles[i].lifetime {
+                self.particles.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Draws every live particle in a single batched pass.
+    pub fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics) {
+        for p in &self.particles {
+            let style = &self.styles[p.style];
+            let t = p.progress() as f32;
+            let size = style.size.sample(t) as f64;
+            let mut color = style.color.sample(t);
+            color[3] *= style.alpha.sample(t);
+
+            if size <= 0.0 || color[3] <= 0.0 {
+                continue;
+            }
+
+            self.batch.push_rect(
+                [p.pos[0] - size / 2.0, p.pos[1] - size / 2.0, size, size],
+                color,
+                style.additive,
+                ctx.transform,
+            );
+        }
+
+        self.batch.flush(&ctx.draw_state, gl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Emitter, EmitterConfig, Particle, ParticleSystem};
+
+    fn style(lifetime: f64, rate: f64) -> EmitterConfig {
+        EmitterConfig {
+            rate,
+            lifetime: (lifetime, lifetime),
+            gravity: [0.0, 0.0],
+            ..EmitterConfig::explosion()
+        }
+    }
+
+    #[test]
+    fn particles_are_capped() {
+        let mut system = ParticleSystem::new(5);
+        let burst = system.add_style(style(1.0, 0.0));
+        let trail = system.add_style(style(1.0, 60.0));
+
+        system.burst(burst, [0.0, 0.0], 10);
+        assert_eq!(system.len(), 5);
+
+        system.add_emitter(Emitter::new(trail, [0.0, 0.0], None));
+        system.update(0.5);
+        assert_eq!(system.len(), 5);
+    }
+
+    #[test]
+    fn expired_particles_are_removed() {
+        let mut system = ParticleSystem::new(10);
+        let short = system.add_style(style(1.0, 0.0));
+        let long = system.add_style(style(3.0, 0.0));
+
+        system.burst(short, [0.0, 0.0], 3);
+        system.burst(long, [0.0, 0.0], 2);
+        system.update(0.5);
+        assert_eq!(system.len(), 5);
+
+        system.update(0.6);
+        assert_eq!(system.len(), 2);
+        assert!(system.particles.iter().all(|p| p.style == long));
+    }
+
+    #[test]
+    fn emitters_finish_when_their_time_runs_out() {
+        let mut system = ParticleSystem::new(100);
+        let trail = system.add_style(style(10.0, 10.0));
+        let id = system.add_emitter(Emitter::new(trail, [0.0, 0.0], Some(0.5)));
+
+        system.update(0.25);
+        assert!(system.move_emitter(id, [1.0, 1.0]));
+        system.update(0.25);
+        assert!(!system.move_emitter(id, [1.0, 1.0]));
+        assert_eq!(system.len(), 5);
+
+        system.update(0.25);
+        assert_eq!(system.len(), 5);
+    }
+
+    #[test]
+    fn progress_handles_zero_lifetime() {
+        let mut particle = Particle {
+            pos: [0.0, 0.0],
+            vel: [0.0, 0.0],
+            age: 0.0,
+            lifetime: 0.0,
+            style: 0,
+        };
+        assert_eq!(particle.progress(), 1.0);
+
+        particle.lifetime = 2.0;
+        particle.age = 0.5;
+        assert_eq!(particle.progress(), 0.25);
+    }
+}
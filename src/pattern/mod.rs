@@ -0,0 +1,276 @@
+use std::f64::consts::PI;
+
+use cgmath::Vector2;
+use graphics;
+
+use controller::{Action, Controller, ControllerContext, ControllerSave, Projectile};
+use ecs::component::bullet::Faction;
+use render::{RectangleGraphic, RenderGraphicState};
+use difficulty::{Difficulty, PerDifficulty, Scaling};
+
+/// Shape of a single volley of bullets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Pattern {
+    /// `count` bullets spread evenly around a circle.
+    Ring { count: u32, speed: f64 },
+    /// `count` bullets fanned over `spread` radians, centered on the player.
+    Aimed { count: u32, spread: f64, speed: f64 },
+    /// `arms` bullets around a circle whose starting angle advances by
+    /// `turn` radians every volley.
+    Spiral { arms: u32, speed: f64, turn: f64 },
+}
+
+/// A pattern together with how often and for how long it fires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternSpec {
+    pub pattern: Pattern,
+    pub interval: f64,
+    #[serde(default)]
+    pub duration: Option<f64>,
+    #[serde(default = "default_bullet_color")]
+    pub color: [f32; 4],
+    #[serde(default = "default_bullet_size")]
+    pub size: f64,
+    /// Fires this prefab instead of a plain bullet, ignoring `color` and
+    /// `size`.
+    #[serde(default)]
+    pub prefab: Option<String>,
+    /// Values used on particular difficulties instead of scaling this
+    /// spec's own.
+    #[serde(default)]
+    pub by_difficulty: PerDifficulty<PatternOverrides>,
+}
+
+/// Replacements for a pattern's parameters on one difficulty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatternOverrides {
+    /// Bullets per volley, or arms of a spiral.
+    pub count: Option<u32>,
+    pub speed: Option<f64>,
+    pub interval: Option<f64>,
+}
+
+/// Fires a `PatternSpec` from the controlled entity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternController {
+    spec: PatternSpec,
+    elapsed: f64,
+    next_fire: f64,
+    angle: f64,
+    despawn_when_done: bool,
+    /// Set once the spec has been adjusted for the difficulty.
+    scaled: bool,
+}
+
+/// Runs several patterns at once from the same entity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternSet {
+    controllers: Vec<PatternController>,
+}
+
+fn default_bullet_color() -> [f32; 4] {
+    [0.2, 0.3, 1.0, 1.0]
+}
+
+fn default_bullet_size() -> f64 {
+    8.0
+}
+
+impl Pattern {
+    /// Bullets per volley.
+    pub fn count(&self) -> u32 {
+        match *self {
+            Pattern::Ring { count, .. } | Pattern::Aimed { count, .. } => count,
+            Pattern::Spiral { arms, .. } => arms,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        match *self {
+            Pattern::Ring { speed, .. } |
+            Pattern::Aimed { speed, .. } |
+            Pattern::Spiral { speed, .. } => speed,
+        }
+    }
+
+    pub fn with_count_and_speed(&self, count: u32, speed: f64) -> Pattern {
+        match *self {
+            Pattern::Ring { .. } => Pattern::Ring { count, speed },
+            Pattern::Aimed { spread, .. } => Pattern::Aimed {
+                count,
+                spread,
+                speed,
+            },
+            Pattern::Spiral { turn, .. } => Pattern::Spiral {
+                arms: count,
+                speed,
+                turn,
+            },
+        }
+    }
+
+    /// This pattern with the difficulty's bullet count and speed applied.
+    pub fn scaled(&self, scaling: &Scaling) -> Pattern {
+        self.with_count_and_speed(scaling.count(self.count()), self.speed() * scaling.bullet_speed)
+    }
+
+    /// Bullet velocities for one volley, starting at `base_angle` radians.
+    pub fn volley(&self, base_angle: f64, aim: Option<Vector2<f64>>) -> Vec<Vector2<f64>> {
+        match *self {
+            Pattern::Ring { count, speed } => ring(count, speed, base_angle),
+            Pattern::Spiral { arms, speed, .. } => ring(arms, speed, base_angle),
+            Pattern::Aimed {
+                count,
+                spread,
+                speed,
+            } => {
+                let center = aim.map_or(PI / 2.0, |v| v.y.atan2(v.x));
+                if count <= 1 {
+                    return vec![polar(center, speed)];
+                }
+                let step = spread / (count - 1) as f64;
+                (0..count)
+                    .map(|i| polar(center - spread / 2.0 + step * i as f64, speed))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl PatternSpec {
+    /// This spec as played on `difficulty`: overridden values where the
+    /// data gives them, scaled ones otherwise.
+    pub fn for_difficulty(&self, difficulty: Difficulty, scaling: &Scaling) -> PatternSpec {
+        let overrides = self.by_difficulty.get(difficulty).cloned().unwrap_or_default();
+        let count = overrides
+            .count
+            .unwrap_or_else(|| scaling.count(self.pattern.count()));
+        let speed = overrides
+            .speed
+            .unwrap_or_else(|| self.pattern.speed() * scaling.bullet_speed);
+
+        PatternSpec {
+            pattern: self.pattern.with_count_and_speed(count, speed),
+            interval: overrides
+                .interval
+                .unwrap_or_else(|| self.interval / scaling.fire_rate),
+            ..self.clone()
+        }
+    }
+
+    pub fn graphic(&self) -> RenderGraphicState {
+        RenderGraphicState::Rectangle(RectangleGraphic {
+            rect: graphics::Rectangle::new(self.color),
+            width: self.size,
+            height: self.size,
+        })
+    }
+}
+
+impl PatternController {
+    pub fn new(spec: PatternSpec) -> PatternController {
+        PatternController {
+            spec,
+            elapsed: 0.0,
+            next_fire: 0.0,
+            angle: 0.0,
+            despawn_when_done: false,
+            scaled: false,
+        }
+    }
+
+    /// Creates a controller for a bare emitter entity that removes itself
+    /// once the pattern's duration runs out.
+    pub fn emitter(spec: PatternSpec) -> PatternController {
+        PatternController {
+            despawn_when_done: true,
+            ..PatternController::new(spec)
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.spec.duration.map_or(false, |d| self.elapsed >= d)
+    }
+}
+
+impl Controller for PatternController {
+    fn do_actions(&mut self, ctx: &ControllerContext) -> Vec<Action> {
+        let mut actions = Vec::new();
+
+        if !self.scaled {
+            self.spec = self.spec.for_difficulty(ctx.difficulty, ctx.scaling);
+            self.scaled = true;
+        }
+
+        if self.is_done() {
+            if self.despawn_when_done {
+                actions.push(Action::Despawn(ctx.entity));
+            }
+            return actions;
+        }
+
+        self.elapsed += ctx.time.elapsed_game_time().as_seconds();
+
+        while self.next_fire <= self.elapsed && !self.is_done() {
+            let graphic = self.spec.graphic();
+            for vel in self.spec.pattern.volley(self.angle, ctx.aim_at_player()) {
+                actions.push(match self.spec.prefab {
+                    Some(ref name) => Action::SpawnPrefab {
+                        name: name.clone(),
+                        offset: Vector2::new(0.0, 0.0),
+                        vel: Some(vel),
+                    },
+                    None => Action::Fire(Projectile {
+                        offset: Vector2::new(0.0, 0.0),
+                        vel,
+                        graphic: graphic.clone(),
+                        faction: Faction::Enemy,
+                    }),
+                });
+            }
+
+            if let Pattern::Spiral { turn, .. } = self.spec.pattern {
+                self.angle = (self.angle + turn) % (2.0 * PI);
+            }
+            self.next_fire += self.spec.interval.max(0.01);
+        }
+
+        actions
+    }
+
+    fn save(&self) -> Option<ControllerSave> {
+        Some(ControllerSave::Pattern(self.clone()))
+    }
+}
+
+impl PatternSet {
+    pub fn new(specs: Vec<PatternSpec>) -> PatternSet {
+        PatternSet { controllers: specs.into_iter().map(PatternController::new).collect() }
+    }
+}
+
+impl Controller for PatternSet {
+    fn do_actions(&mut self, ctx: &ControllerContext) -> Vec<Action> {
+        self.controllers
+            .iter_mut()
+            .flat_map(|c| c.do_actions(ctx))
+            .collect()
+    }
+
+    fn save(&self) -> Option<ControllerSave> {
+        Some(ControllerSave::PatternSet(self.clone()))
+    }
+}
+
+fn ring(count: u32, speed: f64, base_angle: f64) -> Vec<Vector2<f64>> {
+    let step = 2.0 * PI / count.max(1) as f64;
+    (0..count)
+        .map(|i| polar(base_angle + step * i as f64, speed))
+        .collect()
+}
+
+fn polar(angle: f64, speed: f64) -> Vector2<f64> {
+    let (sin, cos) = angle.sin_cos();
+    Vector2::new(cos * speed, sin * speed)
+}
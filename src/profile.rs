@@ -0,0 +1,365 @@
+use std::collections::BTreeSet;
+use std::io::{self, Read};
+use std::fs::File;
+use std::path::Path;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::error;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use toml;
+
+use data;
+use difficulty::Difficulty;
+use settings::Settings;
+
+pub const PROFILE_PATH: &str = "profile.toml";
+/// Entries kept in each high score table.
+pub const MAX_SCORES: usize = 10;
+
+/// Version written by this build. Bump it and add a step to `MIGRATIONS`
+/// whenever the file format changes.
+const CURRENT_VERSION: u32 = 1;
+
+/// Upgrades a profile from version `i` to `i + 1`, in place.
+type Migration = fn(&mut toml::value::Table);
+
+const MIGRATIONS: &[Migration] = &[add_shot_types];
+
+/// Shot type given to scores from profiles that didn't record one, when
+/// there was only the one.
+const DEFAULT_SHOT: &str = "standard";
+
+/// Everything kept between sessions: high scores, unlocked content and
+/// options.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub version: u32,
+    /// Name recorded with new high scores.
+    pub name: String,
+    pub options: Settings,
+    pub unlocks: BTreeSet<String>,
+    pub scores: Vec<ScoreTable>,
+}
+
+pub type SharedProfile = Rc<RefCell<Profile>>;
+
+/// The best scores for one stage on one difficulty, highest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreTable {
+    pub difficulty: Difficulty,
+    pub stage: String,
+    #[serde(default)]
+    pub entries: Vec<ScoreEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: u64,
+    pub date: DateTime<Utc>,
+    /// The shot type played with.
+    pub shot: String,
+    /// Path of the replay recorded for this run, if one was kept. Nothing
+    /// records replays yet, so this is always `None` for now.
+    #[serde(default)]
+    pub replay: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// Written by a newer build than this one.
+    UnsupportedVersion(u32),
+}
+
+impl Profile {
+    pub fn new() -> Profile {
+        Profile {
+            version: CURRENT_VERSION,
+            name: "Player".to_string(),
+            options: Settings::new(),
+            unlocks: BTreeSet::new(),
+            scores: Vec::new(),
+        }
+    }
+
+    /// Reads a profile, upgrading it from older versions as needed.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Profile, ProfileError> {
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+
+        let mut table: toml::value::Table = toml::from_str(&contents)?;
+        upgrade(&mut table, MIGRATIONS)?;
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+
+    /// Loads the profile at `path`, starting a new one if it doesn't exist
+    /// or can't be read.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Profile {
+        let path = path.as_ref();
+        match Profile::load(path) {
+            Ok(profile) => profile,
+            Err(ProfileError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound => Profile::new(),
+            Err(e) => {
+                println!("Unable to load {}: {}", path.display(), e);
+                Profile::new()
+            }
+        }
+    }
+
+    /// Writes the profile without risking the old one if the game crashes
+    /// mid-write, creating its directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ProfileError> {
+        data::write_toml_atomic(path, self)
+    }
+
+    pub fn shared(self) -> SharedProfile {
+        Rc::new(RefCell::new(self))
+    }
+
+    pub fn scores(&self, difficulty: Difficulty, stage: &str) -> &[ScoreEntry] {
+        self.scores
+            .iter()
+            .find(|t| t.difficulty == difficulty && t.stage == stage)
+            .map_or(&[][..], |t| &t.entries[..])
+    }
+
+    pub fn high_score(&self, difficulty: Difficulty, stage: &str) -> u64 {
+        self.scores(difficulty, stage).first().map_or(0, |e| e.score)
+    }
+
+    /// Adds `entry` to the table for `stage` on `difficulty`. Returns its
+    /// rank if it made the table.
+    pub fn submit_score(
+        &mut self,
+        difficulty: Difficulty,
+        stage: &str,
+        entry: ScoreEntry,
+    ) -> Option<usize> {
+        let index = match self.scores.iter().position(|t| {
+            t.difficulty == difficulty && t.stage == stage
+        }) {
+            Some(index) => index,
+            None => {
+                self.scores.push(ScoreTable {
+                    difficulty,
+                    stage: stage.to_string(),
+                    entries: Vec::new(),
+                });
+                self.scores.len() - 1
+            }
+        };
+
+        let entries = &mut self.scores[index].entries;
+        let rank = entries
+            .iter()
+            .position(|e| entry.score > e.score)
+            .unwrap_or_else(|| entries.len());
+        if rank >= MAX_SCORES {
+            return None;
+        }
+        entries.insert(rank, entry);
+        entries.truncate(MAX_SCORES);
+        Some(rank)
+    }
+
+    pub fn is_unlocked(&self, name: &str) -> bool {
+        self.unlocks.contains(name)
+    }
+
+    /// Returns true if `name` wasn't already unlocked.
+    pub fn unlock(&mut self, name: &str) -> bool {
+        self.unlocks.insert(name.to_string())
+    }
+}
+
+/// Runs the steps of `migrations` a profile hasn't had yet and stamps it with
+/// the resulting version. Profiles without a version predate versioning and
+/// count as version 0.
+fn upgrade(table: &mut toml::value::Table, migrations: &[Migration]) -> Result<(), ProfileError> {
+    let version = table
+        .get("version")
+        .and_then(|v| v.as_integer())
+        .unwrap_or(0)
+        .max(0) as u32;
+    if version as usize > migrations.len() {
+        return Err(ProfileError::UnsupportedVersion(version));
+    }
+
+    for migrate in &migrations[version as usize..] {
+        migrate(table);
+    }
+    table.insert(
+        "version".to_string(),
+        toml::Value::Integer(migrations.len() as i64),
+    );
+    Ok(())
+}
+
+/// Version 0 didn't record the shot type with scores.
+fn add_shot_types(table: &mut toml::value::Table) {
+    let tables = match table.get_mut("scores") {
+        Some(&mut toml::Value::Array(ref mut tables)) => tables,
+        _ => return,
+    };
+    for score_table in tables {
+        let entries = match score_table.get_mut("entries") {
+            Some(&mut toml::Value::Array(ref mut entries)) => entries,
+            _ => continue,
+        };
+        for entry in entries {
+            if let toml::Value::Table(ref mut entry) = *entry {
+                if !entry.contains_key("shot") {
+                    entry.insert("shot".to_string(), toml::Value::from(DEFAULT_SHOT));
+                }
+            }
+        }
+    }
+}
+
+/// Name of the unlock that opens practice for `stage`.
+pub fn practice_unlock(stage: &str) -> String {
+    format!("practice/{}", stage)
+}
+
+impl Default for Profile {
+    fn default() -> Profile {
+        Profile::new()
+    }
+}
+
+impl ScoreEntry {
+    /// An entry dated now.
+    pub fn new(name: &str, score: u64, shot: &str) -> ScoreEntry {
+        ScoreEntry {
+            name: name.to_string(),
+            score,
+            date: Utc::now(),
+            shot: shot.to_string(),
+            replay: None,
+        }
+    }
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProfileError::Io(ref e) => write!(f, "unable to access profile: {}", e),
+            ProfileError::Parse(ref e) => write!(f, "invalid profile: {}", e),
+            ProfileError::Serialize(ref e) => write!(f, "unable to write profile: {}", e),
+            ProfileError::UnsupportedVersion(v) => {
+                write!(f, "profile version {} is newer than this build", v)
+            }
+        }
+    }
+}
+
+impl error::Error for ProfileError {
+    fn description(&self) -> &str {
+        match *self {
+            ProfileError::Io(_) => "unable to access profile",
+            ProfileError::Parse(_) => "invalid profile",
+            ProfileError::Serialize(_) => "unable to write profile",
+            ProfileError::UnsupportedVersion(_) => "unsupported profile version",
+        }
+    }
+}
+
+impl From<io::Error> for ProfileError {
+    fn from(e: io::Error) -> ProfileError {
+        ProfileError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ProfileError {
+    fn from(e: toml::de::Error) -> ProfileError {
+        ProfileError::Parse(e)
+    }
+}
+
+impl From<toml::ser::Error> for ProfileError {
+    fn from(e: toml::ser::Error) -> ProfileError {
+        ProfileError::Serialize(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use toml;
+
+    use super::{upgrade, Migration, Profile, ProfileError, CURRENT_VERSION, MIGRATIONS};
+    use difficulty::Difficulty;
+
+    fn parse(source: &str) -> toml::value::Table {
+        toml::from_str(source).unwrap()
+    }
+
+    fn mark_a(table: &mut toml::value::Table) {
+        table.insert("a".to_string(), toml::Value::from(true));
+    }
+
+    fn mark_b(table: &mut toml::value::Table) {
+        table.insert("b".to_string(), toml::Value::from(true));
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[mark_a, mark_b];
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len() as u32, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn upgrade_runs_only_missing_steps() {
+        let mut table = parse("version = 1");
+        upgrade(&mut table, TEST_MIGRATIONS).unwrap();
+        assert!(!table.contains_key("a"));
+        assert!(table.contains_key("b"));
+        assert_eq!(table["version"].as_integer(), Some(2));
+
+        let mut table = parse("name = \"old\"");
+        upgrade(&mut table, TEST_MIGRATIONS).unwrap();
+        assert!(table.contains_key("a"));
+        assert!(table.contains_key("b"));
+    }
+
+    #[test]
+    fn upgrade_rejects_newer_versions() {
+        let mut table = parse("version = 3");
+        match upgrade(&mut table, TEST_MIGRATIONS) {
+            Err(ProfileError::UnsupportedVersion(3)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn version_0_scores_get_a_shot_type() {
+        let mut table = parse(
+            r#"
+            name = "Player"
+
+            [[scores]]
+            difficulty = "normal"
+            stage = "Stage 1"
+
+            [[scores.entries]]
+            name = "Player"
+            score = 1200
+            date = "2017-06-01T12:00:00Z"
+            "#,
+        );
+        upgrade(&mut table, MIGRATIONS).unwrap();
+        let profile: Profile = toml::Value::Table(table).try_into().unwrap();
+
+        assert_eq!(profile.version, CURRENT_VERSION);
+        let entries = profile.scores(Difficulty::Normal, "Stage 1");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].shot, "standard");
+        assert_eq!(entries[0].replay, None);
+    }
+}
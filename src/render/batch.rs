@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use graphics::{self, Graphics};
+use graphics::triangulation::{tx, ty};
+use graphics::draw_state::Blend;
+use opengl_graphics::GlGraphics;
+
+/// Largest multiple of three that fits in one back-end vertex buffer, so a
+/// chunk never splits a triangle.
+const CHUNK_VERTICES: usize = graphics::BACK_END_MAX_VERTEX_COUNT / 3 * 3;
+
+/// Key for a batch: exact RGBA color, by bit pattern, plus whether it
+/// blends additively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BatchKey {
+    color: [u32; 4],
+    additive: bool,
+}
+
+/// Collects solid colored quads and submits them as a handful of large
+/// triangle lists, one per distinct color and blend mode.
+///
+/// Batches are drawn in the order their key was first used in the frame,
+/// so things queued first still end up underneath.
+#[derive(Default)]
+pub struct QuadBatch {
+    batches: Vec<(BatchKey, Vec<[f32; 2]>)>,
+    /// Position of each key's batch in `batches`.
+    index: HashMap<BatchKey, usize>,
+    /// Emptied vertex buffers, reused by the next frame's batches.
+    spare: Vec<Vec<[f32; 2]>>,
+}
+
+impl QuadBatch {
+    pub fn new() -> QuadBatch {
+        QuadBatch {
+            batches: Vec::new(),
+            index: HashMap::new(),
+            spare: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.iter().all(|&(_, ref v)| v.is_empty())
+    }
+
+    /// Queues the rectangle `[x, y, w, h]`, transformed by `transform`.
+    pub fn push_rect(
+        &mut self,
+        rect: [f64; 4],
+        color: [f32; 4],
+        additive: bool,
+        transform: graphics::math::Matrix2d,
+    ) {
+        let key = BatchKey {
+            color: [
+                color[0].to_bits(),
+                color[1].to_bits(),
+                color[2].to_bits(),
+                color[3].to_bits(),
+            ],
+            additive,
+        };
+        let (x0, y0, x1, y1) = (rect[0], rect[1], rect[0] + rect[2], rect[1] + rect[3]);
+        let corners = [
+            [tx(transform, x0, y0), ty(transform, x0, y0)],
+            [tx(transform, x1, y0), ty(transform, x1, y0)],
+            [tx(transform, x1, y1), ty(transform, x1, y1)],
+            [tx(transform, x0, y1), ty(transform, x0, y1)],
+        ];
+
+        let batches = &mut self.batches;
+        let spare = &mut self.spare;
+        let i = *self.index.entry(key).or_insert_with(|| {
+            batches.push((key, spare.pop().unwrap_or_else(Vec::new)));
+            batches.len() - 1
+        });
+        let vertices = &mut batches[i].1;
+        vertices.extend_from_slice(&[corners[0], corners[1], corners[2]]);
+        vertices.extend_from_slice(&[corners[0], corners[2], corners[3]]);
+    }
+
+    /// Draws everything queued and empties the batch, keeping its buffers
+    /// for the next frame.
+    pub fn flush(&mut self, draw_state: &graphics::DrawState, gl: &mut GlGraphics) {
+        for (key, mut vertices) in self.batches.drain(..) {
+            let state = if key.additive {
+                draw_state.blend(Blend::Add)
+            } else {
+                *draw_state
+            };
+
+            let color = [
+                f32::from_bits(key.color[0]),
+                f32::from_bits(key.color[1]),
+                f32::from_bits(key.color[2]),
+                f32::from_bits(key.color[3]),
+            ];
+            gl.tri_list(&state, &color, |f| {
+                for chunk in vertices.chunks(CHUNK_VERTICES) {
+                    f(chunk);
+                }
+            });
+            vertices.clear();
+            self.spare.push(vertices);
+        }
+        self.index.clear();
+    }
+}
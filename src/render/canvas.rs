@@ -0,0 +1,43 @@
+use graphics;
+use opengl_graphics::GlGraphics;
+
+use render::QuadBatch;
+
+/// Destination for the shapes produced by scene traversal. Lets the same
+/// traversal feed either OpenGL or an in-memory image.
+pub trait Canvas {
+    /// Fills the rectangle `[x, y, w, h]`, given in playfield coordinates.
+    fn fill_rect(&mut self, rect: [f64; 4], color: [f32; 4], additive: bool);
+
+    /// Called between layers. Anything queued must be drawn before shapes
+    /// from the next layer.
+    fn flush(&mut self) {}
+}
+
+/// Draws through a `QuadBatch` into the current OpenGL context.
+pub struct GlCanvas<'a> {
+    batch: &'a mut QuadBatch,
+    ctx: &'a graphics::Context,
+    gl: &'a mut GlGraphics,
+}
+
+impl<'a> GlCanvas<'a> {
+    pub fn new(
+        batch: &'a mut QuadBatch,
+        ctx: &'a graphics::Context,
+        gl: &'a mut GlGraphics,
+    ) -> GlCanvas<'a> {
+        GlCanvas { batch, ctx, gl }
+    }
+}
+
+impl<'a> Canvas for GlCanvas<'a> {
+    fn fill_rect(&mut self, rect: [f64; 4], color: [f32; 4], additive: bool) {
+        self.batch
+            .push_rect(rect, color, additive, self.ctx.transform);
+    }
+
+    fn flush(&mut self) {
+        self.batch.flush(&self.ctx.draw_state, self.gl);
+    }
+}
@@ -0,0 +1,130 @@
+use std::env;
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use image::{self, RgbaImage};
+
+/// When set, `check_golden` writes missing or mismatched golden images
+/// instead of failing.
+pub const UPDATE_GOLDEN_VAR: &str = "UPDATE_GOLDEN";
+
+/// Why a rendered frame didn't match its golden image.
+#[derive(Debug)]
+pub enum GoldenError {
+    Io(io::Error),
+    Image(image::ImageError),
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// `differing` pixels were off by more than the tolerance. The rendered
+    /// frame was written to `actual_path` for inspection.
+    Mismatch {
+        differing: usize,
+        actual_path: PathBuf,
+    },
+}
+
+/// Counts pixels where any channel differs by more than `tolerance`.
+/// Returns `None` if the images are different sizes.
+pub fn diff_pixels(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> Option<usize> {
+    if actual.dimensions() != expected.dimensions() {
+        return None;
+    }
+
+    let differing = actual
+        .pixels()
+        .zip(expected.pixels())
+        .filter(|&(a, e)| {
+            a.data
+                .iter()
+                .zip(e.data.iter())
+                .any(|(&a, &e)| (a as i16 - e as i16).abs() > tolerance as i16)
+        })
+        .count();
+    Some(differing)
+}
+
+/// Compares `actual` against the PNG at `path`.
+///
+/// If `UPDATE_GOLDEN` is set in the environment, the golden is (re)written
+/// from `actual` and the check passes.
+pub fn check_golden<P: AsRef<Path>>(
+    actual: &RgbaImage,
+    path: P,
+    tolerance: u8,
+) -> Result<(), GoldenError> {
+    let path = path.as_ref();
+    if env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+        actual.save(path)?;
+        return Ok(());
+    }
+
+    let expected = image::open(path)?.to_rgba();
+    match diff_pixels(actual, &expected, tolerance) {
+        None => Err(GoldenError::SizeMismatch {
+            expected: expected.dimensions(),
+            actual: actual.dimensions(),
+        }),
+        Some(0) => Ok(()),
+        Some(differing) => {
+            let actual_path = path.with_extension("actual.png");
+            actual.save(&actual_path)?;
+            Err(GoldenError::Mismatch {
+                differing,
+                actual_path,
+            })
+        }
+    }
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GoldenError::Io(ref e) => write!(f, "unable to access golden image: {}", e),
+            GoldenError::Image(ref e) => write!(f, "unable to read golden image: {}", e),
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "expected a {}x{} frame, rendered {}x{}",
+                expected.0,
+                expected.1,
+                actual.0,
+                actual.1
+            ),
+            GoldenError::Mismatch {
+                differing,
+                ref actual_path,
+            } => write!(
+                f,
+                "{} pixels differ from the golden image, see {}",
+                differing,
+                actual_path.display()
+            ),
+        }
+    }
+}
+
+impl error::Error for GoldenError {
+    fn description(&self) -> &str {
+        match *self {
+            GoldenError::Io(_) => "unable to access golden image",
+            GoldenError::Image(_) => "unable to read golden image",
+            GoldenError::SizeMismatch { .. } => "frame size differs from golden image",
+            GoldenError::Mismatch { .. } => "frame differs from golden image",
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(e: io::Error) -> GoldenError {
+        GoldenError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for GoldenError {
+    fn from(e: image::ImageError) -> GoldenError {
+        GoldenError::Image(e)
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::slice;
+
+use specs::{self, Join, RunNow};
+
+use graphics::{self, Graphics, Transformed};
+use opengl_graphics::{GlGraphics, GlyphCache};
+
+use image::RgbaImage;
+
+use world::{self, World};
+use background::Background;
+use ecs::component;
+use ecs::component::render::{RenderSystem, RendererRegistry};
+use cgmath;
+
+pub mod batch;
+pub mod canvas;
+pub mod golden;
+pub mod post;
+pub mod software;
+
+pub use self::batch::QuadBatch;
+pub use self::canvas::{Canvas, GlCanvas};
+pub use self::software::SoftwareCanvas;
+
+const BANNER_FONT_SIZE: u32 = 32;
+const BOSS_FONT_SIZE: u32 = 16;
+const BOSS_BAR_MARGIN: f64 = 12.0;
+const BOSS_BAR_HEIGHT: f64 = 8.0;
+const HITBOX_COLOR: [f32; 4] = [0.2, 1.0, 0.2, 1.0];
+
+/// How a graphic's color combines with what's already been drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    Alpha,
+    /// Adds to the destination, for glowing bullets and sparks.
+    Additive,
+}
+
+/// Entities drawn on one layer always appear above those on earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Ships,
+    Bullets,
+}
+
+/// One entry in the draw list: an entity's graphic at its current position.
+#[derive(Clone)]
+pub struct RenderGraphic {
+    pub entity: specs::Entity,
+    pub pos: cgmath::Vector2<f64>,
+    pub layer: Layer,
+    pub blend: BlendMode,
+    /// How far to tint toward white, from 0 to 1.
+    pub flash: f32,
+    pub state: RenderGraphicState,
+}
+
+/// Everything to draw this frame, ordered by layer. Filled by
+/// `RenderSystem` and consumed by `draw_scene`.
+#[derive(Clone, Default)]
+pub struct DrawList {
+    items: Vec<RenderGraphic>,
+}
+
+#[derive(Clone)]
+pub struct RectangleGraphic {
+    pub rect: graphics::Rectangle,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A graphic drawn by a renderer registered under `kind`, for visual types
+/// defined outside this module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomGraphic {
+    pub kind: String,
+    pub width: f64,
+    pub height: f64,
+    pub color: [f32; 4],
+    /// Extra values for the renderer, such as a spin speed or a number of
+    /// points, which differ between graphics of the same kind.
+    pub params: BTreeMap<String, f64>,
+}
+
+#[derive(Clone)]
+pub enum RenderGraphicState {
+    Rectangle(RectangleGraphic),
+    Custom(CustomGraphic),
+}
+
+impl RenderGraphicState {
+    /// Width and height of the graphic's bounding box.
+    pub fn size(&self) -> (f64, f64) {
+        match *self {
+            RenderGraphicState::Rectangle(ref r) => (r.width, r.height),
+            RenderGraphicState::Custom(ref c) => (c.width, c.height),
+        }
+    }
+
+    /// Name of the renderer that draws this graphic.
+    pub fn kind(&self) -> &str {
+        match *self {
+            RenderGraphicState::Rectangle(_) => "rectangle",
+            RenderGraphicState::Custom(ref c) => &c.kind,
+        }
+    }
+}
+
+impl CustomGraphic {
+    /// The named parameter, or `default` if this graphic doesn't set it.
+    pub fn param(&self, name: &str, default: f64) -> f64 {
+        self.params.get(name).cloned().unwrap_or(default)
+    }
+}
+
+impl DrawList {
+    pub fn new() -> DrawList {
+        DrawList { items: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+    pub fn push(&mut self, graphic: RenderGraphic) {
+        self.items.push(graphic);
+    }
+    /// Orders the list by layer, keeping the insertion order within a layer.
+    pub fn sort(&mut self) {
+        self.items.sort_by_key(|g| g.layer);
+    }
+
+    pub fn iter(&self) -> slice::Iter<RenderGraphic> {
+        self.items.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Adds the resources rendering reads from the world.
+pub fn register_resources(world: &mut specs::World) {
+    world.add_resource(DrawList::new());
+    world.add_resource(RendererRegistry::new());
+    world.add_resource(Background::default());
+}
+
+/// Rebuilds the world's draw list from its entities.
+pub fn gather(world: &World) {
+    RenderSystem.run_now(&world.get_specs().res);
+}
+
+/// Draws the background, then the world's draw list, followed by the boss
+/// bar.
+///
+/// Entities are queued into `batch` rather than drawn one at a time, so the
+/// cost is a draw call per distinct color instead of per entity.
+pub fn render(
+    world: &mut World,
+    batch: &mut QuadBatch,
+    ctx: &graphics::Context,
+    gl: &mut GlGraphics,
+    glyphs: &mut GlyphCache,
+) {
+    let specs = world.get_specs();
+    let background = specs.read_resource::<Background>();
+    gl.clear_color(background.color());
+
+    {
+        let mut canvas = GlCanvas::new(batch, ctx, gl);
+        background.draw(&mut canvas);
+        draw_scene(
+            &specs.read_resource::<DrawList>(),
+            &mut specs.write_resource::<RendererRegistry>(),
+            &mut canvas,
+        );
+    }
+
+    if let Some(ref boss) = specs.read_resource::<component::boss::BossStatus>().display {
+        render_boss_bar(boss, ctx, gl, glyphs);
+    }
+}
+
+/// Gathers and draws the entities in `world` into a `width` by `height`
+/// image without touching OpenGL. Text overlays such as the boss bar are
+/// left out, since they depend on the font rasterizer.
+pub fn render_offscreen(world: &World, width: u32, height: u32) -> RgbaImage {
+    gather(world);
+
+    let specs = world.get_specs();
+    let background = specs.read_resource::<Background>();
+    let mut canvas = SoftwareCanvas::new(width, height);
+    canvas.clear(background.color());
+    background.draw(&mut canvas);
+    draw_scene(
+        &specs.read_resource::<DrawList>(),
+        &mut specs.write_resource::<RendererRegistry>(),
+        &mut canvas,
+    );
+    canvas.into_image()
+}
+
+/// Hands each graphic in `list` to the renderer registered for its kind,
+/// flushing `canvas` between layers. Graphics with no renderer are skipped,
+/// and the first of each kind is logged.
+pub fn draw_scene(list: &DrawList, renderers: &mut RendererRegistry, canvas: &mut Canvas) {
+    let mut layer = None;
+    for graphic in list.iter() {
+        if layer.map_or(false, |l| l != graphic.layer) {
+            canvas.flush();
+        }
+        layer = Some(graphic.layer);
+
+        match renderers.get(graphic.state.kind()) {
+            Some(renderer) => renderer.render(graphic, canvas),
+            None => renderers.report_missing(graphic.state.kind()),
+        }
+    }
+    canvas.flush();
+}
+
+/// Outlines the hitbox of every entity in `world`, for debugging.
+pub fn render_hitboxes(world: &World, ctx: &graphics::Context, gl: &mut GlGraphics) {
+    let specs = world.get_specs();
+    let outline = graphics::Ellipse::new_border(HITBOX_COLOR, 1.0);
+    for (&component::pos::Position(pos), hitbox) in (
+        &specs.read::<component::pos::Position>(),
+        &specs.read::<component::collision::Hitbox>(),
+    ).join()
+    {
+        let r = hitbox.radius;
+        outline.draw(
+            [pos.x - r, pos.y - r, r * 2.0, r * 2.0],
+            &ctx.draw_state,
+            ctx.transform,
+            gl,
+        );
+    }
+}
+
+/// Draws `text` across the middle of the playfield on a translucent strip.
+pub fn render_banner(
+    text: &str,
+    ctx: &graphics::Context,
+    gl: &mut GlGraphics,
+    glyphs: &mut GlyphCache,
+) {
+    let strip_height = BANNER_FONT_SIZE as f64 * 2.0;
+    let strip_y = (world::PLAYFIELD_HEIGHT - strip_height) / 2.0;
+
+    graphics::Rectangle::new([0.0, 0.0, 0.0, 0.6]).draw(
+        [0.0, strip_y, world::PLAYFIELD_WIDTH, strip_height],
+        &ctx.draw_state,
+        ctx.transform,
+        gl,
+    );
+
+    let text_x = world::PLAYFIELD_WIDTH / 2.0 -
+        text.len() as f64 * BANNER_FONT_SIZE as f64 * 0.3;
+    let text_y = strip_y + strip_height / 2.0 + BANNER_FONT_SIZE as f64 / 3.0;
+
+    graphics::Text::new_color([1.0, 1.0, 1.0, 1.0], BANNER_FONT_SIZE).draw(
+        text,
+        glyphs,
+        &ctx.draw_state,
+        ctx.transform.trans(text_x, text_y),
+        gl,
+    );
+}
+
+/// Draws the boss health bar along the top of the playfield with the phase
+/// name beneath it.
+fn render_boss_bar(
+    boss: &component::boss::BossDisplay,
+    ctx: &graphics::Context,
+    gl: &mut GlGraphics,
+    glyphs: &mut GlyphCache,
+) {
+    let bar_width = world::PLAYFIELD_WIDTH - BOSS_BAR_MARGIN * 2.0;
+
+    graphics::Rectangle::new([0.2, 0.2, 0.2, 0.8]).draw(
+        [BOSS_BAR_MARGIN, BOSS_BAR_MARGIN, bar_width, BOSS_BAR_HEIGHT],
+        &ctx.draw_state,
+        ctx.transform,
+        gl,
+    );
+    graphics::Rectangle::new([0.9, 0.1, 0.1, 1.0]).draw(
+        [
+            BOSS_BAR_MARGIN,
+            BOSS_BAR_MARGIN,
+            bar_width * boss.health_fraction,
+            BOSS_BAR_HEIGHT,
+        ],
+        &ctx.draw_state,
+        ctx.transform,
+        gl,
+    );
+
+    let text_y = BOSS_BAR_MARGIN * 2.0 + BOSS_BAR_HEIGHT + BOSS_FONT_SIZE as f64;
+    let label = format!("{}  x{}  {}", boss.name, boss.phases_remaining, boss.phase_name);
+
+    render_text(
+        &label,
+        [BOSS_BAR_MARGIN, text_y],
+        BOSS_FONT_SIZE,
+        [0.1, 0.1, 0.1, 1.0],
+        ctx,
+        gl,
+        glyphs,
+    );
+}
+
+/// Draws a line of text with its baseline starting at `pos`.
+pub fn render_text(
+    text: &str,
+    pos: [f64; 2],
+    size: u32,
+    color: [f32; 4],
+    ctx: &graphics::Context,
+    gl: &mut GlGraphics,
+    glyphs: &mut GlyphCache,
+) {
+    graphics::Text::new_color(color, size).draw(
+        text,
+        glyphs,
+        &ctx.draw_state,
+        ctx.transform.trans(pos[0], pos[1]),
+        gl,
+    );
+}
+
+/// Darkens everything drawn so far, for screens shown over the game.
+pub fn render_dim(alpha: f32, ctx: &graphics::Context, gl: &mut GlGraphics) {
+    graphics::Rectangle::new([0.0, 0.0, 0.0, alpha]).draw(
+        [0.0, 0.0, world::PLAYFIELD_WIDTH, world::PLAYFIELD_HEIGHT],
+        &ctx.draw_state,
+        ctx.transform,
+        gl,
+    );
+}
@@ -0,0 +1,172 @@
+use gl;
+use opengl_graphics::GlGraphics;
+
+pub mod shader;
+pub mod target;
+
+pub use self::shader::{Shader, ShaderError};
+pub use self::target::{FullscreenQuad, RenderTarget};
+
+const FULLSCREEN_VERTEX: &str = include_str!("../../../assets/shaders/fullscreen.vert");
+const BLOOM_FRAGMENT: &str = include_str!("../../../assets/shaders/bloom.frag");
+const CHROMATIC_FRAGMENT: &str = include_str!("../../../assets/shaders/chromatic.frag");
+
+/// Per-frame strength of each full-screen effect, supplied by the active
+/// game state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenEffects {
+    pub bloom: f32,
+    /// Pixels red and blue are pulled apart at the screen edges, e.g. while
+    /// a bomb is active.
+    pub chromatic_shift: f32,
+}
+
+/// One stage of the post-process chain.
+pub trait PostEffect {
+    fn fragment_source(&self) -> &str;
+    fn is_enabled(&self, effects: &ScreenEffects) -> bool;
+    /// Sets the effect's uniforms on its bound shader.
+    fn set_uniforms(&self, shader: &Shader, effects: &ScreenEffects);
+}
+
+/// Glow around bright pixels, so additive bullets bleed light.
+#[derive(Debug, Clone)]
+pub struct Bloom {
+    pub threshold: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChromaticShift;
+
+/// Renders the scene into an offscreen target and runs it through a chain
+/// of full-screen shaders before it reaches the window.
+pub struct PostProcess {
+    effects: Vec<(Box<PostEffect>, Shader)>,
+    targets: [RenderTarget; 2],
+    quad: FullscreenQuad,
+}
+
+impl ScreenEffects {
+    pub fn new() -> ScreenEffects {
+        ScreenEffects {
+            bloom: 0.0,
+            chromatic_shift: 0.0,
+        }
+    }
+}
+
+impl Default for ScreenEffects {
+    fn default() -> ScreenEffects {
+        ScreenEffects::new()
+    }
+}
+
+impl PostEffect for Bloom {
+    fn fragment_source(&self) -> &str {
+        BLOOM_FRAGMENT
+    }
+    fn is_enabled(&self, effects: &ScreenEffects) -> bool {
+        effects.bloom > 0.0
+    }
+    fn set_uniforms(&self, shader: &Shader, effects: &ScreenEffects) {
+        shader.set_float("threshold", self.threshold);
+        shader.set_float("intensity", effects.bloom);
+    }
+}
+
+impl PostEffect for ChromaticShift {
+    fn fragment_source(&self) -> &str {
+        CHROMATIC_FRAGMENT
+    }
+    fn is_enabled(&self, effects: &ScreenEffects) -> bool {
+        effects.chromatic_shift > 0.0
+    }
+    fn set_uniforms(&self, shader: &Shader, effects: &ScreenEffects) {
+        shader.set_float("amount", effects.chromatic_shift);
+    }
+}
+
+impl PostProcess {
+    /// Creates the chain with bloom followed by chromatic shift.
+    pub fn new(width: u32, height: u32) -> Result<PostProcess, ShaderError> {
+        let mut post = PostProcess {
+            effects: Vec::new(),
+            targets: [
+                RenderTarget::new(width, height),
+                RenderTarget::new(width, height),
+            ],
+            quad: FullscreenQuad::new(),
+        };
+        post.add_effect(Bloom { threshold: 0.7 })?;
+        post.add_effect(ChromaticShift)?;
+        Ok(post)
+    }
+
+    /// Appends `effect` to the end of the chain.
+    pub fn add_effect<E: PostEffect + 'static>(&mut self, effect: E) -> Result<(), ShaderError> {
+        let shader = Shader::new(FULLSCREEN_VERTEX, effect.fragment_source())?;
+        self.effects.push((Box::new(effect), shader));
+        Ok(())
+    }
+
+    /// Redirects drawing into the offscreen target. Call before drawing
+    /// the frame, with the window's framebuffer size.
+    pub fn begin(&mut self, width: u32, height: u32) {
+        if self.targets[0].size() != (width, height) {
+            self.targets = [
+                RenderTarget::new(width, height),
+                RenderTarget::new(width, height),
+            ];
+        }
+        self.targets[0].bind();
+    }
+
+    /// Runs the enabled effects and writes the result to the window.
+    pub fn finish(&mut self, effects: &ScreenEffects, gl_ctx: &mut GlGraphics) {
+        let (width, height) = self.targets[0].size();
+        let texel = [1.0 / width as f32, 1.0 / height as f32];
+
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::SCISSOR_TEST);
+        }
+
+        let enabled: Vec<_> = self.effects
+            .iter()
+            .filter(|&&(ref e, _)| e.is_enabled(effects))
+            .collect();
+
+        let mut source = 0;
+        for (i, &&(ref effect, ref shader)) in enabled.iter().enumerate() {
+            if i + 1 == enabled.len() {
+                unsafe {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    gl::Viewport(0, 0, width as i32, height as i32);
+                }
+            } else {
+                self.targets[1 - source].bind();
+            }
+
+            self.targets[source].bind_texture();
+            shader.bind();
+            shader.set_int("source", 0);
+            shader.set_vec2("texel", texel);
+            effect.set_uniforms(shader, effects);
+            self.quad.draw();
+
+            source = 1 - source;
+        }
+
+        if enabled.is_empty() {
+            self.targets[0].blit_to_screen();
+        }
+
+        unsafe {
+            gl::UseProgram(0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        // The GL state no longer matches what the piston back-end cached.
+        gl_ctx.clear_program();
+        gl_ctx.clear_draw_state();
+    }
+}
@@ -0,0 +1,148 @@
+use std::error;
+use std::ffi::{CString, NulError};
+use std::fmt;
+use std::ptr;
+
+use gl;
+use gl::types::{GLchar, GLenum, GLint, GLuint};
+
+/// Name of the vertex position attribute, bound to location 0.
+pub const POSITION_ATTRIBUTE: &str = "pos";
+
+/// Failure to build a GLSL program, with the driver's log.
+#[derive(Debug, Clone)]
+pub enum ShaderError {
+    Compile(String),
+    Link(String),
+    /// Source or a name passed to GL contained a nul byte.
+    Nul(NulError),
+}
+
+/// A linked vertex and fragment shader pair.
+pub struct Shader {
+    program: GLuint,
+}
+
+impl Shader {
+    pub fn new(vertex: &str, fragment: &str) -> Result<Shader, ShaderError> {
+        let attribute = CString::new(POSITION_ATTRIBUTE)?;
+        unsafe {
+            let vs = compile(gl::VERTEX_SHADER, vertex)?;
+            let fs = match compile(gl::FRAGMENT_SHADER, fragment) {
+                Ok(fs) => fs,
+                Err(e) => {
+                    gl::DeleteShader(vs);
+                    return Err(e);
+                }
+            };
+
+            let program = gl::CreateProgram();
+            gl::AttachShader(program, vs);
+            gl::AttachShader(program, fs);
+            gl::BindAttribLocation(program, 0, attribute.as_ptr());
+            gl::LinkProgram(program);
+
+            gl::DetachShader(program, vs);
+            gl::DetachShader(program, fs);
+            gl::DeleteShader(vs);
+            gl::DeleteShader(fs);
+
+            let mut status = gl::FALSE as GLint;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+            if status != gl::TRUE as GLint {
+                let log = info_log(program, gl::GetProgramiv, gl::GetProgramInfoLog);
+                gl::DeleteProgram(program);
+                return Err(ShaderError::Link(log));
+            }
+
+            Ok(Shader { program })
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe { gl::UseProgram(self.program) };
+    }
+
+    /// Sets a uniform on this shader, which must be bound. Unknown names
+    /// are ignored, since drivers strip uniforms a shader doesn't use.
+    pub fn set_float(&self, name: &str, value: f32) {
+        unsafe { gl::Uniform1f(self.location(name), value) };
+    }
+    pub fn set_vec2(&self, name: &str, value: [f32; 2]) {
+        unsafe { gl::Uniform2f(self.location(name), value[0], value[1]) };
+    }
+    pub fn set_int(&self, name: &str, value: i32) {
+        unsafe { gl::Uniform1i(self.location(name), value) };
+    }
+
+    /// The uniform's location, or -1, which GL ignores, if `name` can't be
+    /// passed to GL.
+    fn location(&self, name: &str) -> GLint {
+        match CString::new(name) {
+            Ok(name) => unsafe { gl::GetUniformLocation(self.program, name.as_ptr()) },
+            Err(_) => -1,
+        }
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteProgram(self.program) };
+    }
+}
+
+unsafe fn compile(kind: GLenum, source: &str) -> Result<GLuint, ShaderError> {
+    let source = CString::new(source)?;
+    let shader = gl::CreateShader(kind);
+    gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+    gl::CompileShader(shader);
+
+    let mut status = gl::FALSE as GLint;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+    if status != gl::TRUE as GLint {
+        let log = info_log(shader, gl::GetShaderiv, gl::GetShaderInfoLog);
+        gl::DeleteShader(shader);
+        return Err(ShaderError::Compile(log));
+    }
+    Ok(shader)
+}
+
+unsafe fn info_log(
+    object: GLuint,
+    get_iv: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, i32, *mut i32, *mut GLchar),
+) -> String {
+    let mut len = 0;
+    get_iv(object, gl::INFO_LOG_LENGTH, &mut len);
+    let mut buf = vec![0u8; len.max(1) as usize];
+    get_log(object, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
+    String::from_utf8_lossy(&buf)
+        .trim_right_matches('\0')
+        .to_string()
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShaderError::Compile(ref log) => write!(f, "unable to compile shader: {}", log),
+            ShaderError::Link(ref log) => write!(f, "unable to link shader: {}", log),
+            ShaderError::Nul(ref e) => write!(f, "invalid shader text: {}", e),
+        }
+    }
+}
+
+impl error::Error for ShaderError {
+    fn description(&self) -> &str {
+        match *self {
+            ShaderError::Compile(_) => "unable to compile shader",
+            ShaderError::Link(_) => "unable to link shader",
+            ShaderError::Nul(_) => "invalid shader text",
+        }
+    }
+}
+
+impl From<NulError> for ShaderError {
+    fn from(e: NulError) -> ShaderError {
+        ShaderError::Nul(e)
+    }
+}
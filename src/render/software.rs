@@ -0,0 +1,88 @@
+use image::{Rgba, RgbaImage};
+
+use render::canvas::Canvas;
+use world;
+
+/// CPU rasterizer for the shapes in a scene, used where there is no GPU or
+/// display. Coordinates are scaled so the playfield fills the image.
+pub struct SoftwareCanvas {
+    image: RgbaImage,
+    scale: [f64; 2],
+}
+
+impl SoftwareCanvas {
+    pub fn new(width: u32, height: u32) -> SoftwareCanvas {
+        SoftwareCanvas {
+            image: RgbaImage::new(width, height),
+            scale: [
+                width as f64 / world::PLAYFIELD_WIDTH,
+                height as f64 / world::PLAYFIELD_HEIGHT,
+            ],
+        }
+    }
+
+    pub fn clear(&mut self, color: [f32; 4]) {
+        let pixel = to_pixel(color);
+        for p in self.image.pixels_mut() {
+            *p = pixel;
+        }
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+    pub fn into_image(self) -> RgbaImage {
+        self.image
+    }
+}
+
+impl Canvas for SoftwareCanvas {
+    fn fill_rect(&mut self, rect: [f64; 4], color: [f32; 4], additive: bool) {
+        let (width, height) = self.image.dimensions();
+        let clamp = |v: f64, max: u32| v.round().max(0.0).min(max as f64) as u32;
+
+        let x0 = clamp(rect[0] * self.scale[0], width);
+        let y0 = clamp(rect[1] * self.scale[1], height);
+        let x1 = clamp((rect[0] + rect[2]) * self.scale[0], width);
+        let y1 = clamp((rect[1] + rect[3]) * self.scale[1], height);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let dst = self.image.get_pixel_mut(x, y);
+                *dst = blend(*dst, color, additive);
+            }
+        }
+    }
+}
+
+fn to_pixel(color: [f32; 4]) -> Rgba<u8> {
+    let channel = |c: f32| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+    Rgba([
+        channel(color[0]),
+        channel(color[1]),
+        channel(color[2]),
+        channel(color[3]),
+    ])
+}
+
+/// Same blend equations as the OpenGL path: source-over alpha, or source
+/// scaled by alpha and added to the destination.
+fn blend(dst: Rgba<u8>, src: [f32; 4], additive: bool) -> Rgba<u8> {
+    let a = src[3].max(0.0).min(1.0);
+    let mut out = [0.0; 4];
+    for i in 0..3 {
+        let d = dst.data[i] as f32 / 255.0;
+        out[i] = if additive {
+            d + src[i] * a
+        } else {
+            src[i] * a + d * (1.0 - a)
+        };
+    }
+    let dst_alpha = dst.data[3] as f32 / 255.0;
+    out[3] = if additive {
+        dst_alpha
+    } else {
+        a + dst_alpha * (1.0 - a)
+    };
+    to_pixel(out)
+}
@@ -0,0 +1,46 @@
+/// Small xorshift generator for gameplay randomness. Deterministic for a
+/// given seed, which keeps scripts and effects reproducible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: if seed == 0 { DEFAULT_SEED } else { seed } }
+    }
+
+    /// Resumes a generator from a value returned by `state`.
+    pub fn from_state(state: u64) -> Rng {
+        Rng::new(state)
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Uniform value in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform value in `[min, max)`.
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Rng {
+        Rng::new(DEFAULT_SEED)
+    }
+}
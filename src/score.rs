@@ -0,0 +1,133 @@
+use specs;
+use game_time::GameTime;
+
+use ecs::event::{BossPhaseEnded, EntityDied, EventChannel, ReaderId};
+
+pub const KILL_POINTS: u64 = 100;
+pub const GRAZE_POINTS: u64 = 10;
+pub const POINT_ITEM_POINTS: u64 = 500;
+
+/// Seconds after the last kill or graze before the combo starts decaying.
+const COMBO_WINDOW: f64 = 2.0;
+/// Seconds between each step of combo decay once the window has passed.
+const COMBO_DECAY_INTERVAL: f64 = 0.25;
+const COMBO_STEP: f64 = 0.1;
+const MAX_MULTIPLIER: f64 = 5.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Score {
+    pub points: u64,
+    pub graze: u64,
+    pub point_items: u64,
+    combo: u32,
+    combo_timer: f64,
+}
+
+/// Applies the scoring rules for kills, boss bonuses and combo decay.
+#[derive(Debug, Clone, Default)]
+pub struct ScoreSystem {
+    deaths: Option<ReaderId>,
+    phase_ends: Option<ReaderId>,
+}
+
+#[derive(SystemData)]
+pub struct ScoreSystemData<'a> {
+    score: specs::FetchMut<'a, Score>,
+    deaths: specs::Fetch<'a, EventChannel<EntityDied>>,
+    phase_ends: specs::Fetch<'a, EventChannel<BossPhaseEnded>>,
+    time: specs::Fetch<'a, GameTime>,
+}
+
+impl Score {
+    pub fn new() -> Score {
+        Score {
+            points: 0,
+            graze: 0,
+            point_items: 0,
+            combo: 0,
+            combo_timer: 0.0,
+        }
+    }
+
+    pub fn combo(&self) -> u32 {
+        self.combo
+    }
+
+    pub fn multiplier(&self) -> f64 {
+        (1.0 + self.combo as f64 * COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+
+    /// Adds `base` points scaled by the combo multiplier.
+    pub fn award(&mut self, base: u64) {
+        self.points += (base as f64 * self.multiplier()).round() as u64;
+    }
+
+    fn extend_combo(&mut self) {
+        self.combo += 1;
+        self.combo_timer = COMBO_WINDOW;
+    }
+
+    pub fn on_kill(&mut self) {
+        self.award(KILL_POINTS);
+        self.extend_combo();
+    }
+
+    pub fn on_graze(&mut self) {
+        self.graze += 1;
+        self.award(GRAZE_POINTS);
+        self.extend_combo();
+    }
+
+    pub fn on_point_item(&mut self) {
+        self.point_items += 1;
+        self.award(POINT_ITEM_POINTS);
+    }
+
+    /// Bonuses are awarded flat, without the combo multiplier.
+    pub fn on_bonus(&mut self, bonus: u64) {
+        self.points += bonus;
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.combo_timer -= dt;
+        while self.combo_timer <= 0.0 && self.combo > 0 {
+            self.combo -= 1;
+            self.combo_timer += COMBO_DECAY_INTERVAL;
+        }
+        if self.combo == 0 {
+            self.combo_timer = 0.0;
+        }
+    }
+}
+
+impl ScoreSystem {
+    pub fn new() -> ScoreSystem {
+        ScoreSystem {
+            deaths: None,
+            phase_ends: None,
+        }
+    }
+}
+
+impl<'a> specs::System<'a> for ScoreSystem {
+    type SystemData = ScoreSystemData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        data.score.update(data.time.elapsed_game_time().as_seconds());
+
+        let deaths = &data.deaths;
+        let reader = self.deaths.get_or_insert_with(|| deaths.register_reader());
+        for _ in deaths.read(reader) {
+            data.score.on_kill();
+        }
+
+        let phase_ends = &data.phase_ends;
+        let reader = self.phase_ends
+            .get_or_insert_with(|| phase_ends.register_reader());
+        for phase in phase_ends.read(reader) {
+            if phase.bonus > 0 {
+                data.score.on_bonus(phase.bonus);
+            }
+        }
+    }
+}
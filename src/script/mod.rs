@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::collections::hash_map;
+use std::io::{self, Read};
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::error;
+use std::fmt;
+
+pub mod parser;
+pub mod vm;
+
+pub use self::vm::ScriptController;
+
+use rng::Rng;
+use self::parser::Stmt;
+
+pub const SCRIPT_DIR: &str = "assets/scripts";
+pub const SCRIPT_EXTENSION: &str = "bsc";
+
+/// A parsed and compiled script, shared between every entity running it.
+#[derive(Debug)]
+pub struct Program {
+    pub name: String,
+    pub code: Vec<vm::Instr>,
+}
+
+/// All scripts available to the game, keyed by file stem.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptLibrary {
+    programs: Arc<HashMap<String, Arc<Program>>>,
+    /// Number of controllers created so far, shared between clones so that
+    /// each controller gets its own random seed.
+    instances: Arc<AtomicUsize>,
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    InScript(String, Box<ScriptError>),
+}
+
+impl Program {
+    pub fn compile(name: &str, source: &str) -> Result<Program, ScriptError> {
+        let stmts = parser::parse(source)?;
+        Ok(Program {
+            name: name.to_string(),
+            code: vm::compile(stmts),
+        })
+    }
+
+    /// Names of the prefabs the script spawns.
+    pub fn prefab_names(&self) -> Vec<&str> {
+        self.code
+            .iter()
+            .filter_map(|instr| match *instr {
+                vm::Instr::Exec(Stmt::Prefab(ref name, _, _)) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl ScriptLibrary {
+    pub fn new() -> ScriptLibrary {
+        ScriptLibrary {
+            programs: Arc::new(HashMap::new()),
+            instances: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Compiles every script in `dir`. A single bad script fails the whole load
+    /// so that errors are caught at startup rather than mid-stage.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<ScriptLibrary, ScriptError> {
+        let mut programs = HashMap::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |e| e != SCRIPT_EXTENSION) {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            let mut source = String::new();
+            File::open(&path)?.read_to_string(&mut source)?;
+            let program = Program::compile(&name, &source).map_err(|e| {
+                ScriptError::InScript(name.clone(), Box::new(e))
+            })?;
+            programs.insert(name, Arc::new(program));
+        }
+
+        Ok(ScriptLibrary {
+            programs: Arc::new(programs),
+            instances: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Program>> {
+        self.programs.get(name).cloned()
+    }
+
+    pub fn programs(&self) -> hash_map::Values<String, Arc<Program>> {
+        self.programs.values()
+    }
+
+    /// Creates a controller running the named script.
+    pub fn controller(&self, name: &str) -> Option<ScriptController> {
+        self.get(name).map(
+            |program| ScriptController::new(program, self.clone()),
+        )
+    }
+
+    /// A generator for a new controller, seeded differently from every
+    /// other controller made from this library.
+    pub fn next_rng(&self) -> Rng {
+        let instance = self.instances.fetch_add(1, Ordering::Relaxed) as u64;
+        Rng::new((instance + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+}
+
+impl ScriptError {
+    pub fn parse<S: Into<String>>(line: usize, message: S) -> ScriptError {
+        ScriptError::Parse {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScriptError::Io(ref e) => write!(f, "unable to read script: {}", e),
+            ScriptError::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+            ScriptError::InScript(ref name, ref e) => write!(f, "{}: {}", name, e),
+        }
+    }
+}
+
+impl error::Error for ScriptError {
+    fn description(&self) -> &str {
+        match *self {
+            ScriptError::Io(_) => "unable to read script",
+            ScriptError::Parse { .. } => "invalid script",
+            ScriptError::InScript(_, ref e) => e.description(),
+        }
+    }
+}
+
+impl From<io::Error> for ScriptError {
+    fn from(e: io::Error) -> ScriptError {
+        ScriptError::Io(e)
+    }
+}
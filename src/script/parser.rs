@@ -0,0 +1,510 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use script::ScriptError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Var(String),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Move(Expr, Expr),
+    MoveTo(Expr, Expr, Expr),
+    Wait(Expr),
+    Set(String, Expr),
+    Fire(String, Vec<Expr>),
+    Spawn(String, Expr, Expr),
+    Prefab(String, Expr, Expr),
+    Color(Expr, Expr, Expr),
+    Size(Expr),
+    Despawn,
+    Repeat(Expr, Vec<Stmt>),
+    Loop(Vec<Stmt>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Str(String),
+    Op(char),
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Comma,
+    Newline,
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+pub fn parse(source: &str) -> Result<Vec<Stmt>, ScriptError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let stmts = parser.block()?;
+
+    match parser.peek() {
+        None => Ok(stmts),
+        Some(_) => Err(parser.error("unexpected '}'")),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ScriptError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&ch) = chars.peek() {
+        match ch {
+            '\n' => {
+                chars.next();
+                tokens.push((line, Token::Newline));
+                line += 1;
+            }
+            '#' => {
+                while chars.peek().map_or(false, |&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            c if c.is_digit(10) || c == '.' => {
+                let text = take_while(&mut chars, |c| c.is_digit(10) || c == '.');
+                let value = text.parse().map_err(|_| {
+                    ScriptError::parse(line, format!("invalid number '{}'", text))
+                })?;
+                tokens.push((line, Token::Number(value)));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let text = take_while(&mut chars, |c| c.is_alphanumeric() || c == '_');
+                tokens.push((line, Token::Ident(text)));
+            }
+            '"' => {
+                chars.next();
+                let text = take_while(&mut chars, |c| c != '"' && c != '\n');
+                if chars.next() != Some('"') {
+                    return Err(ScriptError::parse(line, "unterminated string"));
+                }
+                tokens.push((line, Token::Str(text)));
+            }
+            '+' | '-' | '*' | '/' => {
+                chars.next();
+                tokens.push((line, Token::Op(ch)));
+            }
+            '{' | '}' | '(' | ')' | ',' => {
+                chars.next();
+                let token = match ch {
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => Token::Comma,
+                };
+                tokens.push((line, token));
+            }
+            _ => {
+                return Err(ScriptError::parse(line, format!("unexpected '{}'", ch)));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn take_while<F: Fn(char) -> bool>(chars: &mut Peekable<Chars>, pred: F) -> String {
+    let mut text = String::new();
+    while let Some(&c) = chars.peek() {
+        if !pred(c) {
+            break;
+        }
+        text.push(c);
+        chars.next();
+    }
+    text
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|&(_, ref t)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|&(_, ref t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |&(line, _)| line)
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> ScriptError {
+        ScriptError::parse(self.line(), message)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ScriptError> {
+        match self.next() {
+            Some(ref t) if *t == expected => Ok(()),
+            Some(t) => {
+                self.pos -= 1;
+                Err(self.error(format!("expected {:?}, found {:?}", expected, t)))
+            }
+            None => Err(self.error(format!("expected {:?}", expected))),
+        }
+    }
+
+    fn end_of_statement(&mut self) -> Result<(), ScriptError> {
+        match self.peek() {
+            None | Some(&Token::RBrace) => Ok(()),
+            Some(&Token::Newline) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(t) => Err(self.error(format!("unexpected {:?} after statement", t))),
+        }
+    }
+
+    /// Parses statements up to a closing brace or the end of input.
+    fn block(&mut self) -> Result<Vec<Stmt>, ScriptError> {
+        let mut stmts = Vec::new();
+
+        loop {
+            match self.peek() {
+                None | Some(&Token::RBrace) => return Ok(stmts),
+                Some(&Token::Newline) => {
+                    self.pos += 1;
+                }
+                _ => stmts.push(self.statement()?),
+            }
+        }
+    }
+
+    fn braced_block(&mut self) -> Result<Vec<Stmt>, ScriptError> {
+        self.expect(Token::LBrace)?;
+        let stmts = self.block()?;
+        self.expect(Token::RBrace)?;
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ScriptError> {
+        let command = match self.next() {
+            Some(Token::Ident(name)) => name,
+            Some(t) => {
+                self.pos -= 1;
+                return Err(self.error(format!("expected a command, found {:?}", t)));
+            }
+            None => return Err(self.error("expected a command")),
+        };
+
+        let stmt = match command.as_str() {
+            "move" => {
+                let args = self.args(2)?;
+                Stmt::Move(args[0].clone(), args[1].clone())
+            }
+            "moveto" => {
+                let args = self.args(3)?;
+                Stmt::MoveTo(args[0].clone(), args[1].clone(), args[2].clone())
+            }
+            "wait" => Stmt::Wait(self.expr()?),
+            "size" => Stmt::Size(self.expr()?),
+            "color" => {
+                let args = self.args(3)?;
+                Stmt::Color(args[0].clone(), args[1].clone(), args[2].clone())
+            }
+            "set" => {
+                let name = self.ident()?;
+                Stmt::Set(name, self.expr()?)
+            }
+            "fire" => {
+                let pattern = self.ident()?;
+                let args = self.arg_list()?;
+                Stmt::Fire(pattern, args)
+            }
+            "spawn" => {
+                let script = match self.next() {
+                    Some(Token::Str(s)) => s,
+                    _ => {
+                        self.pos -= 1;
+                        return Err(self.error("spawn expects a script name in quotes"));
+                    }
+                };
+                self.expect(Token::Comma)?;
+                let args = self.args(2)?;
+                Stmt::Spawn(script, args[0].clone(), args[1].clone())
+            }
+            "prefab" => {
+                let prefab = match self.next() {
+                    Some(Token::Str(s)) => s,
+                    _ => {
+                        self.pos -= 1;
+                        return Err(self.error("prefab expects a prefab name in quotes"));
+                    }
+                };
+                self.expect(Token::Comma)?;
+                let args = self.args(2)?;
+                Stmt::Prefab(prefab, args[0].clone(), args[1].clone())
+            }
+            "despawn" => Stmt::Despawn,
+            "repeat" => {
+                let count = self.expr()?;
+                Stmt::Repeat(count, self.braced_block()?)
+            }
+            "loop" => Stmt::Loop(self.braced_block()?),
+            _ => {
+                self.pos -= 1;
+                return Err(self.error(format!("unknown command '{}'", command)));
+            }
+        };
+
+        self.end_of_statement()?;
+        Ok(stmt)
+    }
+
+    fn ident(&mut self) -> Result<String, ScriptError> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a name"))
+            }
+        }
+    }
+
+    /// Comma separated expressions running to the end of the line.
+    fn arg_list(&mut self) -> Result<Vec<Expr>, ScriptError> {
+        let mut args = Vec::new();
+        match self.peek() {
+            None | Some(&Token::Newline) | Some(&Token::RBrace) => return Ok(args),
+            _ => (),
+        }
+
+        args.push(self.expr()?);
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.expr()?);
+        }
+        Ok(args)
+    }
+
+    fn args(&mut self, count: usize) -> Result<Vec<Expr>, ScriptError> {
+        let args = self.arg_list()?;
+        if args.len() != count {
+            return Err(self.error(format!(
+                "expected {} arguments, found {}",
+                count,
+                args.len()
+            )));
+        }
+        Ok(args)
+    }
+
+    fn expr(&mut self) -> Result<Expr, ScriptError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(&Token::Op('+')) => BinOp::Add,
+                Some(&Token::Op('-')) => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ScriptError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(&Token::Op('*')) => BinOp::Mul,
+                Some(&Token::Op('/')) => BinOp::Div,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ScriptError> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Expr::Var(name));
+                }
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    args.push(self.expr()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.expr()?);
+                    }
+                }
+                self.expect(Token::RParen)?;
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::LParen) => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(t) => {
+                self.pos -= 1;
+                Err(self.error(format!("expected an expression, found {:?}", t)))
+            }
+            None => Err(self.error("expected an expression")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(n: f64) -> Box<Expr> {
+        Box::new(Expr::Number(n))
+    }
+
+    fn parse_error_line(source: &str) -> usize {
+        match parse(source) {
+            Err(ScriptError::Parse { line, .. }) => line,
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        let stmts = parse("set a 1 + 2 * 3").unwrap();
+        assert_eq!(
+            stmts,
+            vec![
+                Stmt::Set(
+                    "a".to_string(),
+                    Expr::Binary(
+                        BinOp::Add,
+                        num(1.0),
+                        Box::new(Expr::Binary(BinOp::Mul, num(2.0), num(3.0))),
+                    ),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_calls_negation_and_strings() {
+        let stmts = parse("spawn \"spinner\", -x, sin(pi / 2)").unwrap();
+        assert_eq!(
+            stmts,
+            vec![
+                Stmt::Spawn(
+                    "spinner".to_string(),
+                    Expr::Neg(Box::new(Expr::Var("x".to_string()))),
+                    Expr::Call(
+                        "sin".to_string(),
+                        vec![
+                            Expr::Binary(
+                                BinOp::Div,
+                                Box::new(Expr::Var("pi".to_string())),
+                                num(2.0),
+                            ),
+                        ],
+                    ),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_nested_blocks_and_skips_comments() {
+        let source = "# comment\n\nloop {\n    repeat 2 {\n        wait 1 # trailing\n    }\n}\n";
+        let stmts = parse(source).unwrap();
+        assert_eq!(
+            stmts,
+            vec![
+                Stmt::Loop(vec![
+                    Stmt::Repeat(Expr::Number(2.0), vec![Stmt::Wait(Expr::Number(1.0))]),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn fire_takes_any_number_of_arguments() {
+        assert_eq!(
+            parse("fire ring").unwrap(),
+            vec![Stmt::Fire("ring".to_string(), Vec::new())]
+        );
+        assert_eq!(
+            parse("fire aimed 5, 0.6, 200").unwrap(),
+            vec![
+                Stmt::Fire(
+                    "aimed".to_string(),
+                    vec![Expr::Number(5.0), Expr::Number(0.6), Expr::Number(200.0)],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_prefab_names() {
+        assert_eq!(
+            parse("prefab \"small_blue_bullet\", 0, 8").unwrap(),
+            vec![
+                Stmt::Prefab(
+                    "small_blue_bullet".to_string(),
+                    Expr::Number(0.0),
+                    Expr::Number(8.0),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_report_the_line_they_occur_on() {
+        assert_eq!(parse_error_line("move 1, 2\nbogus 3"), 2);
+        assert_eq!(parse_error_line("wait 1\n\nmove 1"), 3);
+        assert_eq!(parse_error_line("spawn \"open"), 1);
+        assert_eq!(parse_error_line("wait 1\nset a 2 $"), 2);
+        assert_eq!(parse_error_line("loop {\n    wait 1\n"), 2);
+        assert_eq!(parse_error_line("wait 1\n}"), 2);
+    }
+
+    #[test]
+    fn rejects_wrong_argument_counts() {
+        match parse("moveto 1, 2") {
+            Err(ScriptError::Parse { ref message, .. }) => {
+                assert_eq!(message, "expected 3 arguments, found 2");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cgmath::Vector2;
+use graphics;
+
+use controller::{Action, Controller, ControllerContext, ControllerSave, Projectile};
+use ecs::component::bullet::Faction;
+use ecs::spawn::Spawn;
+use pattern::Pattern;
+use rng::Rng;
+use render::{RectangleGraphic, RenderGraphicState};
+use script::{Program, ScriptLibrary};
+use script::parser::{BinOp, Expr, Stmt};
+
+/// Upper bound on instructions run in one frame, so a script that never
+/// waits cannot stall the game.
+const INSTRUCTION_BUDGET: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    Exec(Stmt),
+    PushCounter(Expr),
+    /// Decrements the top counter, jumping to the target once it is exhausted.
+    LoopCounter(usize),
+    PopCounter,
+    Jump(usize),
+}
+
+/// Runs a compiled script as an entity's controller.
+pub struct ScriptController {
+    program: Arc<Program>,
+    library: ScriptLibrary,
+    pc: usize,
+    counters: Vec<i64>,
+    vars: HashMap<String, f64>,
+    wait: u64,
+    frame: u64,
+    move_to: Option<(Vector2<f64>, u64)>,
+    color: [f32; 4],
+    size: f64,
+    rng: Rng,
+}
+
+/// A `ScriptController`'s progress in a form that can be saved. The
+/// program is stored by name and looked up again when restoring.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptState {
+    script: String,
+    pc: usize,
+    counters: Vec<i64>,
+    vars: HashMap<String, f64>,
+    wait: u64,
+    frame: u64,
+    move_to: Option<MoveTo>,
+    color: [f32; 4],
+    size: f64,
+    /// The generator's state bit for bit, since TOML integers are signed.
+    rng: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MoveTo {
+    target: [f64; 2],
+    frames: u64,
+}
+
+impl ScriptState {
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+}
+
+pub fn compile(stmts: Vec<Stmt>) -> Vec<Instr> {
+    let mut code = Vec::new();
+    compile_block(stmts, &mut code);
+    code
+}
+
+fn compile_block(stmts: Vec<Stmt>, code: &mut Vec<Instr>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Repeat(count, body) => {
+                code.push(Instr::PushCounter(count));
+                let start = code.len();
+                code.push(Instr::LoopCounter(0));
+                compile_block(body, code);
+                code.push(Instr::Jump(start));
+                let end = code.len();
+                code[start] = Instr::LoopCounter(end);
+                code.push(Instr::PopCounter);
+            }
+            Stmt::Loop(body) => {
+                let start = code.len();
+                compile_block(body, code);
+                code.push(Instr::Jump(start));
+            }
+            stmt => code.push(Instr::Exec(stmt)),
+        }
+    }
+}
+
+impl ScriptController {
+    pub fn new(program: Arc<Program>, library: ScriptLibrary) -> ScriptController {
+        ScriptController {
+            program,
+            pc: 0,
+            counters: Vec::new(),
+            vars: HashMap::new(),
+            wait: 0,
+            frame: 0,
+            move_to: None,
+            color: [0.9, 0.9, 0.2, 1.0],
+            size: 16.0,
+            rng: library.next_rng(),
+            library,
+        }
+    }
+
+    /// Resumes a saved script, or `None` if `library` has no script by the
+    /// saved name.
+    pub fn restore(state: ScriptState, library: &ScriptLibrary) -> Option<ScriptController> {
+        library.get(&state.script).map(|program| ScriptController {
+            program,
+            library: library.clone(),
+            pc: state.pc,
+            counters: state.counters,
+            vars: state.vars,
+            wait: state.wait,
+            frame: state.frame,
+            move_to: state
+                .move_to
+                .map(|m| (Vector2::new(m.target[0], m.target[1]), m.frames)),
+            color: state.color,
+            size: state.size,
+            rng: Rng::from_state(state.rng as u64),
+        })
+    }
+
+    fn eval(&mut self, expr: &Expr, ctx: &ControllerContext) -> f64 {
+        match *expr {
+            Expr::Number(n) => n,
+            Expr::Neg(ref e) => -self.eval(e, ctx),
+            Expr::Binary(op, ref lhs, ref rhs) => {
+                let lhs = self.eval(lhs, ctx);
+                let rhs = self.eval(rhs, ctx);
+                match op {
+                    BinOp::Add => lhs + rhs,
+                    BinOp::Sub => lhs - rhs,
+                    BinOp::Mul => lhs * rhs,
+                    BinOp::Div => if rhs == 0.0 { 0.0 } else { lhs / rhs },
+                }
+            }
+            Expr::Var(ref name) => {
+                let player = ctx.player_pos.unwrap_or(ctx.pos);
+                match name.as_str() {
+                    "x" => ctx.pos.x,
+                    "y" => ctx.pos.y,
+                    "player_x" => player.x,
+                    "player_y" => player.y,
+                    "frame" => self.frame as f64,
+                    "pi" => ::std::f64::consts::PI,
+                    "difficulty" => ctx.difficulty as u32 as f64,
+                    _ => self.vars.get(name).cloned().unwrap_or(0.0),
+                }
+            }
+            Expr::Call(ref name, ref args) => {
+                let args: Vec<f64> = args.iter().map(|a| self.eval(a, ctx)).collect();
+                let arg = |i: usize| args.get(i).cloned().unwrap_or(0.0);
+                match name.as_str() {
+                    "rand" => self.rng.range(arg(0), arg(1)),
+                    "sin" => arg(0).sin(),
+                    "cos" => arg(0).cos(),
+                    "atan2" => arg(0).atan2(arg(1)),
+                    "abs" => arg(0).abs(),
+                    "min" => arg(0).min(arg(1)),
+                    "max" => arg(0).max(arg(1)),
+                    "aim" => {
+                        let player = ctx.player_pos.unwrap_or(ctx.pos);
+                        (player.y - ctx.pos.y).atan2(player.x - ctx.pos.x)
+                    }
+                    _ => 0.0,
+                }
+            }
+        }
+    }
+
+    fn graphic(&self, size: f64) -> RenderGraphicState {
+        RenderGraphicState::Rectangle(RectangleGraphic {
+            rect: graphics::Rectangle::new(self.color),
+            width: size,
+            height: size,
+        })
+    }
+
+    fn exec(&mut self, stmt: &Stmt, ctx: &ControllerContext, actions: &mut Vec<Action>) {
+        match *stmt {
+            Stmt::Move(ref vx, ref vy) => {
+                let vel = Vector2::new(self.eval(vx, ctx), self.eval(vy, ctx));
+                self.move_to = None;
+                actions.push(Action::Move(vel));
+            }
+            Stmt::MoveTo(ref x, ref y, ref frames) => {
+                let target = Vector2::new(self.eval(x, ctx), self.eval(y, ctx));
+                let frames = self.eval(frames, ctx).max(1.0) as u64;
+                self.move_to = Some((target, frames));
+            }
+            Stmt::Wait(ref frames) => {
+                // Waits pace a script's volleys, so they shorten as the
+                // difficulty's fire rate goes up.
+                let frames = self.eval(frames, ctx) / ctx.scaling.fire_rate;
+                self.wait = frames.round().max(0.0) as u64;
+            }
+            Stmt::Set(ref name, ref value) => {
+                let value = self.eval(value, ctx);
+                self.vars.insert(name.clone(), value);
+            }
+            Stmt::Color(ref r, ref g, ref b) => {
+                self.color = [
+                    self.eval(r, ctx) as f32,
+                    self.eval(g, ctx) as f32,
+                    self.eval(b, ctx) as f32,
+                    1.0,
+                ];
+            }
+            Stmt::Size(ref size) => {
+                self.size = self.eval(size, ctx).max(1.0);
+            }
+            Stmt::Fire(ref kind, ref args) => {
+                let args: Vec<f64> = args.iter().map(|a| self.eval(a, ctx)).collect();
+                let arg = |i: usize| args.get(i).cloned().unwrap_or(0.0);
+                let pattern = match kind.as_str() {
+                    "ring" => Pattern::Ring {
+                        count: arg(0) as u32,
+                        speed: arg(1),
+                    },
+                    "aimed" => Pattern::Aimed {
+                        count: arg(0) as u32,
+                        spread: arg(1),
+                        speed: arg(2),
+                    },
+                    "spiral" => Pattern::Spiral {
+                        arms: arg(0) as u32,
+                        speed: arg(1),
+                        turn: arg(2),
+                    },
+                    _ => return,
+                }.scaled(ctx.scaling);
+
+                let angle = self.vars.get("angle").cloned().unwrap_or(0.0);
+                let graphic = self.graphic(self.size / 2.0);
+                for vel in pattern.volley(angle, ctx.aim_at_player()) {
+                    actions.push(Action::Fire(Projectile {
+                        offset: Vector2::new(0.0, 0.0),
+                        vel,
+                        graphic: graphic.clone(),
+                        faction: Faction::Enemy,
+                    }));
+                }
+
+                if let Pattern::Spiral { turn, .. } = pattern {
+                    self.vars.insert("angle".to_string(), angle + turn);
+                }
+            }
+            Stmt::Spawn(ref name, ref dx, ref dy) => {
+                let offset = Vector2::new(self.eval(dx, ctx), self.eval(dy, ctx));
+                if let Some(program) = self.library.get(name) {
+                    let child = ScriptController::new(program, self.library.clone());
+                    actions.push(Action::Spawn(
+                        Spawn::new(ctx.pos + offset)
+                            .with_render(self.graphic(self.size))
+                            .with_controller(Box::new(child)),
+                    ));
+                }
+            }
+            Stmt::Prefab(ref name, ref dx, ref dy) => {
+                actions.push(Action::SpawnPrefab {
+                    name: name.clone(),
+                    offset: Vector2::new(self.eval(dx, ctx), self.eval(dy, ctx)),
+                    vel: None,
+                });
+            }
+            Stmt::Despawn => {
+                actions.push(Action::Despawn(ctx.entity));
+                self.pc = self.program.code.len();
+            }
+            Stmt::Repeat(..) | Stmt::Loop(..) => unreachable!("blocks are compiled away"),
+        }
+    }
+}
+
+impl Controller for ScriptController {
+    fn do_actions(&mut self, ctx: &ControllerContext) -> Vec<Action> {
+        let mut actions = Vec::new();
+        self.frame += 1;
+
+        if let Some((target, frames)) = self.move_to.take() {
+            if frames == 0 {
+                actions.push(Action::Move(Vector2::new(0.0, 0.0)));
+            } else {
+                let dt = ctx.time.elapsed_game_time().as_seconds().max(1e-6);
+                actions.push(Action::Move((target - ctx.pos) / (frames as f64 * dt)));
+                self.move_to = Some((target, frames - 1));
+            }
+        }
+
+        if self.wait > 0 {
+            self.wait -= 1;
+            return actions;
+        }
+
+        let program = self.program.clone();
+        let mut budget = INSTRUCTION_BUDGET;
+
+        while self.pc < program.code.len() && self.wait == 0 && budget > 0 {
+            budget -= 1;
+            let pc = self.pc;
+            self.pc += 1;
+
+            match program.code[pc] {
+                Instr::Exec(ref stmt) => self.exec(stmt, ctx, &mut actions),
+                Instr::PushCounter(ref count) => {
+                    let count = self.eval(count, ctx).max(0.0) as i64;
+                    self.counters.push(count);
+                }
+                Instr::LoopCounter(end) => {
+                    let exhausted = match self.counters.last_mut() {
+                        Some(count) => {
+                            *count -= 1;
+                            *count < 0
+                        }
+                        None => true,
+                    };
+                    if exhausted {
+                        self.pc = end;
+                    }
+                }
+                Instr::PopCounter => {
+                    self.counters.pop();
+                }
+                Instr::Jump(target) => self.pc = target,
+            }
+        }
+
+        // A wait of N frames resumes on the Nth frame from now, and this
+        // frame counts as the first.
+        if self.wait > 0 {
+            self.wait -= 1;
+        }
+
+        actions
+    }
+
+    fn save(&self) -> Option<ControllerSave> {
+        Some(ControllerSave::Script(ScriptState {
+            script: self.program.name.clone(),
+            pc: self.pc,
+            counters: self.counters.clone(),
+            vars: self.vars.clone(),
+            wait: self.wait,
+            frame: self.frame,
+            move_to: self.move_to.map(|(target, frames)| MoveTo {
+                target: [target.x, target.y],
+                frames,
+            }),
+            color: self.color,
+            size: self.size,
+            rng: self.rng.state() as i64,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use specs;
+    use cgmath::Vector2;
+    use float_duration::FloatDuration;
+    use game_time::GameClock;
+    use game_time::step::ConstantStep;
+
+    use controller::{Action, Controller, ControllerContext};
+    use difficulty::{Difficulty, Scaling};
+    use input::InputManager;
+    use input::bindings::KeyBindings;
+    use script::{Program, ScriptLibrary};
+    use super::ScriptController;
+
+    fn controller(source: &str, library: &ScriptLibrary) -> ScriptController {
+        let program = Program::compile("test", source).unwrap();
+        ScriptController::new(Arc::new(program), library.clone())
+    }
+
+    /// Runs `controller` for `frames` frames, returning each frame's actions.
+    fn run(controller: &mut ScriptController, frames: usize) -> Vec<Vec<Action>> {
+        run_scaled(controller, frames, &Difficulty::Normal.scaling())
+    }
+
+    fn run_scaled(
+        controller: &mut ScriptController,
+        frames: usize,
+        scaling: &Scaling,
+    ) -> Vec<Vec<Action>> {
+        let mut world = specs::World::new();
+        let entity = world.create_entity().build();
+        let time = GameClock::new().tick(&ConstantStep::new(FloatDuration::seconds(1.0 / 60.0)));
+        let input = InputManager::new();
+        let bindings = KeyBindings::new();
+        let ctx = ControllerContext {
+            entity,
+            time: &time,
+            input: &input,
+            bindings: &bindings,
+            pos: Vector2::new(100.0, 100.0),
+            vel: Vector2::new(0.0, 0.0),
+            player_pos: Some(Vector2::new(100.0, 300.0)),
+            entities: &[],
+            difficulty: Difficulty::Normal,
+            scaling,
+        };
+
+        (0..frames).map(|_| controller.do_actions(&ctx)).collect()
+    }
+
+    fn moves(actions: &[Action]) -> Vec<Vector2<f64>> {
+        actions
+            .iter()
+            .filter_map(|a| match *a {
+                Action::Move(vel) => Some(vel),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn shots(actions: &[Action]) -> usize {
+        actions
+            .iter()
+            .filter(|a| match **a {
+                Action::Fire(_) => true,
+                _ => false,
+            })
+            .count()
+    }
+
+    #[test]
+    fn wait_resumes_after_the_given_number_of_frames() {
+        let mut script = controller("wait 2\nmove 1, 0", &ScriptLibrary::new());
+        let frames = run(&mut script, 4);
+
+        assert!(moves(&frames[0]).is_empty());
+        assert!(moves(&frames[1]).is_empty());
+        assert_eq!(moves(&frames[2]), vec![Vector2::new(1.0, 0.0)]);
+        assert!(frames[3].is_empty());
+    }
+
+    #[test]
+    fn waits_are_divided_by_the_fire_rate() {
+        let scaling = Scaling {
+            fire_rate: 2.0,
+            ..Difficulty::Normal.scaling()
+        };
+        let mut script = controller("wait 4\nmove 1, 0", &ScriptLibrary::new());
+        let frames = run_scaled(&mut script, 3, &scaling);
+
+        assert!(moves(&frames[1]).is_empty());
+        assert_eq!(moves(&frames[2]), vec![Vector2::new(1.0, 0.0)]);
+    }
+
+    #[test]
+    fn repeat_runs_its_body_the_given_number_of_times() {
+        let source = "repeat 3 {\n    fire ring 4, 100\n    wait 1\n}\nmove 5, 0";
+        let mut script = controller(source, &ScriptLibrary::new());
+        let frames = run(&mut script, 5);
+
+        let fired: Vec<usize> = frames.iter().map(|f| shots(f)).collect();
+        assert_eq!(fired, vec![4, 4, 4, 0, 0]);
+        assert_eq!(moves(&frames[3]), vec![Vector2::new(5.0, 0.0)]);
+    }
+
+    #[test]
+    fn expressions_see_variables_and_builtins() {
+        let source = "set speed 2 * 3\nmove speed + 1, y - player_y";
+        let mut script = controller(source, &ScriptLibrary::new());
+        let frames = run(&mut script, 1);
+
+        assert_eq!(moves(&frames[0]), vec![Vector2::new(7.0, -200.0)]);
+    }
+
+    #[test]
+    fn controllers_from_one_library_are_seeded_differently() {
+        let library = ScriptLibrary::new();
+        let source = "move rand(0, 1000), 0";
+        let first = moves(&run(&mut controller(source, &library), 1)[0]);
+        let second = moves(&run(&mut controller(source, &library), 1)[0]);
+
+        assert_eq!(first.len(), 1);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn despawn_ends_the_script() {
+        let mut script = controller("despawn\nmove 1, 0", &ScriptLibrary::new());
+        let frames = run(&mut script, 2);
+
+        assert_eq!(frames[0].len(), 1);
+        match frames[0][0] {
+            Action::Despawn(_) => (),
+            _ => panic!("expected a despawn"),
+        }
+        assert!(frames[1].is_empty());
+    }
+
+    #[test]
+    fn a_script_that_never_waits_yields_each_frame() {
+        let mut script = controller("loop {\n    move 1, 0\n}", &ScriptLibrary::new());
+        let frames = run(&mut script, 2);
+
+        assert_eq!(frames[0].len(), super::INSTRUCTION_BUDGET / 2);
+        assert!(!frames[1].is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use input::bindings::KeyBindings;
+use ui::Theme;
+
+/// Player adjustable options, shared between the menus and gameplay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub key_bindings: KeyBindings,
+    pub show_fps: bool,
+    pub dark_ui: bool,
+    pub music_volume: f64,
+    pub sfx_volume: f64,
+}
+
+pub type SharedSettings = Rc<RefCell<Settings>>;
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings {
+            key_bindings: KeyBindings::new(),
+            show_fps: false,
+            dark_ui: false,
+            music_volume: 0.8,
+            sfx_volume: 0.8,
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        if self.dark_ui {
+            Theme::dark()
+        } else {
+            Theme::light()
+        }
+    }
+
+    pub fn shared(self) -> SharedSettings {
+        Rc::new(RefCell::new(self))
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new()
+    }
+}
@@ -0,0 +1,47 @@
+use std::f64::consts::PI;
+
+use cgmath::Vector2;
+
+/// Arrangement of enemies spawned together by a stage event.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Formation {
+    Single,
+    /// `count` enemies in a row, each `spacing` apart.
+    Line { count: u32, spacing: [f64; 2] },
+    /// A V opening downwards with the point at the origin.
+    Wedge { count: u32, spacing: [f64; 2] },
+    /// `count` enemies evenly spaced on a circle of `radius`.
+    Circle { count: u32, radius: f64 },
+}
+
+impl Formation {
+    /// Offsets of each member relative to the formation's origin.
+    pub fn offsets(&self) -> Vec<Vector2<f64>> {
+        match *self {
+            Formation::Single => vec![Vector2::new(0.0, 0.0)],
+            Formation::Line { count, spacing } => {
+                let step = Vector2::new(spacing[0], spacing[1]);
+                (0..count).map(|i| step * i as f64).collect()
+            }
+            Formation::Wedge { count, spacing } => {
+                (0..count)
+                    .map(|i| {
+                        let rank = ((i + 1) / 2) as f64;
+                        let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+                        Vector2::new(side * rank * spacing[0], -rank * spacing[1])
+                    })
+                    .collect()
+            }
+            Formation::Circle { count, radius } => {
+                let step = 2.0 * PI / count.max(1) as f64;
+                (0..count)
+                    .map(|i| {
+                        let (sin, cos) = (step * i as f64).sin_cos();
+                        Vector2::new(cos * radius, sin * radius)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
@@ -0,0 +1,687 @@
+use std::path::Path;
+
+use cgmath::Vector2;
+use graphics;
+use game_time::GameTime;
+
+use data::{self, DataError};
+use ecs::spawn::{Spawn, SpawnQueue};
+use ecs::prefab::{Prefab, PrefabLibrary};
+use ecs::component::boss::{Boss, BossPhase};
+use ecs::component::pickup::{Drops, PickupKind};
+use pattern::{PatternController, PatternSpec};
+use script::ScriptLibrary;
+use render::{RectangleGraphic, RenderGraphicState};
+use background::{Background, BackgroundData};
+use audio::MusicCue;
+use ecs::event::EventChannel;
+use difficulty::{Difficulty, PerDifficulty, Scaling};
+
+pub mod formation;
+
+pub use self::formation::Formation;
+
+const DEFAULT_BANNER_DURATION: f64 = 3.0;
+
+/// A stage as described by its data file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StageData {
+    pub name: String,
+    #[serde(default)]
+    pub background: BackgroundData,
+    #[serde(default)]
+    pub events: Vec<TimedEvent>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimedEvent {
+    pub time: f64,
+    #[serde(flatten)]
+    pub event: StageEvent,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StageEvent {
+    SpawnFormation {
+        formation: Formation,
+        at: [f64; 2],
+        enemy: EnemyData,
+    },
+    /// Spawns a prefab at `at`, or once per position in `formation`, with
+    /// any fields in `overrides` replacing the prefab's own.
+    SpawnPrefab {
+        prefab: String,
+        at: [f64; 2],
+        #[serde(default)]
+        formation: Option<Formation>,
+        #[serde(default)]
+        overrides: Prefab,
+    },
+    StartPattern { at: [f64; 2], pattern: PatternSpec },
+    SpawnBoss { at: [f64; 2], boss: BossData },
+    BossIntro {
+        name: String,
+        #[serde(default = "default_banner_duration")]
+        duration: f64,
+    },
+    /// Eases the background's scroll speed to `speed` times normal over
+    /// `duration` seconds. Applies to every layer unless `layer` is given.
+    Scroll {
+        speed: f64,
+        #[serde(default)]
+        duration: f64,
+        #[serde(default)]
+        layer: Option<usize>,
+    },
+    /// Crossfades to a new music track over `fade` seconds.
+    Music {
+        #[serde(flatten)]
+        cue: MusicCue,
+        #[serde(default)]
+        fade: f64,
+    },
+    StopMusic {
+        #[serde(default)]
+        fade: f64,
+    },
+    EndStage,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnemyData {
+    pub size: f64,
+    pub color: [f32; 4],
+    #[serde(default = "default_enemy_health")]
+    pub health: f64,
+    /// Health on particular difficulties, used instead of scaling `health`.
+    #[serde(default)]
+    pub health_by_difficulty: PerDifficulty<f64>,
+    #[serde(default)]
+    pub velocity: [f64; 2],
+    #[serde(default)]
+    pub pattern: Option<PatternSpec>,
+    /// Name of a script in `assets/scripts/` driving this enemy. Takes
+    /// precedence over `pattern`.
+    #[serde(default)]
+    pub script: Option<String>,
+    #[serde(default)]
+    pub drops: Vec<PickupKind>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BossData {
+    pub name: String,
+    pub size: f64,
+    pub color: [f32; 4],
+    pub phases: Vec<BossPhase>,
+}
+
+/// Text shown across the playfield for a limited time.
+#[derive(Debug, Clone)]
+pub struct Banner {
+    pub text: String,
+    pub remaining: f64,
+}
+
+/// The parts of the world a stage's events act on.
+pub struct StageContext<'a> {
+    pub scripts: &'a ScriptLibrary,
+    pub prefabs: &'a PrefabLibrary,
+    pub difficulty: Difficulty,
+    pub scaling: &'a Scaling,
+    pub spawns: &'a mut SpawnQueue,
+    pub background: &'a mut Background,
+    pub events: &'a mut EventChannel<StageEvent>,
+}
+
+/// Runs a stage's timeline, spawning entities as their events come due.
+pub struct Stage {
+    data: StageData,
+    time: f64,
+    next_event: usize,
+    banner: Option<Banner>,
+    finished: bool,
+    /// Phase the next boss starts at, when practicing a later phase.
+    boss_phase: Option<usize>,
+}
+
+/// How far a stage has got through its timeline, kept as a resource so it
+/// can be saved with the world.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StageCursor {
+    pub time: f64,
+    pub next_event: usize,
+    pub finished: bool,
+}
+
+fn default_banner_duration() -> f64 {
+    DEFAULT_BANNER_DURATION
+}
+
+fn default_enemy_health() -> f64 {
+    1.0
+}
+
+impl Stage {
+    pub fn new(mut data: StageData) -> Stage {
+        data.events.sort_by(|a, b| {
+            a.time.partial_cmp(&b.time).unwrap_or(::std::cmp::Ordering::Equal)
+        });
+
+        Stage {
+            data,
+            time: 0.0,
+            next_event: 0,
+            banner: None,
+            finished: false,
+            boss_phase: None,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Stage, DataError> {
+        let data: StageData = data::load_toml(path)?;
+        let mut last = 0.0;
+        for event in &data.events {
+            if !event.time.is_finite() || event.time < last {
+                return Err(DataError::Invalid(format!(
+                    "{} event at {}s is out of order; keep events sorted by time",
+                    data.name, event.time
+                )));
+            }
+            last = event.time;
+            if let StageEvent::Music { ref cue, .. } = event.event {
+                cue.validate()?;
+            }
+        }
+        Ok(Stage::new(data))
+    }
+
+    /// Fails if an event names a prefab or script that `prefabs` doesn't
+    /// have.
+    pub fn check_names(&self, prefabs: &PrefabLibrary) -> Result<(), DataError> {
+        for event in &self.data.events {
+            let user = format!("{} event at {}s", self.data.name, event.time);
+            match event.event {
+                StageEvent::SpawnFormation { ref enemy, .. } => {
+                    if let Some(ref script) = enemy.script {
+                        prefabs.check_script(script, &user)?;
+                    }
+                    if let Some(ref pattern) = enemy.pattern {
+                        prefabs.check_pattern(pattern, &user)?;
+                    }
+                }
+                StageEvent::SpawnPrefab {
+                    ref prefab,
+                    ref overrides,
+                    ..
+                } => {
+                    prefabs.check_prefab(prefab, &user)?;
+                    prefabs.check_prefab_data(overrides, &user)?;
+                }
+                StageEvent::StartPattern { ref pattern, .. } => {
+                    prefabs.check_pattern(pattern, &user)?;
+                }
+                StageEvent::SpawnBoss { ref boss, .. } => {
+                    for pattern in boss.phases.iter().flat_map(|p| &p.patterns) {
+                        prefabs.check_pattern(pattern, &user)?;
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.data.name
+    }
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    pub fn background(&self) -> &BackgroundData {
+        &self.data.background
+    }
+    pub fn banner(&self) -> Option<&Banner> {
+        self.banner.as_ref()
+    }
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn cursor(&self) -> StageCursor {
+        StageCursor {
+            time: self.time,
+            next_event: self.next_event,
+            finished: self.finished,
+        }
+    }
+
+    /// Moves the timeline to a saved position. Unlike `seek`, events
+    /// skipped over don't take effect and it can move backwards.
+    pub fn set_cursor(&mut self, cursor: &StageCursor) {
+        self.time = cursor.time;
+        self.next_event = cursor.next_event.min(self.data.events.len());
+        self.finished = cursor.finished;
+        self.banner = None;
+    }
+
+    /// Time of the last event in the timeline.
+    pub fn length(&self) -> f64 {
+        self.data.events.last().map_or(0.0, |e| e.time)
+    }
+
+    /// Names of the first boss's phases, in order.
+    pub fn boss_phases(&self) -> Vec<String> {
+        self.data
+            .events
+            .iter()
+            .filter_map(|e| match e.event {
+                StageEvent::SpawnBoss { ref boss, .. } => Some(boss),
+                _ => None,
+            })
+            .next()
+            .map_or_else(Vec::new, |boss| {
+                boss.phases.iter().map(|p| p.name.clone()).collect()
+            })
+    }
+
+    /// Jumps the timeline to `time` without spawning anything on the way.
+    /// Scrolling and the latest music cue still take effect, so the stage
+    /// looks and sounds as it would have had it been played up to there.
+    /// The timeline never moves backwards.
+    pub fn seek(&mut self, time: f64, ctx: &mut StageContext) {
+        let mut music = None;
+
+        while self.next_event < self.data.events.len() &&
+            self.data.events[self.next_event].time < time
+        {
+            let event = self.data.events[self.next_event].event.clone();
+            self.next_event += 1;
+            match event {
+                StageEvent::Scroll { speed, layer, .. } => {
+                    ctx.background.set_scroll_speed(layer, speed, 0.0);
+                }
+                StageEvent::Music { cue, .. } => {
+                    music = Some(StageEvent::Music { cue, fade: 0.0 });
+                }
+                StageEvent::StopMusic { .. } => music = Some(StageEvent::StopMusic { fade: 0.0 }),
+                StageEvent::EndStage => self.run_event(event, ctx),
+                _ => (),
+            }
+        }
+
+        if let Some(event) = music {
+            ctx.events.single_write(event);
+        }
+        self.time = time.max(self.time);
+    }
+
+    /// Seeks to the first boss's entrance and has it start at `phase`.
+    /// Returns false if the stage has no boss.
+    pub fn seek_to_boss(&mut self, phase: usize, ctx: &mut StageContext) -> bool {
+        let entrance = self.data.events.iter().find(|e| match e.event {
+            StageEvent::SpawnBoss { .. } => true,
+            _ => false,
+        });
+        match entrance.map(|e| e.time) {
+            Some(time) => {
+                self.seek(time, ctx);
+                self.boss_phase = Some(phase);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn update(&mut self, time: &GameTime, ctx: &mut StageContext) {
+        let dt = time.elapsed_game_time().as_seconds();
+
+        if let Some(mut banner) = self.banner.take() {
+            banner.remaining -= dt;
+            if banner.remaining > 0.0 {
+                self.banner = Some(banner);
+            }
+        }
+
+        if self.finished {
+            return;
+        }
+
+        self.time += dt;
+
+        while self.next_event < self.data.events.len() &&
+            self.data.events[self.next_event].time <= self.time
+        {
+            let event = self.data.events[self.next_event].event.clone();
+            self.next_event += 1;
+            ctx.events.single_write(event.clone());
+            self.run_event(event, ctx);
+        }
+    }
+
+    fn run_event(&mut self, event: StageEvent, ctx: &mut StageContext) {
+        match event {
+            StageEvent::SpawnFormation {
+                formation,
+                at,
+                enemy,
+            } => {
+                let origin = Vector2::new(at[0], at[1]);
+                for offset in formation.offsets() {
+                    let spawn =
+                        enemy.spawn(origin + offset, ctx.scripts, ctx.difficulty, ctx.scaling);
+                    ctx.spawns.spawn(spawn);
+                }
+            }
+            StageEvent::SpawnPrefab {
+                prefab,
+                at,
+                formation,
+                overrides,
+            } => {
+                let origin = Vector2::new(at[0], at[1]);
+                for offset in formation.unwrap_or(Formation::Single).offsets() {
+                    if let Some(mut spawn) =
+                        ctx.prefabs.spawn(&prefab, origin + offset, Some(&overrides))
+                    {
+                        ctx.scaling.scale_spawn(&mut spawn);
+                        ctx.spawns.spawn(spawn);
+                    }
+                }
+            }
+            StageEvent::StartPattern { at, pattern } => {
+                ctx.spawns.spawn(
+                    Spawn::new(Vector2::new(at[0], at[1]))
+                        .with_controller(Box::new(PatternController::emitter(pattern))),
+                );
+            }
+            StageEvent::SpawnBoss { at, boss } => {
+                let mut spawn = boss.spawn(Vector2::new(at[0], at[1]));
+                if let Some(phase) = self.boss_phase.take() {
+                    spawn.boss = spawn.boss.map(|b| b.starting_at(phase));
+                }
+                ctx.spawns.spawn(spawn);
+            }
+            StageEvent::BossIntro { name, duration } => {
+                self.banner = Some(Banner {
+                    text: name,
+                    remaining: duration,
+                });
+            }
+            StageEvent::Scroll {
+                speed,
+                duration,
+                layer,
+            } => {
+                ctx.background.set_scroll_speed(layer, speed, duration);
+            }
+            // Played by whoever listens for stage events.
+            StageEvent::Music { .. } | StageEvent::StopMusic { .. } => (),
+            StageEvent::EndStage => {
+                self.finished = true;
+                self.banner = Some(Banner {
+                    text: "Stage Clear".to_string(),
+                    remaining: DEFAULT_BANNER_DURATION,
+                });
+            }
+        }
+    }
+}
+
+impl EnemyData {
+    pub fn spawn(
+        &self,
+        pos: Vector2<f64>,
+        scripts: &ScriptLibrary,
+        difficulty: Difficulty,
+        scaling: &Scaling,
+    ) -> Spawn {
+        let health = self.health_by_difficulty
+            .get_or(difficulty, || self.health * scaling.enemy_health);
+        let mut spawn = Spawn::new(pos)
+            .with_velocity(Vector2::new(self.velocity[0], self.velocity[1]))
+            .with_render(square_graphic(self.color, self.size))
+            .with_hitbox(self.size / 2.0)
+            .with_health(health)
+            .with_drops(Drops(self.drops.clone()));
+
+        if let Some(controller) = self.script.as_ref().and_then(|name| scripts.controller(name)) {
+            return spawn.with_controller(Box::new(controller));
+        }
+        if let Some(ref pattern) = self.pattern {
+            spawn = spawn.with_controller(Box::new(PatternController::new(pattern.clone())));
+        }
+
+        spawn
+    }
+}
+
+impl BossData {
+    pub fn spawn(&self, pos: Vector2<f64>) -> Spawn {
+        // Health is filled in from the first phase when the boss starts.
+        Spawn::new(pos)
+            .with_velocity(Vector2::new(0.0, 0.0))
+            .with_render(square_graphic(self.color, self.size))
+            .with_hitbox(self.size / 2.0)
+            .with_health(1.0)
+            .with_boss(Boss::new(self.name.clone(), self.phases.clone()))
+    }
+}
+
+fn square_graphic(color: [f32; 4], size: f64) -> RenderGraphicState {
+    RenderGraphicState::Rectangle(RectangleGraphic {
+        rect: graphics::Rectangle::new(color),
+        width: size,
+        height: size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    use float_duration::FloatDuration;
+    use game_time::GameClock;
+    use game_time::step::ConstantStep;
+    use specs::{self, Join};
+    use toml;
+
+    use background::{Background, BackgroundData};
+    use data::DataError;
+    use difficulty::{Difficulty, Scaling};
+    use ecs::component::{self, boss::Boss};
+    use ecs::event::EventChannel;
+    use ecs::prefab::PrefabLibrary;
+    use ecs::spawn::SpawnQueue;
+    use script::ScriptLibrary;
+    use super::{Stage, StageContext, StageEvent};
+
+    const STAGE: &str = r#"
+        name = "Test"
+
+        [[events]]
+        time = 0.0
+        type = "music"
+        path = "first.ogg"
+
+        [[events]]
+        time = 2.0
+        type = "spawn_formation"
+        at = [0.0, 0.0]
+        formation = { type = "line", count = 2, spacing = [10.0, 0.0] }
+        enemy = { size = 10.0, color = [1.0, 1.0, 1.0, 1.0] }
+
+        [[events]]
+        time = 3.0
+        type = "music"
+        path = "second.ogg"
+
+        [[events]]
+        time = 5.0
+        type = "spawn_boss"
+        at = [0.0, 0.0]
+
+        [events.boss]
+        name = "Boss"
+        size = 10.0
+        color = [1.0, 1.0, 1.0, 1.0]
+
+        [[events.boss.phases]]
+        name = "One"
+        health = 1.0
+        time_limit = 10.0
+
+        [[events.boss.phases]]
+        name = "Two"
+        health = 1.0
+        time_limit = 10.0
+    "#;
+
+    struct Harness {
+        scripts: ScriptLibrary,
+        prefabs: PrefabLibrary,
+        scaling: Scaling,
+        spawns: SpawnQueue,
+        background: Background,
+        events: EventChannel<StageEvent>,
+    }
+
+    impl Harness {
+        fn new() -> Harness {
+            Harness {
+                scripts: ScriptLibrary::new(),
+                prefabs: PrefabLibrary::default(),
+                scaling: Scaling::default(),
+                spawns: SpawnQueue::new(),
+                background: Background::new(&BackgroundData::default()),
+                events: EventChannel::new(),
+            }
+        }
+
+        fn ctx(&mut self) -> StageContext {
+            StageContext {
+                scripts: &self.scripts,
+                prefabs: &self.prefabs,
+                difficulty: Difficulty::Normal,
+                scaling: &self.scaling,
+                spawns: &mut self.spawns,
+                background: &mut self.background,
+                events: &mut self.events,
+            }
+        }
+
+        /// Builds everything spawned so far and returns the bosses' phases.
+        fn boss_phases(&mut self) -> Vec<usize> {
+            let mut world = specs::World::new();
+            component::register_components(&mut world);
+            ::std::mem::replace(&mut self.spawns, SpawnQueue::new()).apply(&mut world);
+            let bosses = world.read::<Boss>();
+            (&bosses).join().map(|b| b.phase_index()).collect()
+        }
+    }
+
+    fn stage() -> Stage {
+        Stage::new(toml::from_str(STAGE).unwrap())
+    }
+
+    fn load(name: &str, contents: &str) -> Result<Stage, DataError> {
+        let path = env::temp_dir().join(format!("stage_test_{}.toml", name));
+        File::create(&path)
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+        let stage = Stage::load(&path);
+        let _ = fs::remove_file(&path);
+        stage
+    }
+
+    #[test]
+    fn seek_spawns_nothing_and_plays_the_latest_music() {
+        let mut stage = stage();
+        let mut harness = Harness::new();
+        let mut reader = harness.events.register_reader();
+
+        stage.seek(4.0, &mut harness.ctx());
+
+        assert_eq!(stage.time(), 4.0);
+        assert!(harness.spawns.is_empty());
+        let events: Vec<_> = harness.events.read(&mut reader).cloned().collect();
+        assert_eq!(events.len(), 1);
+        match events[0] {
+            StageEvent::Music { ref cue, fade } => {
+                assert_eq!(cue.path, "second.ogg");
+                assert_eq!(fade, 0.0);
+            }
+            ref other => panic!("expected music, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn seek_never_moves_backwards() {
+        let mut stage = stage();
+        let mut harness = Harness::new();
+
+        stage.seek(4.0, &mut harness.ctx());
+        stage.seek(1.0, &mut harness.ctx());
+
+        assert_eq!(stage.time(), 4.0);
+        assert_eq!(stage.cursor().next_event, 3);
+    }
+
+    #[test]
+    fn seek_to_boss_starts_the_boss_at_the_phase() {
+        let mut stage = stage();
+        let mut harness = Harness::new();
+
+        assert!(stage.seek_to_boss(1, &mut harness.ctx()));
+        assert_eq!(stage.time(), 5.0);
+        assert!(harness.spawns.is_empty());
+
+        let time = GameClock::new().tick(&ConstantStep::new(FloatDuration::seconds(1.0 / 60.0)));
+        stage.update(&time, &mut harness.ctx());
+
+        assert_eq!(harness.boss_phases(), vec![1]);
+    }
+
+    #[test]
+    fn seek_to_boss_fails_without_a_boss() {
+        let data = toml::from_str(
+            r#"
+            name = "No boss"
+
+            [[events]]
+            time = 1.0
+            type = "end_stage"
+        "#,
+        ).unwrap();
+        let mut stage = Stage::new(data);
+        let mut harness = Harness::new();
+
+        assert!(!stage.seek_to_boss(0, &mut harness.ctx()));
+        assert_eq!(stage.time(), 0.0);
+    }
+
+    #[test]
+    fn load_rejects_events_out_of_time_order() {
+        assert!(load("ordered", STAGE).is_ok());
+
+        let unordered = r#"
+            name = "Unordered"
+
+            [[events]]
+            time = 2.0
+            type = "end_stage"
+
+            [[events]]
+            time = 1.0
+            type = "stop_music"
+        "#;
+        match load("unordered", unordered) {
+            Err(DataError::Invalid(_)) => (),
+            other => panic!("expected an invalid stage, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use glutin::VirtualKeyCode;
+use game_time::GameTime;
+
+use render;
+use score::Score;
+use settings::SharedSettings;
+use profile::SharedProfile;
+use input::{InputEvent, InputEvents, InputManager, Repeat};
+use state::{GameState, TitleState, Transition};
+use state::results::rank_text;
+
+pub struct GameOverState {
+    score: Score,
+    /// Place in the high score table, if the score made it.
+    rank: Option<usize>,
+    settings: SharedSettings,
+    profile: SharedProfile,
+}
+
+impl GameOverState {
+    pub fn new(
+        score: Score,
+        rank: Option<usize>,
+        settings: SharedSettings,
+        profile: SharedProfile,
+    ) -> GameOverState {
+        GameOverState {
+            score,
+            rank,
+            settings,
+            profile,
+        }
+    }
+}
+
+impl GameState for GameOverState {
+    fn handle_input(&mut self, events: &InputEvents, _input: &InputManager) -> Transition {
+        for event in events {
+            if let InputEvent::VirtKey(VirtualKeyCode::Return, _, Repeat::NoRepeat) = *event {
+                return Transition::Reset(Box::new(TitleState::new(
+                    self.settings.clone(),
+                    self.profile.clone(),
+                )));
+            }
+        }
+        Transition::None
+    }
+
+    fn update(&mut self, _time: &GameTime) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        let color = [0.9, 0.2, 0.2, 1.0];
+        graphics::clear([0.1, 0.1, 0.1, 1.0], gl);
+        render::render_text("Game Over", [460.0, 320.0], 48, color, ctx, gl, glyphs);
+        render::render_text(
+            &format!("Score {:010}", self.score.points),
+            [480.0, 400.0],
+            24,
+            [1.0, 1.0, 1.0, 1.0],
+            ctx,
+            gl,
+            glyphs,
+        );
+        if let Some(rank) = self.rank {
+            render::render_text(
+                &rank_text(rank),
+                [480.0, 440.0],
+                24,
+                [1.0, 1.0, 1.0, 1.0],
+                ctx,
+                gl,
+                glyphs,
+            );
+        }
+    }
+}
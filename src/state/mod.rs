@@ -0,0 +1,175 @@
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use game_time::GameTime;
+
+use input::{InputEvents, InputManager};
+use console::{Console, Cvars, Invocation};
+use render::post::ScreenEffects;
+
+pub mod title;
+pub mod play;
+pub mod pause;
+pub mod game_over;
+pub mod results;
+pub mod options;
+pub mod practice;
+
+pub use self::title::TitleState;
+pub use self::play::PlayState;
+pub use self::pause::PauseState;
+pub use self::game_over::GameOverState;
+pub use self::results::ResultsState;
+pub use self::options::OptionsState;
+pub use self::practice::PracticeState;
+
+/// What the state stack should do after a state handles a frame.
+pub enum Transition {
+    None,
+    Push(Box<GameState>),
+    Pop,
+    Replace(Box<GameState>),
+    /// Clears the whole stack and starts over from the given state.
+    Reset(Box<GameState>),
+    Quit,
+}
+
+/// One screen of the game. Only the top state of the stack receives input
+/// and updates, but states beneath an overlay are still drawn.
+pub trait GameState {
+    fn handle_input(&mut self, events: &InputEvents, input: &InputManager) -> Transition;
+    fn update(&mut self, time: &GameTime) -> Transition;
+    fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache);
+
+    /// Overlays are drawn on top of the state below them instead of
+    /// replacing it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Whether game time should stand still while this state is on top.
+    /// Wall time keeps running regardless.
+    fn freezes_game_time(&self) -> bool {
+        false
+    }
+
+    /// Full-screen effects to apply while this state is drawn.
+    fn screen_effects(&self) -> ScreenEffects {
+        ScreenEffects::new()
+    }
+
+    /// Runs a console command, printing any result to `console`. Returns
+    /// false if this state doesn't know the command.
+    fn run_command(&mut self, _command: &Invocation, _console: &mut Console) -> bool {
+        false
+    }
+
+    /// Picks up the console variables this state cares about. Called every
+    /// frame before `update`.
+    fn apply_cvars(&mut self, _cvars: &Cvars) {}
+}
+
+#[derive(Default)]
+pub struct StateStack {
+    states: Vec<Box<GameState>>,
+}
+
+impl StateStack {
+    pub fn new() -> StateStack {
+        StateStack { states: Vec::new() }
+    }
+
+    pub fn push(&mut self, state: Box<GameState>) {
+        self.states.push(state);
+    }
+
+    pub fn pop(&mut self) -> Option<Box<GameState>> {
+        self.states.pop()
+    }
+
+    pub fn replace(&mut self, state: Box<GameState>) {
+        self.states.pop();
+        self.states.push(state);
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn freezes_game_time(&self) -> bool {
+        self.states.last().map_or(false, |s| s.freezes_game_time())
+    }
+
+    /// Effects of the state at the bottom of what's drawn, so they carry on
+    /// under overlays such as the pause menu.
+    pub fn screen_effects(&self) -> ScreenEffects {
+        self.states
+            .iter()
+            .rev()
+            .find(|s| !s.is_overlay())
+            .map_or_else(ScreenEffects::new, |s| s.screen_effects())
+    }
+
+    /// Applies a transition. Returns false once the stack wants the game to
+    /// exit, either through `Transition::Quit` or by running out of states.
+    pub fn apply(&mut self, transition: Transition) -> bool {
+        match transition {
+            Transition::None => (),
+            Transition::Push(state) => self.push(state),
+            Transition::Pop => {
+                self.pop();
+            }
+            Transition::Replace(state) => self.replace(state),
+            Transition::Reset(state) => {
+                self.states.clear();
+                self.push(state);
+            }
+            Transition::Quit => self.states.clear(),
+        }
+
+        !self.states.is_empty()
+    }
+
+    pub fn handle_input(&mut self, events: &InputEvents, input: &InputManager) -> bool {
+        let transition = match self.states.last_mut() {
+            Some(state) => state.handle_input(events, input),
+            None => return false,
+        };
+        self.apply(transition)
+    }
+
+    /// Hands a console command to the top state. Returns false if it
+    /// wasn't handled.
+    pub fn run_command(&mut self, command: &Invocation, console: &mut Console) -> bool {
+        self.states
+            .last_mut()
+            .map_or(false, |s| s.run_command(command, console))
+    }
+
+    pub fn apply_cvars(&mut self, cvars: &Cvars) {
+        for state in &mut self.states {
+            state.apply_cvars(cvars);
+        }
+    }
+
+    pub fn update(&mut self, time: &GameTime) -> bool {
+        let transition = match self.states.last_mut() {
+            Some(state) => state.update(time),
+            None => return false,
+        };
+        self.apply(transition)
+    }
+
+    pub fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        let base = self.states
+            .iter()
+            .rposition(|s| !s.is_overlay())
+            .unwrap_or(0);
+
+        for state in &mut self.states[base..] {
+            state.draw(ctx, gl, glyphs);
+        }
+    }
+}
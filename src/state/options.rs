@@ -0,0 +1,84 @@
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use game_time::GameTime;
+
+use render;
+use settings::SharedSettings;
+use profile::{self, SharedProfile};
+use ui::Ui;
+use input::{InputEvents, InputManager};
+use state::{GameState, Transition};
+
+const MENU_ORIGIN: [f64; 2] = [420.0, 140.0];
+const THEMES: [&str; 2] = ["Light", "Dark"];
+
+/// Edits the shared `Settings` in place, saving them to the profile on the
+/// way out.
+pub struct OptionsState {
+    settings: SharedSettings,
+    profile: SharedProfile,
+    ui: Ui,
+}
+
+impl OptionsState {
+    pub fn new(settings: SharedSettings, profile: SharedProfile) -> OptionsState {
+        let theme = settings.borrow().theme();
+        OptionsState {
+            settings,
+            profile,
+            ui: Ui::new(theme),
+        }
+    }
+}
+
+impl GameState for OptionsState {
+    fn handle_input(&mut self, events: &InputEvents, input: &InputManager) -> Transition {
+        let mut settings = self.settings.borrow_mut();
+        let ui = &mut self.ui;
+        let mut theme = if settings.dark_ui { 1 } else { 0 };
+
+        ui.set_theme(settings.theme());
+        ui.begin(events, input, MENU_ORIGIN);
+
+        ui.slider("Music volume", &mut settings.music_volume, 0.0, 1.0, 0.1);
+        ui.slider("Effects volume", &mut settings.sfx_volume, 0.0, 1.0, 0.1);
+        ui.toggle("Show FPS", &mut settings.show_fps);
+        if ui.list("Theme", &THEMES, &mut theme) {
+            settings.dark_ui = theme == 1;
+        }
+
+        let keys = &mut settings.key_bindings;
+        ui.key_binding("Up", &mut keys.up);
+        ui.key_binding("Down", &mut keys.down);
+        ui.key_binding("Left", &mut keys.left);
+        ui.key_binding("Right", &mut keys.right);
+        ui.key_binding("Shoot", &mut keys.shoot);
+        ui.key_binding("Focus", &mut keys.focus);
+        ui.key_binding("Bomb", &mut keys.bomb);
+
+        let back = ui.button("Back") || ui.back_pressed();
+        ui.end();
+
+        if back {
+            let mut profile = self.profile.borrow_mut();
+            profile.options = settings.clone();
+            if let Err(e) = profile.save(profile::PROFILE_PATH) {
+                println!("Unable to save {}: {}", profile::PROFILE_PATH, e);
+            }
+            Transition::Pop
+        } else {
+            Transition::None
+        }
+    }
+
+    fn update(&mut self, _time: &GameTime) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        let theme = self.ui.theme().clone();
+        graphics::clear(theme.background, gl);
+        render::render_text("Options", [420.0, 100.0], 36, theme.text, ctx, gl, glyphs);
+        self.ui.draw(ctx, gl, glyphs);
+    }
+}
@@ -0,0 +1,76 @@
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use game_time::GameTime;
+
+use render;
+use settings::SharedSettings;
+use profile::SharedProfile;
+use ui::Ui;
+use input::{InputEvents, InputManager};
+use state::{GameState, OptionsState, TitleState, Transition};
+
+const MENU_ORIGIN: [f64; 2] = [420.0, 400.0];
+
+/// Drawn over a paused `PlayState`, which stops receiving updates while
+/// this is on top of the stack.
+pub struct PauseState {
+    settings: SharedSettings,
+    profile: SharedProfile,
+    ui: Ui,
+}
+
+impl PauseState {
+    pub fn new(settings: SharedSettings, profile: SharedProfile) -> PauseState {
+        let theme = settings.borrow().theme();
+        PauseState {
+            settings,
+            profile,
+            ui: Ui::new(theme),
+        }
+    }
+}
+
+impl GameState for PauseState {
+    fn handle_input(&mut self, events: &InputEvents, input: &InputManager) -> Transition {
+        let mut transition = Transition::None;
+
+        self.ui.set_theme(self.settings.borrow().theme());
+        self.ui.begin(events, input, MENU_ORIGIN);
+        if self.ui.button("Resume") || self.ui.back_pressed() {
+            transition = Transition::Pop;
+        }
+        if self.ui.button("Options") {
+            transition = Transition::Push(Box::new(OptionsState::new(
+                self.settings.clone(),
+                self.profile.clone(),
+            )));
+        }
+        if self.ui.button("Quit to title") {
+            transition = Transition::Reset(Box::new(TitleState::new(
+                self.settings.clone(),
+                self.profile.clone(),
+            )));
+        }
+        self.ui.end();
+
+        transition
+    }
+
+    fn update(&mut self, _time: &GameTime) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        render::render_dim(0.5, ctx, gl);
+        render::render_text("Paused", [520.0, 360.0], 40, [1.0; 4], ctx, gl, glyphs);
+        self.ui.draw(ctx, gl, glyphs);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn freezes_game_time(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,816 @@
+use std::collections::HashMap;
+use std::mem;
+use std::path::Path;
+
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use glutin::VirtualKeyCode;
+use specs::{self, Join};
+use cgmath;
+use game_time::GameTime;
+
+use world;
+use ecs::component;
+use ecs::event::{self, BombUsed, EnemyHit, EntityDied, EventChannel, Grazed, LifeLost,
+                 PickupCollected, PlayerHit, ReaderId, ShotFired};
+use ecs::prefab::{PrefabLibrary, PREFAB_PATH};
+use ecs::save::{SaveError, SaveRegistry, WorldSave};
+use ecs::spawn::SpawnQueue;
+use render::{self, QuadBatch, RectangleGraphic, RenderGraphicState};
+use controller;
+use stage;
+use data::DataError;
+use script::{self, ScriptLibrary};
+use score::{self, Score};
+use input::{InputEvent, InputEvents, InputManager, Repeat};
+use console::{self, Console, Cvars, Invocation};
+use settings::SharedSettings;
+use profile::{self, ScoreEntry, SharedProfile};
+use difficulty::{self, Difficulty, Scaling};
+use hud::{Hud, HudValues};
+use particle::{Emitter, EmitterConfig, EmitterId, ParticleSystem, StyleId};
+use background::Background;
+use audio::{self, Audio, Channel, Sfx};
+use render::post::ScreenEffects;
+use state::{GameState, GameOverState, PauseState, ResultsState, Transition};
+use state::practice::{PracticeOptions, PracticeStart};
+
+pub const STAGE_PATH: &str = "assets/stages/stage1.toml";
+/// Recorded with high scores. There's only the one shot type so far.
+const SHOT_TYPE: &str = "standard";
+const QUICK_SAVE_PATH: &str = "saves/quicksave.toml";
+const QUICK_SAVE_KEY: VirtualKeyCode = VirtualKeyCode::F5;
+const QUICK_LOAD_KEY: VirtualKeyCode = VirtualKeyCode::F9;
+/// Starts a practice run over with the same options.
+const RESTART_KEY: VirtualKeyCode = VirtualKeyCode::R;
+/// Weight given to the newest frame when smoothing the FPS readout.
+const FPS_SMOOTHING: f64 = 0.1;
+/// Seconds the player can't be hit again after losing a life.
+const RESPAWN_INVULNERABILITY: f64 = 2.0;
+/// Seconds the player can't be hit after using a bomb.
+const BOMB_INVULNERABILITY: f64 = 3.0;
+/// Seconds of chromatic shift after the player uses a bomb.
+const BOMB_SHIFT_DURATION: f64 = 0.5;
+const BOMB_SHIFT_AMOUNT: f32 = 12.0;
+const BLOOM_INTENSITY: f32 = 0.6;
+const MAX_PARTICLES: usize = 4096;
+const EXPLOSION_PARTICLES: usize = 24;
+const SPARK_PARTICLES: usize = 3;
+/// Bullet trails emit more slowly than the player's, as there are so many
+/// more of them.
+const BULLET_TRAIL_RATE: f64 = 15.0;
+
+/// Particle styles used for gameplay feedback, and the events that
+/// trigger them.
+struct Effects {
+    explosion: StyleId,
+    spark: StyleId,
+    player_trail: StyleId,
+    bullet_trail: StyleId,
+    /// Trail emitter following each entity that has one.
+    trails: HashMap<specs::Entity, EmitterId>,
+    enemy_hits: ReaderId,
+    deaths: ReaderId,
+}
+
+/// Events that play sounds or change the music.
+struct SoundCues {
+    shots: ReaderId,
+    enemy_hits: ReaderId,
+    deaths: ReaderId,
+    player_hits: ReaderId,
+    grazes: ReaderId,
+    pickups: ReaderId,
+    stage: ReaderId,
+}
+
+/// Gameplay: owns the entity world and the running stage.
+pub struct PlayState {
+    world: world::World,
+    dispatcher: specs::Dispatcher<'static, 'static>,
+    stage: Option<stage::Stage>,
+    invulnerable: f64,
+    /// Seconds left of the chromatic shift from the last bomb.
+    bomb_shift: f64,
+    settings: SharedSettings,
+    profile: SharedProfile,
+    difficulty: Difficulty,
+    /// Set when this is a practice run rather than a scored one.
+    practice: Option<PracticeOptions>,
+    god_mode: bool,
+    show_hitboxes: bool,
+    hud: Hud,
+    hi_score: u64,
+    fps: f64,
+    batch: QuadBatch,
+    particles: ParticleSystem,
+    effects: Effects,
+    audio: Audio,
+    sound_cues: SoundCues,
+    player_hits: ReaderId,
+    bombs: ReaderId,
+}
+
+impl PlayState {
+    pub fn new(
+        settings: SharedSettings,
+        profile: SharedProfile,
+        difficulty: Difficulty,
+    ) -> PlayState {
+        let mut entity_set = specs::World::new();
+        component::register_components(&mut entity_set);
+        render::register_resources(&mut entity_set);
+        event::register_events(&mut entity_set);
+        entity_set.add_resource(SpawnQueue::new());
+        entity_set.add_resource(component::boss::BossStatus::new());
+        entity_set.add_resource(score::Score::new());
+        entity_set.add_resource(component::player::PlayerStats::new());
+        entity_set.add_resource(stage::StageCursor::default());
+        entity_set.add_resource(InputManager::new());
+        entity_set.add_resource(settings.borrow().key_bindings.clone());
+        {
+            let mut saves = entity_set.write_resource::<SaveRegistry>();
+            saves.register_resource::<score::Score>("score");
+            saves.register_resource::<component::player::PlayerStats>("player_stats");
+            saves.register_resource::<stage::StageCursor>("stage");
+        }
+
+        let scripts = ScriptLibrary::load_dir(script::SCRIPT_DIR).unwrap_or_else(|e| {
+            println!("Unable to load scripts: {}", e);
+            ScriptLibrary::new()
+        });
+        let prefabs = PrefabLibrary::load(PREFAB_PATH, scripts.clone()).unwrap_or_else(|e| {
+            println!("Unable to load {}: {}", PREFAB_PATH, e);
+            PrefabLibrary::new(HashMap::new(), scripts.clone())
+        });
+        entity_set.add_resource(scripts);
+        entity_set.add_resource(prefabs.clone());
+
+        let stage = stage::Stage::load(STAGE_PATH).and_then(|stage| {
+            stage.check_names(&prefabs)?;
+            Ok(stage)
+        });
+        let stage = match stage {
+            Ok(stage) => Some(stage),
+            Err(e) => {
+                println!("Unable to load {}: {}", STAGE_PATH, e);
+                None
+            }
+        };
+
+        if let Some(ref stage) = stage {
+            entity_set.add_resource(Background::new(stage.background()));
+        }
+        let scaling = difficulty
+            .load_scaling(difficulty::DIFFICULTY_PATH)
+            .unwrap_or_else(|e| {
+                println!("Unable to load {}: {}", difficulty::DIFFICULTY_PATH, e);
+                difficulty.scaling()
+            });
+        entity_set.add_resource(difficulty);
+        entity_set.add_resource(scaling);
+        let hi_score = stage
+            .as_ref()
+            .map_or(0, |s| profile.borrow().high_score(difficulty, s.name()));
+
+        let mut audio = Audio::new(audio::default_backend());
+        if let Err(e) = audio.load_sounds(audio::SOUNDS_PATH) {
+            println!("Unable to load {}: {}", audio::SOUNDS_PATH, e);
+        }
+
+        let mut particles = ParticleSystem::new(MAX_PARTICLES);
+        let effects = Effects {
+            explosion: particles.add_style(EmitterConfig::explosion()),
+            spark: particles.add_style(EmitterConfig::hit_spark()),
+            player_trail: particles.add_style(EmitterConfig::trail()),
+            bullet_trail: particles.add_style(EmitterConfig {
+                rate: BULLET_TRAIL_RATE,
+                ..EmitterConfig::trail()
+            }),
+            trails: HashMap::new(),
+            enemy_hits: reader::<EnemyHit>(&entity_set),
+            deaths: reader::<EntityDied>(&entity_set),
+        };
+        let sound_cues = SoundCues {
+            shots: reader::<ShotFired>(&entity_set),
+            enemy_hits: reader::<EnemyHit>(&entity_set),
+            deaths: reader::<EntityDied>(&entity_set),
+            player_hits: reader::<PlayerHit>(&entity_set),
+            grazes: reader::<Grazed>(&entity_set),
+            pickups: reader::<PickupCollected>(&entity_set),
+            stage: reader::<stage::StageEvent>(&entity_set),
+        };
+        let player_hits = reader::<PlayerHit>(&entity_set);
+        let bombs = reader::<BombUsed>(&entity_set);
+
+        let mut state = PlayState {
+            world: world::World::new(entity_set),
+            dispatcher: build_dispatcher(),
+            stage,
+            invulnerable: 0.0,
+            bomb_shift: 0.0,
+            settings,
+            profile,
+            difficulty,
+            practice: None,
+            god_mode: false,
+            show_hitboxes: false,
+            hud: Hud::load_or_default(),
+            hi_score,
+            fps: 0.0,
+            batch: QuadBatch::new(),
+            particles,
+            effects,
+            audio,
+            sound_cues,
+            player_hits,
+            bombs,
+        };
+        state.spawn_player();
+        state
+    }
+
+    /// A practice run, starting partway through the stage. Practice scores
+    /// aren't entered in the high score table.
+    pub fn practice(
+        settings: SharedSettings,
+        profile: SharedProfile,
+        difficulty: Difficulty,
+        options: PracticeOptions,
+    ) -> PlayState {
+        let mut state = PlayState::new(settings, profile, difficulty);
+        match options.start {
+            PracticeStart::Time(time) => state.with_stage(|stage, ctx| stage.seek(time, ctx)),
+            PracticeStart::BossPhase(phase) => {
+                state.with_stage(|stage, ctx| {
+                    if !stage.seek_to_boss(phase, ctx) {
+                        println!("Stage '{}' has no boss to practice", stage.name());
+                    }
+                });
+            }
+        }
+        state.practice = Some(options);
+        state.apply_practice_stats();
+        state
+    }
+
+    pub fn world(&self) -> &world::World {
+        &self.world
+    }
+    pub fn world_mut(&mut self) -> &mut world::World {
+        &mut self.world
+    }
+
+    fn spawn_player(&mut self) {
+        let entity_set = self.world.get_specs_mut();
+        entity_set
+            .create_entity()
+            .with(component::pos::Position(cgmath::Vector2::new(
+                world::PLAYFIELD_WIDTH / 2.0,
+                world::PLAYFIELD_HEIGHT - 80.0,
+            )))
+            .with(component::pos::Movable(cgmath::Vector2::new(0.0, 0.0)))
+            .with(component::render::Render::new(
+                RenderGraphicState::Rectangle(RectangleGraphic {
+                    rect: graphics::Rectangle::new([1.0, 0.0, 1.0, 1.0]),
+                    width: 25.0,
+                    height: 25.0,
+                }),
+            ))
+            .with(player_control())
+            .with(component::player::Player::new())
+            .with(component::collision::Hitbox::new(3.0))
+            .with(component::graze::Graze::new(24.0))
+            .with(component::pickup::Collector::new(32.0))
+            .with(component::weapon::Weapon::new(0.08))
+            .build();
+
+        entity_set.maintain();
+    }
+
+    /// Runs `f` on the stage, if one loaded, with the parts of the world
+    /// its events act on.
+    fn with_stage<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut stage::Stage, &mut stage::StageContext),
+    {
+        if let Some(ref mut stage) = self.stage {
+            let specs = self.world.get_specs();
+            f(
+                stage,
+                &mut stage::StageContext {
+                    scripts: &specs.read_resource::<ScriptLibrary>(),
+                    prefabs: &specs.read_resource::<PrefabLibrary>(),
+                    difficulty: *specs.read_resource::<Difficulty>(),
+                    scaling: &specs.read_resource::<Scaling>(),
+                    spawns: &mut specs.write_resource::<SpawnQueue>(),
+                    background: &mut specs.write_resource::<Background>(),
+                    events: &mut specs.write_resource::<EventChannel<stage::StageEvent>>(),
+                },
+            );
+        }
+    }
+
+    /// Replaces the running stage with the one at `path`, starting from its
+    /// beginning. Anything already spawned stays where it is.
+    pub fn load_stage<P: AsRef<Path>>(&mut self, path: P) -> Result<(), DataError> {
+        let stage = stage::Stage::load(path)?;
+        stage.check_names(&self.world.get_specs().read_resource::<PrefabLibrary>())?;
+        self.world
+            .get_specs_mut()
+            .add_resource(Background::new(stage.background()));
+        self.stage = Some(stage);
+        Ok(())
+    }
+
+    /// Spawns the prefab `name` at `pos`, scaled for the difficulty.
+    /// Returns false if there's no such prefab.
+    fn spawn_prefab(&mut self, name: &str, pos: cgmath::Vector2<f64>) -> bool {
+        let specs = self.world.get_specs();
+        let spawn = specs.read_resource::<PrefabLibrary>().spawn(name, pos, None);
+        match spawn {
+            Some(mut spawn) => {
+                specs.read_resource::<Scaling>().scale_spawn(&mut spawn);
+                specs.write_resource::<SpawnQueue>().spawn(spawn);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Holds the player's lives and power at the practice run's fixed
+    /// values, if it has them.
+    fn apply_practice_stats(&mut self) {
+        if let Some(ref practice) = self.practice {
+            let mut stats = self.world
+                .get_specs()
+                .write_resource::<component::player::PlayerStats>();
+            if let Some(lives) = practice.lives {
+                stats.lives = lives;
+            }
+            if let Some(power) = practice.power {
+                stats.power = power;
+            }
+        }
+    }
+
+    /// Writes every entity, the score and the stage's progress to `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        let specs = self.world.get_specs();
+        self.store_stage_cursor();
+        let save = specs.read_resource::<SaveRegistry>().save(specs)?;
+        save.write(path)
+    }
+
+    /// Replaces the world with one written by `save` and moves the stage
+    /// timeline back to where it was. Controllers that couldn't be saved
+    /// are lost, so those entities simply drift.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SaveError> {
+        let save = WorldSave::read(path)?;
+        // Saves without the stage's progress leave the timeline as it is.
+        self.store_stage_cursor();
+
+        let specs = self.world.get_specs_mut();
+        let saves = mem::replace(
+            &mut *specs.write_resource::<SaveRegistry>(),
+            SaveRegistry::new(),
+        );
+        let loaded = saves.load(specs, &save);
+        *specs.write_resource::<SaveRegistry>() = saves;
+        loaded?;
+
+        if let Some(ref mut stage) = self.stage {
+            stage.set_cursor(&specs.read_resource::<stage::StageCursor>());
+        }
+        Ok(())
+    }
+
+    /// Copies the stage's progress into the world so it is saved.
+    fn store_stage_cursor(&self) {
+        if let Some(ref stage) = self.stage {
+            *self.world
+                .get_specs()
+                .write_resource::<stage::StageCursor>() = stage.cursor();
+        }
+    }
+
+    /// Makes the player invulnerable for a while after each bomb, and
+    /// shakes the screen's colors apart.
+    fn handle_bombs(&mut self, dt: f64) {
+        self.bomb_shift = (self.bomb_shift - dt).max(0.0);
+        let bombs = self.world
+            .get_specs()
+            .read_resource::<EventChannel<BombUsed>>()
+            .read(&mut self.bombs)
+            .count();
+        if bombs > 0 {
+            self.invulnerable = self.invulnerable.max(BOMB_INVULNERABILITY);
+            self.bomb_shift = BOMB_SHIFT_DURATION;
+        }
+    }
+
+    /// Takes a life for each hit this tick. Returns false once the player
+    /// is hit with no lives left.
+    fn handle_player_hits(&mut self, dt: f64) -> bool {
+        let specs = self.world.get_specs();
+        let hits = specs
+            .read_resource::<EventChannel<PlayerHit>>()
+            .read(&mut self.player_hits)
+            .count();
+
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+        let invincible = self.god_mode || self.practice.as_ref().map_or(false, |p| p.invincible);
+        if hits == 0 || self.invulnerable > 0.0 || invincible {
+            return true;
+        }
+
+        let mut stats = specs.write_resource::<component::player::PlayerStats>();
+        if stats.lives == 0 {
+            return false;
+        }
+        stats.lives -= 1;
+        self.invulnerable = RESPAWN_INVULNERABILITY;
+        specs
+            .write_resource::<EventChannel<LifeLost>>()
+            .single_write(LifeLost { lives_left: stats.lives });
+        true
+    }
+
+    /// Emits particles for this tick's hits and kills.
+    fn spawn_effects(&mut self) {
+        let specs = self.world.get_specs();
+        let effects = &mut self.effects;
+
+        let hits = specs.read_resource::<EventChannel<EnemyHit>>();
+        for hit in hits.read(&mut effects.enemy_hits) {
+            self.particles
+                .burst(effects.spark, [hit.pos.x, hit.pos.y], SPARK_PARTICLES);
+        }
+        let deaths = specs.read_resource::<EventChannel<EntityDied>>();
+        for kill in deaths.read(&mut effects.deaths) {
+            self.particles.burst(
+                effects.explosion,
+                [kill.pos.x, kill.pos.y],
+                EXPLOSION_PARTICLES,
+            );
+        }
+    }
+
+    /// Keeps a trail emitter on the player and on every bullet, removing
+    /// those whose entity is gone.
+    fn update_trails(&mut self) {
+        let specs = self.world.get_specs();
+        let positions = specs.read::<component::pos::Position>();
+        let players = specs.read::<component::player::Player>();
+        let bullets = specs.read::<component::bullet::Bullet>();
+        let effects = &mut self.effects;
+
+        let mut trails = HashMap::with_capacity(effects.trails.len());
+        for (entity, &component::pos::Position(pos)) in (&*specs.entities(), &positions).join() {
+            let style = if players.get(entity).is_some() {
+                effects.player_trail
+            } else if bullets.get(entity).is_some() {
+                effects.bullet_trail
+            } else {
+                continue;
+            };
+
+            let pos = [pos.x, pos.y];
+            let id = match effects.trails.remove(&entity) {
+                Some(id) if self.particles.move_emitter(id, pos) => id,
+                _ => self.particles.add_emitter(Emitter::new(style, pos, None)),
+            };
+            trails.insert(entity, id);
+        }
+
+        for (_, id) in effects.trails.drain() {
+            self.particles.remove_emitter(id);
+        }
+        effects.trails = trails;
+    }
+
+    /// Plays sounds for this tick's events at the current volume settings.
+    fn play_sounds(&mut self, dt: f64) {
+        {
+            let settings = self.settings.borrow();
+            self.audio
+                .set_volume(Channel::Music, settings.music_volume as f32);
+            self.audio
+                .set_volume(Channel::Sfx, settings.sfx_volume as f32);
+        }
+
+        let specs = self.world.get_specs();
+        let cues = &mut self.sound_cues;
+        let audio = &mut self.audio;
+
+        // Each sound plays at most once a tick however many events asked
+        // for it, so a burst can't start voices only to cut them off.
+        let shots = specs.read_resource::<EventChannel<ShotFired>>();
+        if shots.read(&mut cues.shots).next().is_some() {
+            audio.play(Sfx::Shot);
+        }
+        let hits = specs.read_resource::<EventChannel<EnemyHit>>();
+        if hits.read(&mut cues.enemy_hits).next().is_some() {
+            audio.play(Sfx::Hit);
+        }
+        let deaths = specs.read_resource::<EventChannel<EntityDied>>();
+        if deaths.read(&mut cues.deaths).next().is_some() {
+            audio.play(Sfx::Explosion);
+        }
+        let player_hits = specs.read_resource::<EventChannel<PlayerHit>>();
+        if player_hits.read(&mut cues.player_hits).next().is_some() {
+            audio.play(Sfx::PlayerHit);
+        }
+        let grazes = specs.read_resource::<EventChannel<Grazed>>();
+        if grazes.read(&mut cues.grazes).next().is_some() {
+            audio.play(Sfx::Graze);
+        }
+        let pickups = specs.read_resource::<EventChannel<PickupCollected>>();
+        if pickups.read(&mut cues.pickups).next().is_some() {
+            audio.play(Sfx::Pickup);
+        }
+
+        let stage_events = specs.read_resource::<EventChannel<stage::StageEvent>>();
+        for event in stage_events.read(&mut cues.stage) {
+            match *event {
+                stage::StageEvent::Music { ref cue, fade } => audio.play_music(cue, fade),
+                stage::StageEvent::StopMusic { fade } => audio.stop_music(fade),
+                _ => (),
+            }
+        }
+
+        audio.update(dt);
+    }
+
+    fn hud_values(&self) -> HudValues {
+        let specs = self.world.get_specs();
+        let score = specs.read_resource::<Score>();
+        let stats = specs.read_resource::<component::player::PlayerStats>();
+        let boss = specs.read_resource::<component::boss::BossStatus>();
+
+        HudValues {
+            score: score.points,
+            hi_score: self.hi_score,
+            lives: stats.lives,
+            bombs: stats.bombs,
+            power: stats.power,
+            graze: score.graze,
+            fps: if self.settings.borrow().show_fps {
+                Some(self.fps)
+            } else {
+                None
+            },
+            boss_time: boss.display.as_ref().map(|d| d.time_left),
+        }
+    }
+
+    fn score(&self) -> Score {
+        self.world.get_specs().read_resource::<Score>().clone()
+    }
+
+    /// Enters the final score in the profile's high score table, unlocking
+    /// practice for the stage if it was cleared, and saves the profile.
+    /// Returns the score's rank if it made the table.
+    fn record_score(&self, cleared: bool) -> Option<usize> {
+        let stage = match self.stage {
+            Some(ref stage) if self.practice.is_none() => stage.name().to_string(),
+            _ => return None,
+        };
+
+        let mut profile = self.profile.borrow_mut();
+        let entry = ScoreEntry::new(&profile.name, self.score().points, SHOT_TYPE);
+        let rank = profile.submit_score(self.difficulty, &stage, entry);
+        if cleared {
+            profile.unlock(&profile::practice_unlock(&stage));
+        }
+        if let Err(e) = profile.save(profile::PROFILE_PATH) {
+            println!("Unable to save {}: {}", profile::PROFILE_PATH, e);
+        }
+        rank
+    }
+}
+
+/// Builds the systems run every tick. Kept for the whole stage so systems
+/// can hold on to their event readers.
+fn build_dispatcher() -> specs::Dispatcher<'static, 'static> {
+    specs::DispatcherBuilder::new()
+        .add(component::pos::MovementSystem, "movement", &[])
+        .add(component::controller::ControlSystem, "control", &[])
+        .add(component::player::BombSystem, "bomb", &["control"])
+        .add(
+            component::collision::CollisionSystem,
+            "collision",
+            &["movement", "control"],
+        )
+        .add(component::boss::BossSystem::new(), "boss", &["collision"])
+        .add(component::graze::GrazeSystem, "graze", &["collision"])
+        .add(
+            component::render::HitFlashSystem::new(),
+            "hit_flash",
+            &["collision"],
+        )
+        .add(score::ScoreSystem::new(), "score", &["boss", "graze"])
+        .add(component::pickup::PickupSystem::new(), "pickup", &["score"])
+        .add(component::weapon::WeaponSystem, "weapon", &["pickup"])
+        .add(
+            component::bullet::BulletCleanupSystem,
+            "bullet_cleanup",
+            &["movement"],
+        )
+        .add(
+            component::bullet::EnemyCleanupSystem::new(),
+            "enemy_cleanup",
+            &["movement"],
+        )
+        .build()
+}
+
+fn player_control() -> component::controller::Control {
+    component::controller::Control::new(Box::new(controller::HumanController::new()))
+}
+
+fn reader<T: Send + Sync + 'static>(world: &specs::World) -> ReaderId {
+    world.read_resource::<EventChannel<T>>().register_reader()
+}
+
+impl GameState for PlayState {
+    fn handle_input(&mut self, events: &InputEvents, input: &InputManager) -> Transition {
+        {
+            let bindings = self.settings.borrow().key_bindings.clone();
+            let specs = self.world.get_specs_mut();
+            specs.add_resource(input.clone());
+            // Read every frame so rebinding from the pause menu takes effect.
+            specs.add_resource(bindings);
+        }
+
+        for event in events {
+            match *event {
+                InputEvent::VirtKey(VirtualKeyCode::Escape, _, Repeat::NoRepeat) => {
+                    return Transition::Push(Box::new(PauseState::new(
+                        self.settings.clone(),
+                        self.profile.clone(),
+                    )));
+                }
+                InputEvent::VirtKey(QUICK_SAVE_KEY, _, Repeat::NoRepeat) => {
+                    if let Err(e) = self.save(QUICK_SAVE_PATH) {
+                        println!("Unable to save {}: {}", QUICK_SAVE_PATH, e);
+                    }
+                }
+                InputEvent::VirtKey(RESTART_KEY, _, Repeat::NoRepeat) => {
+                    if let Some(ref options) = self.practice {
+                        return Transition::Replace(Box::new(PlayState::practice(
+                            self.settings.clone(),
+                            self.profile.clone(),
+                            self.difficulty,
+                            options.clone(),
+                        )));
+                    }
+                }
+                InputEvent::VirtKey(QUICK_LOAD_KEY, _, Repeat::NoRepeat) => {
+                    if let Err(e) = self.load(QUICK_SAVE_PATH) {
+                        println!("Unable to load {}: {}", QUICK_SAVE_PATH, e);
+                    }
+                }
+                _ => (),
+            }
+        }
+        Transition::None
+    }
+
+    fn update(&mut self, time: &GameTime) -> Transition {
+        let wall_dt = time.elapsed_wall_time().as_seconds();
+        if wall_dt > 0.0 {
+            self.fps += (1.0 / wall_dt - self.fps) * FPS_SMOOTHING;
+        }
+
+        self.world
+            .get_specs_mut()
+            .add_resource_with_id(time.clone(), 0);
+
+        self.dispatcher
+            .dispatch(&mut self.world.get_specs_mut().res);
+
+        self.with_stage(|stage, ctx| stage.update(time, ctx));
+        self.world
+            .get_specs()
+            .write_resource::<Background>()
+            .update(time.elapsed_game_time().as_seconds());
+
+        SpawnQueue::flush(self.world.get_specs_mut());
+        render::gather(&self.world);
+
+        self.spawn_effects();
+        self.update_trails();
+        self.play_sounds(time.elapsed_game_time().as_seconds());
+        self.particles.update(time.elapsed_game_time().as_seconds());
+
+        self.handle_bombs(time.elapsed_game_time().as_seconds());
+        let alive = self.handle_player_hits(time.elapsed_game_time().as_seconds());
+        self.apply_practice_stats();
+        event::maintain_events(self.world.get_specs());
+
+        if !alive {
+            let rank = self.record_score(false);
+            return Transition::Replace(Box::new(GameOverState::new(
+                self.score(),
+                rank,
+                self.settings.clone(),
+                self.profile.clone(),
+            )));
+        }
+
+        let cleared = self.stage
+            .as_ref()
+            .map_or(false, |s| s.is_finished() && s.banner().is_none());
+        if cleared {
+            let rank = self.record_score(true);
+            return Transition::Replace(Box::new(ResultsState::new(
+                self.score(),
+                rank,
+                self.settings.clone(),
+                self.profile.clone(),
+            )));
+        }
+
+        Transition::None
+    }
+
+    fn screen_effects(&self) -> ScreenEffects {
+        ScreenEffects {
+            bloom: BLOOM_INTENSITY,
+            chromatic_shift: (self.bomb_shift / BOMB_SHIFT_DURATION) as f32 * BOMB_SHIFT_AMOUNT,
+        }
+    }
+
+    fn apply_cvars(&mut self, cvars: &Cvars) {
+        self.god_mode = cvars.get_bool(console::GOD_MODE);
+        self.show_hitboxes = cvars.get_bool(console::SHOW_HITBOXES);
+    }
+
+    fn run_command(&mut self, command: &Invocation, console: &mut Console) -> bool {
+        match command.name.as_str() {
+            "spawn" => {
+                let name = match command.args.first() {
+                    Some(name) => name,
+                    None => {
+                        console.print("usage: spawn <prefab> [x y]");
+                        return true;
+                    }
+                };
+                let mut coords = Vec::new();
+                for arg in &command.args[1..] {
+                    match arg.parse::<f64>() {
+                        Ok(coord) => coords.push(coord),
+                        Err(_) => {
+                            console.print(format!(
+                                "'{}' is not a coordinate; usage: spawn <prefab> [x y]",
+                                arg
+                            ));
+                            return true;
+                        }
+                    }
+                }
+                let pos = match coords.len() {
+                    0 => cgmath::Vector2::new(
+                        world::PLAYFIELD_WIDTH / 2.0,
+                        world::PLAYFIELD_HEIGHT / 4.0,
+                    ),
+                    2 => cgmath::Vector2::new(coords[0], coords[1]),
+                    _ => {
+                        console.print("usage: spawn <prefab> [x y]");
+                        return true;
+                    }
+                };
+                if self.spawn_prefab(name, pos) {
+                    console.print(format!("Spawned '{}' at ({}, {})", name, pos.x, pos.y));
+                } else {
+                    console.print(format!("Unknown prefab '{}'", name));
+                }
+                true
+            }
+            "stage" => {
+                let path = command.args.first().map_or(STAGE_PATH, |p| p.as_str());
+                match self.load_stage(path) {
+                    Ok(()) => console.print(format!("Loaded {}", path)),
+                    Err(e) => console.print(format!("Unable to load {}: {}", path, e)),
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        render::render(&mut self.world, &mut self.batch, ctx, gl, glyphs);
+        self.particles.draw(ctx, gl);
+        if self.show_hitboxes {
+            render::render_hitboxes(&self.world, ctx, gl);
+        }
+
+        if let Some(banner) = self.stage.as_ref().and_then(|s| s.banner()) {
+            render::render_banner(&banner.text, ctx, gl, glyphs);
+        }
+
+        self.hud.draw(&self.hud_values(), ctx, gl, glyphs);
+    }
+}
@@ -0,0 +1,178 @@
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use game_time::GameTime;
+
+use render;
+use settings::SharedSettings;
+use profile::{self, SharedProfile};
+use difficulty::Difficulty;
+use stage::Stage;
+use ui::Ui;
+use input::{InputEvents, InputManager};
+use ecs::component::player::MAX_POWER;
+use state::{GameState, PlayState, Transition};
+use state::play::STAGE_PATH;
+
+const MENU_ORIGIN: [f64; 2] = [420.0, 140.0];
+/// Seconds the start time moves per step.
+const TIME_STEP: f64 = 5.0;
+const MAX_LIVES: f64 = 9.0;
+
+/// Where in the stage a practice run begins.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PracticeStart {
+    /// Seconds into the stage's timeline.
+    Time(f64),
+    /// The boss fight, starting at the given phase.
+    BossPhase(usize),
+}
+
+/// How a practice run differs from a normal one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PracticeOptions {
+    pub start: PracticeStart,
+    /// Lives held at this value for the whole run, if set.
+    pub lives: Option<u32>,
+    /// Shot power held at this value for the whole run, if set.
+    pub power: Option<f64>,
+    /// Ignore every hit on the player.
+    pub invincible: bool,
+}
+
+/// Picks a section of the stage and the practice options, then starts the
+/// run. Practice opens once the stage has been cleared, or always in debug
+/// builds.
+pub struct PracticeState {
+    settings: SharedSettings,
+    profile: SharedProfile,
+    stage: Option<Stage>,
+    unlocked: bool,
+    /// Index into the start list: the timeline, then each boss phase.
+    start: usize,
+    time: f64,
+    difficulty: usize,
+    fixed_stats: bool,
+    lives: f64,
+    power: f64,
+    invincible: bool,
+    ui: Ui,
+}
+
+impl PracticeState {
+    pub fn new(settings: SharedSettings, profile: SharedProfile) -> PracticeState {
+        let theme = settings.borrow().theme();
+        let stage = match Stage::load(STAGE_PATH) {
+            Ok(stage) => Some(stage),
+            Err(e) => {
+                println!("Unable to load {}: {}", STAGE_PATH, e);
+                None
+            }
+        };
+        let unlocked = cfg!(debug_assertions) ||
+            stage.as_ref().map_or(false, |s| {
+                profile.borrow().is_unlocked(&profile::practice_unlock(s.name()))
+            });
+
+        PracticeState {
+            settings,
+            profile,
+            stage,
+            unlocked,
+            start: 0,
+            time: 0.0,
+            difficulty: 1,
+            fixed_stats: true,
+            lives: 3.0,
+            power: MAX_POWER,
+            invincible: false,
+            ui: Ui::new(theme),
+        }
+    }
+
+    fn options(&self) -> PracticeOptions {
+        let start = if self.start == 0 {
+            PracticeStart::Time(self.time)
+        } else {
+            PracticeStart::BossPhase(self.start - 1)
+        };
+        PracticeOptions {
+            start,
+            lives: if self.fixed_stats {
+                Some(self.lives.round() as u32)
+            } else {
+                None
+            },
+            power: if self.fixed_stats {
+                Some(self.power)
+            } else {
+                None
+            },
+            invincible: self.invincible,
+        }
+    }
+}
+
+impl GameState for PracticeState {
+    fn handle_input(&mut self, events: &InputEvents, input: &InputManager) -> Transition {
+        let mut transition = Transition::None;
+
+        self.ui.set_theme(self.settings.borrow().theme());
+        self.ui.begin(events, input, MENU_ORIGIN);
+
+        let stage = match self.stage {
+            Some(ref stage) if self.unlocked => Some(stage),
+            _ => None,
+        };
+        match stage {
+            Some(stage) => {
+                let mut starts = vec!["Timeline".to_string()];
+                starts.extend(stage.boss_phases().into_iter().map(|p| format!("Boss: {}", p)));
+                let starts: Vec<&str> = starts.iter().map(|s| s.as_str()).collect();
+                self.ui.list("Start", &starts, &mut self.start);
+                if self.start == 0 {
+                    let label = format!("Start at {:.0}s", self.time);
+                    self.ui.slider(&label, &mut self.time, 0.0, stage.length(), TIME_STEP);
+                }
+
+                let names: Vec<_> = Difficulty::all().iter().map(|d| d.name()).collect();
+                self.ui.list("Difficulty", &names, &mut self.difficulty);
+                self.ui.toggle("Fixed lives and power", &mut self.fixed_stats);
+                if self.fixed_stats {
+                    let lives = format!("Lives {:.0}", self.lives);
+                    self.ui.slider(&lives, &mut self.lives, 1.0, MAX_LIVES, 1.0);
+                    let power = format!("Power {:.2}", self.power);
+                    self.ui.slider(&power, &mut self.power, 1.0, MAX_POWER, 0.25);
+                }
+                self.ui.toggle("Invincible", &mut self.invincible);
+
+                if self.ui.button("Start") {
+                    transition = Transition::Replace(Box::new(PlayState::practice(
+                        self.settings.clone(),
+                        self.profile.clone(),
+                        Difficulty::all()[self.difficulty],
+                        self.options(),
+                    )));
+                }
+            }
+            None => self.ui.label("Clear the stage to unlock practice"),
+        }
+
+        if self.ui.button("Back") || self.ui.back_pressed() {
+            transition = Transition::Pop;
+        }
+        self.ui.end();
+
+        transition
+    }
+
+    fn update(&mut self, _time: &GameTime) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        let theme = self.ui.theme().clone();
+        graphics::clear(theme.background, gl);
+        render::render_text("Practice", [420.0, 100.0], 36, theme.text, ctx, gl, glyphs);
+        self.ui.draw(ctx, gl, glyphs);
+    }
+}
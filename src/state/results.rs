@@ -0,0 +1,83 @@
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use glutin::VirtualKeyCode;
+use game_time::GameTime;
+
+use render;
+use score::Score;
+use settings::SharedSettings;
+use profile::SharedProfile;
+use input::{InputEvent, InputEvents, InputManager, Repeat};
+use state::{GameState, TitleState, Transition};
+
+/// Summary shown after a stage is cleared.
+pub struct ResultsState {
+    score: Score,
+    /// Place in the high score table, if the score made it.
+    rank: Option<usize>,
+    settings: SharedSettings,
+    profile: SharedProfile,
+}
+
+impl ResultsState {
+    pub fn new(
+        score: Score,
+        rank: Option<usize>,
+        settings: SharedSettings,
+        profile: SharedProfile,
+    ) -> ResultsState {
+        ResultsState {
+            score,
+            rank,
+            settings,
+            profile,
+        }
+    }
+}
+
+impl GameState for ResultsState {
+    fn handle_input(&mut self, events: &InputEvents, _input: &InputManager) -> Transition {
+        for event in events {
+            if let InputEvent::VirtKey(VirtualKeyCode::Return, _, Repeat::NoRepeat) = *event {
+                return Transition::Reset(Box::new(TitleState::new(
+                    self.settings.clone(),
+                    self.profile.clone(),
+                )));
+            }
+        }
+        Transition::None
+    }
+
+    fn update(&mut self, _time: &GameTime) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        let color = [0.1, 0.1, 0.1, 1.0];
+        let mut lines = vec![
+            format!("Score        {:010}", self.score.points),
+            format!("Graze        {}", self.score.graze),
+            format!("Point items  {}", self.score.point_items),
+        ];
+        if let Some(rank) = self.rank {
+            lines.push(rank_text(rank));
+        }
+
+        graphics::clear([0.8, 0.8, 0.8, 1.0], gl);
+        render::render_text("Stage Clear", [440.0, 240.0], 48, color, ctx, gl, glyphs);
+        for (i, line) in lines.iter().enumerate() {
+            let y = 320.0 + i as f64 * 36.0;
+            render::render_text(line, [440.0, y], 24, color, ctx, gl, glyphs);
+        }
+        render::render_text("Enter: continue", [440.0, 480.0], 20, color, ctx, gl, glyphs);
+    }
+}
+
+/// Describes a place in the high score table.
+pub fn rank_text(rank: usize) -> String {
+    if rank == 0 {
+        "New high score!".to_string()
+    } else {
+        format!("Rank         {}", rank + 1)
+    }
+}
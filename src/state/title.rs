@@ -0,0 +1,80 @@
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use game_time::GameTime;
+
+use render;
+use settings::SharedSettings;
+use profile::SharedProfile;
+use difficulty::Difficulty;
+use ui::Ui;
+use input::{InputEvents, InputManager};
+use state::{GameState, OptionsState, PlayState, PracticeState, Transition};
+
+const MENU_ORIGIN: [f64; 2] = [420.0, 360.0];
+
+pub struct TitleState {
+    settings: SharedSettings,
+    profile: SharedProfile,
+    /// Index into `Difficulty::all()`.
+    difficulty: usize,
+    ui: Ui,
+}
+
+impl TitleState {
+    pub fn new(settings: SharedSettings, profile: SharedProfile) -> TitleState {
+        let theme = settings.borrow().theme();
+        TitleState {
+            settings,
+            profile,
+            difficulty: 1,
+            ui: Ui::new(theme),
+        }
+    }
+}
+
+impl GameState for TitleState {
+    fn handle_input(&mut self, events: &InputEvents, input: &InputManager) -> Transition {
+        let mut transition = Transition::None;
+
+        self.ui.set_theme(self.settings.borrow().theme());
+        self.ui.begin(events, input, MENU_ORIGIN);
+        if self.ui.button("Start") {
+            transition = Transition::Push(Box::new(PlayState::new(
+                self.settings.clone(),
+                self.profile.clone(),
+                Difficulty::all()[self.difficulty],
+            )));
+        }
+        let names: Vec<_> = Difficulty::all().iter().map(|d| d.name()).collect();
+        self.ui.list("Difficulty", &names, &mut self.difficulty);
+        if self.ui.button("Practice") {
+            transition = Transition::Push(Box::new(PracticeState::new(
+                self.settings.clone(),
+                self.profile.clone(),
+            )));
+        }
+        if self.ui.button("Options") {
+            transition = Transition::Push(Box::new(OptionsState::new(
+                self.settings.clone(),
+                self.profile.clone(),
+            )));
+        }
+        if self.ui.button("Quit") || self.ui.back_pressed() {
+            transition = Transition::Quit;
+        }
+        self.ui.end();
+
+        transition
+    }
+
+    fn update(&mut self, _time: &GameTime) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        let theme = self.ui.theme().clone();
+        graphics::clear(theme.background, gl);
+        render::render_text("Bullet Game", [440.0, 300.0], 48, theme.text, ctx, gl, glyphs);
+        self.ui.draw(ctx, gl, glyphs);
+    }
+}
@@ -0,0 +1,342 @@
+use graphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
+use glutin::{MouseButton, VirtualKeyCode};
+
+use render;
+use input::{InputEvent, InputEvents, InputManager, Repeat};
+
+pub mod theme;
+
+pub use self::theme::Theme;
+
+/// Layout position of the slider bar within a widget row, as a fraction of
+/// the row width.
+const SLIDER_START: f64 = 0.5;
+const TEXT_PADDING: f64 = 10.0;
+
+#[derive(Debug, Clone, PartialEq)]
+enum DrawCmd {
+    Rect([f64; 4], [f32; 4]),
+    Text(String, [f64; 2], [f32; 4]),
+}
+
+/// Input for one frame, reduced to what widgets care about.
+#[derive(Debug, Clone, Default)]
+struct FrameInput {
+    up: bool,
+    down: bool,
+    left: bool,
+    right: bool,
+    activate: bool,
+    back: bool,
+    key: Option<VirtualKeyCode>,
+    mouse: [f64; 2],
+    mouse_moved: bool,
+    clicked: bool,
+}
+
+/// Immediate-mode widget set. Call `begin`, then the widget functions in
+/// the same order every frame, then `end`; `draw` renders the result.
+///
+/// Widgets are identified by the order they are declared in, and keyboard
+/// focus moves through them in that order.
+pub struct Ui {
+    theme: Theme,
+    focus: usize,
+    next_id: usize,
+    cursor: [f64; 2],
+    input: FrameInput,
+    capturing: Option<usize>,
+    commands: Vec<DrawCmd>,
+}
+
+impl Ui {
+    pub fn new(theme: Theme) -> Ui {
+        Ui {
+            theme,
+            focus: 0,
+            next_id: 0,
+            cursor: [0.0, 0.0],
+            input: FrameInput::default(),
+            capturing: None,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Whether the back key (Escape) was pressed this frame while no widget
+    /// was capturing input.
+    pub fn back_pressed(&self) -> bool {
+        self.input.back
+    }
+
+    /// Starts a frame, laying widgets out downwards from `origin`.
+    pub fn begin(&mut self, events: &InputEvents, input: &InputManager, origin: [f64; 2]) {
+        let mouse = input.mouse_position();
+        let mut frame = FrameInput {
+            mouse,
+            mouse_moved: mouse != self.input.mouse,
+            ..FrameInput::default()
+        };
+
+        for event in events {
+            match *event {
+                InputEvent::VirtKey(key, _, ref repeat) => {
+                    let pressed = *repeat == Repeat::NoRepeat;
+                    if self.capturing.is_some() {
+                        if pressed {
+                            frame.key = Some(key);
+                        }
+                        continue;
+                    }
+                    if let Repeat::EarlyRepeat(_) = *repeat {
+                        continue;
+                    }
+
+                    match key {
+                        VirtualKeyCode::Up => frame.up = true,
+                        VirtualKeyCode::Down => frame.down = true,
+                        VirtualKeyCode::Left => frame.left = true,
+                        VirtualKeyCode::Right => frame.right = true,
+                        VirtualKeyCode::Return | VirtualKeyCode::Space => frame.activate |= pressed,
+                        VirtualKeyCode::Escape => frame.back |= pressed,
+                        _ => (),
+                    }
+                }
+                InputEvent::MouseDown(MouseButton::Left, pos) => {
+                    frame.mouse = pos;
+                    frame.clicked = self.capturing.is_none();
+                }
+                _ => (),
+            }
+        }
+
+        self.input = frame;
+        self.next_id = 0;
+        self.cursor = origin;
+        self.commands.clear();
+    }
+
+    /// Finishes the frame, moving keyboard focus for the next one.
+    pub fn end(&mut self) {
+        let count = self.next_id;
+        if count == 0 {
+            self.focus = 0;
+            return;
+        }
+
+        if self.input.up {
+            self.focus = (self.focus + count - 1) % count;
+        }
+        if self.input.down {
+            self.focus = (self.focus + 1) % count;
+        }
+        self.focus = self.focus.min(count - 1);
+    }
+
+    /// Non-interactive text taking up a row.
+    pub fn label(&mut self, text: &str) {
+        let rect = self.next_rect();
+        let color = self.theme.text;
+        self.text(text, rect, color);
+    }
+
+    pub fn button(&mut self, label: &str) -> bool {
+        let (rect, focused, activated) = self.widget();
+        self.frame(rect, focused);
+        let color = self.theme.text;
+        self.text(label, rect, color);
+        activated
+    }
+
+    pub fn toggle(&mut self, label: &str, value: &mut bool) -> bool {
+        let (rect, focused, activated) = self.widget();
+        let changed = activated || (focused && (self.input.left || self.input.right));
+        if changed {
+            *value = !*value;
+        }
+
+        self.frame(rect, focused);
+        let color = self.theme.text;
+        self.text(label, rect, color);
+        self.value_text(if *value { "On" } else { "Off" }, rect);
+        changed
+    }
+
+    pub fn slider(&mut self, label: &str, value: &mut f64, min: f64, max: f64, step: f64) -> bool {
+        let (rect, focused, _) = self.widget();
+        let old = *value;
+        let bar = [
+            rect[0] + rect[2] * SLIDER_START,
+            rect[1] + rect[3] / 2.0 - 3.0,
+            rect[2] * (1.0 - SLIDER_START) - TEXT_PADDING,
+            6.0,
+        ];
+
+        if focused && self.input.left {
+            *value -= step;
+        }
+        if focused && self.input.right {
+            *value += step;
+        }
+        if self.input.clicked && contains(rect, self.input.mouse) &&
+            self.input.mouse[0] >= bar[0]
+        {
+            *value = min + (max - min) * (self.input.mouse[0] - bar[0]) / bar[2];
+        }
+        *value = value.max(min).min(max);
+
+        self.frame(rect, focused);
+        let color = self.theme.text;
+        self.text(label, rect, color);
+        let fraction = if max > min { (*value - min) / (max - min) } else { 0.0 };
+        self.commands.push(DrawCmd::Rect(bar, self.theme.background));
+        self.commands.push(DrawCmd::Rect(
+            [bar[0], bar[1], bar[2] * fraction, bar[3]],
+            self.theme.accent,
+        ));
+
+        *value != old
+    }
+
+    /// Cycles through `items` with left/right or by activating it.
+    pub fn list(&mut self, label: &str, items: &[&str], selected: &mut usize) -> bool {
+        let (rect, focused, activated) = self.widget();
+        let old = *selected;
+
+        if !items.is_empty() {
+            if (focused && self.input.right) || activated {
+                *selected = (*selected + 1) % items.len();
+            }
+            if focused && self.input.left {
+                *selected = (*selected + items.len() - 1) % items.len();
+            }
+            *selected = (*selected).min(items.len() - 1);
+        }
+
+        self.frame(rect, focused);
+        let color = self.theme.text;
+        self.text(label, rect, color);
+        if let Some(item) = items.get(*selected) {
+            self.value_text(&format!("< {} >", item), rect);
+        }
+
+        *selected != old
+    }
+
+    /// Activating the field waits for the next key press and assigns it.
+    /// Escape cancels.
+    pub fn key_binding(&mut self, label: &str, key: &mut VirtualKeyCode) -> bool {
+        let id = self.next_id;
+        let (rect, focused, activated) = self.widget();
+        let mut changed = false;
+
+        if self.capturing == Some(id) {
+            if let Some(pressed) = self.input.key {
+                if pressed != VirtualKeyCode::Escape {
+                    changed = *key != pressed;
+                    *key = pressed;
+                }
+                self.capturing = None;
+            }
+        } else if activated {
+            self.capturing = Some(id);
+        }
+
+        self.frame(rect, focused);
+        let color = self.theme.text;
+        self.text(label, rect, color);
+        if self.capturing == Some(id) {
+            self.value_text("press a key...", rect);
+        } else {
+            self.value_text(&format!("{:?}", key), rect);
+        }
+
+        changed
+    }
+
+    pub fn draw(&self, ctx: &graphics::Context, gl: &mut GlGraphics, glyphs: &mut GlyphCache) {
+        for cmd in &self.commands {
+            match *cmd {
+                DrawCmd::Rect(rect, color) => {
+                    graphics::Rectangle::new(color).draw(
+                        rect,
+                        &ctx.draw_state,
+                        ctx.transform,
+                        gl,
+                    );
+                }
+                DrawCmd::Text(ref text, pos, color) => {
+                    render::render_text(text, pos, self.theme.font_size, color, ctx, gl, glyphs);
+                }
+            }
+        }
+    }
+
+    fn next_rect(&mut self) -> [f64; 4] {
+        let rect = [
+            self.cursor[0],
+            self.cursor[1],
+            self.theme.width,
+            self.theme.row_height,
+        ];
+        self.cursor[1] += self.theme.row_height + self.theme.spacing;
+        rect
+    }
+
+    /// Allocates an interactive row, returning its rect, whether it has
+    /// focus and whether it was activated this frame.
+    fn widget(&mut self) -> ([f64; 4], bool, bool) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let rect = self.next_rect();
+
+        let hovered = contains(rect, self.input.mouse);
+        if hovered && (self.input.mouse_moved || self.input.clicked) {
+            self.focus = id;
+        }
+
+        let focused = self.focus == id;
+        let activated = (focused && self.input.activate) || (hovered && self.input.clicked);
+        (rect, focused, activated)
+    }
+
+    fn frame(&mut self, rect: [f64; 4], focused: bool) {
+        let color = if focused {
+            self.theme.focused
+        } else {
+            self.theme.widget
+        };
+        self.commands.push(DrawCmd::Rect(rect, color));
+    }
+
+    fn text(&mut self, text: &str, rect: [f64; 4], color: [f32; 4]) {
+        let baseline = rect[1] + (rect[3] + self.theme.font_size as f64 * 0.7) / 2.0;
+        self.commands.push(DrawCmd::Text(
+            text.to_string(),
+            [rect[0] + TEXT_PADDING, baseline],
+            color,
+        ));
+    }
+
+    fn value_text(&mut self, text: &str, rect: [f64; 4]) {
+        let baseline = rect[1] + (rect[3] + self.theme.font_size as f64 * 0.7) / 2.0;
+        let color = self.theme.accent;
+        self.commands.push(DrawCmd::Text(
+            text.to_string(),
+            [rect[0] + rect[2] * SLIDER_START, baseline],
+            color,
+        ));
+    }
+}
+
+fn contains(rect: [f64; 4], point: [f64; 2]) -> bool {
+    point[0] >= rect[0] && point[0] < rect[0] + rect[2] && point[1] >= rect[1] &&
+        point[1] < rect[1] + rect[3]
+}
@@ -0,0 +1,46 @@
+/// Colors and metrics used to draw widgets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub background: [f32; 4],
+    pub widget: [f32; 4],
+    pub focused: [f32; 4],
+    pub accent: [f32; 4],
+    pub text: [f32; 4],
+    pub font_size: u32,
+    pub row_height: f64,
+    pub width: f64,
+    pub spacing: f64,
+}
+
+impl Theme {
+    pub fn light() -> Theme {
+        Theme {
+            background: [0.8, 0.8, 0.8, 1.0],
+            widget: [0.7, 0.7, 0.72, 1.0],
+            focused: [0.55, 0.6, 0.8, 1.0],
+            accent: [0.2, 0.3, 0.7, 1.0],
+            text: [0.1, 0.1, 0.1, 1.0],
+            font_size: 20,
+            row_height: 36.0,
+            width: 360.0,
+            spacing: 8.0,
+        }
+    }
+
+    pub fn dark() -> Theme {
+        Theme {
+            background: [0.08, 0.08, 0.1, 1.0],
+            widget: [0.2, 0.2, 0.24, 1.0],
+            focused: [0.3, 0.3, 0.5, 1.0],
+            accent: [0.9, 0.6, 0.2, 1.0],
+            text: [0.95, 0.95, 0.95, 1.0],
+            ..Theme::light()
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::light()
+    }
+}
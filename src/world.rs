@@ -1,4 +1,8 @@
 use specs;
+use cgmath::Vector2;
+
+pub const PLAYFIELD_WIDTH: f64 = 1200.0;
+pub const PLAYFIELD_HEIGHT: f64 = 800.0;
 
 pub struct World {
     components: specs::World,
@@ -16,3 +20,8 @@ impl World {
         &mut self.components
     }
 }
+
+pub fn in_playfield(pos: &Vector2<f64>, margin: f64) -> bool {
+    pos.x >= -margin && pos.x <= PLAYFIELD_WIDTH + margin && pos.y >= -margin &&
+        pos.y <= PLAYFIELD_HEIGHT + margin
+}
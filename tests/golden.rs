@@ -0,0 +1,91 @@
+extern crate bullet_game;
+extern crate cgmath;
+extern crate graphics;
+extern crate specs;
+
+use cgmath::Vector2;
+
+use bullet_game::background::{Background, BackgroundData, LayerData, LayerKind};
+use bullet_game::ecs::component;
+use bullet_game::ecs::component::bullet::{Bullet, Faction};
+use bullet_game::render::{self, BlendMode, RectangleGraphic, RenderGraphicState};
+use bullet_game::render::golden;
+use bullet_game::world::World;
+
+const WIDTH: u32 = 120;
+const HEIGHT: u32 = 80;
+/// Allows for rounding differences in the blend arithmetic.
+const TOLERANCE: u8 = 1;
+
+fn rectangle(color: [f32; 4], size: f64) -> RenderGraphicState {
+    RenderGraphicState::Rectangle(RectangleGraphic {
+        rect: graphics::Rectangle::new(color),
+        width: size,
+        height: size,
+    })
+}
+
+/// A tiled background, two overlapping ships and an additive bullet.
+fn scene() -> World {
+    let mut specs = specs::World::new();
+    component::register_components(&mut specs);
+    render::register_resources(&mut specs);
+    specs.add_resource(Background::new(&BackgroundData {
+        color: [0.1, 0.1, 0.2, 1.0],
+        layers: vec![
+            LayerData {
+                velocity: [0.0, 0.0],
+                kind: LayerKind::Tiles {
+                    size: 200.0,
+                    colors: [[0.1, 0.1, 0.2, 1.0], [0.2, 0.2, 0.3, 1.0]],
+                },
+            },
+        ],
+    }));
+
+    specs
+        .create_entity()
+        .with(component::pos::Position(Vector2::new(600.0, 400.0)))
+        .with(component::render::Render::new(rectangle([1.0, 0.0, 1.0, 1.0], 100.0)))
+        .build();
+    specs
+        .create_entity()
+        .with(component::pos::Position(Vector2::new(650.0, 450.0)))
+        .with(component::render::Render::new(rectangle([1.0, 1.0, 0.0, 0.5], 80.0)))
+        .build();
+    specs
+        .create_entity()
+        .with(component::pos::Position(Vector2::new(600.0, 250.0)))
+        .with(
+            component::render::Render::new(rectangle([0.2, 0.6, 1.0, 0.8], 60.0))
+                .with_blend(BlendMode::Additive),
+        )
+        .with(Bullet::new(Faction::Enemy))
+        .build();
+
+    World::new(specs)
+}
+
+#[test]
+fn offscreen_scene_matches_golden() {
+    let image = render::render_offscreen(&scene(), WIDTH, HEIGHT);
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/scene.png");
+    if let Err(e) = golden::check_golden(&image, path, TOLERANCE) {
+        panic!("{}", e);
+    }
+}
+
+#[test]
+fn diff_counts_pixels_beyond_tolerance() {
+    let image = render::render_offscreen(&scene(), WIDTH, HEIGHT);
+    let mut changed = image.clone();
+    changed.get_pixel_mut(0, 0).data[0] ^= 0xff;
+    changed.get_pixel_mut(5, 5).data[1] ^= 1;
+
+    assert_eq!(golden::diff_pixels(&image, &image, 0), Some(0));
+    assert_eq!(golden::diff_pixels(&changed, &image, 0), Some(2));
+    assert_eq!(golden::diff_pixels(&changed, &image, 1), Some(1));
+
+    let smaller = render::render_offscreen(&scene(), WIDTH / 2, HEIGHT / 2);
+    assert_eq!(golden::diff_pixels(&smaller, &image, 0), None);
+}